@@ -0,0 +1,79 @@
+//! Parsing the custom colors for every palette, like `Palette::generate` used to, against
+//! reusing ones parsed at startup. The old way isn't in the crate anymore, so both sides use
+//! material-colors directly, the same way the service does.
+
+use std::{hint::black_box, str::FromStr};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use material_colors::{
+    color::Argb,
+    theme::{CustomColor, ThemeBuilder},
+};
+
+/// The defaults from the config: name, hex value, blend.
+const CUSTOM_COLORS: &[(&str, &str, bool)] = &[
+    ("red", "FF7676", true),
+    ("green", "59EB5C", true),
+    ("blue", "0000FF", true),
+    ("yellow", "F8F770", false),
+    ("purple", "BA64F2", true),
+    ("cyan", "61D1FA", true),
+    ("orange", "E69E50", false),
+];
+
+fn parse_every_time() -> Vec<CustomColor> {
+    CUSTOM_COLORS
+        .iter()
+        .map(|(name, value, blend)| CustomColor {
+            value: Argb::from_str(value).unwrap(),
+            name: name.to_string(),
+            blend: *blend,
+        })
+        .collect()
+}
+
+fn from_parsed(parsed: &[(String, Argb, bool)]) -> Vec<CustomColor> {
+    parsed
+        .iter()
+        .map(|(name, value, blend)| CustomColor {
+            value: *value,
+            name: name.clone(),
+            blend: *blend,
+        })
+        .collect()
+}
+
+fn custom_colors(c: &mut Criterion) {
+    let parsed: Vec<_> = CUSTOM_COLORS
+        .iter()
+        .map(|(name, value, blend)| (name.to_string(), Argb::from_str(value).unwrap(), *blend))
+        .collect();
+    let source = Argb::from_str("59EB5C").unwrap();
+
+    let mut group = c.benchmark_group("custom colors");
+    group.bench_function("parsed per palette", |b| b.iter(parse_every_time));
+    group.bench_function("parsed once", |b| {
+        b.iter(|| from_parsed(black_box(&parsed)))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("whole theme");
+    group.bench_function("parsed per palette", |b| {
+        b.iter(|| {
+            ThemeBuilder::with_source(black_box(source))
+                .custom_colors(parse_every_time())
+                .build()
+        })
+    });
+    group.bench_function("parsed once", |b| {
+        b.iter(|| {
+            ThemeBuilder::with_source(black_box(source))
+                .custom_colors(from_parsed(&parsed))
+                .build()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, custom_colors);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//! Build info for `/version`: the commit, when it was built and which material-colors it got.
+
+use std::{
+    env, fs,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    // HEAD only changes when switching branches, a commit moves the branch it points to
+    if let Some(branch) = fs::read_to_string(".git/HEAD")
+        .ok()
+        .and_then(|head| Some(head.strip_prefix("ref: ")?.trim().to_string()))
+    {
+        println!("cargo:rerun-if-changed=.git/{}", branch);
+    }
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // Shuttle and Docker builds don't get the .git directory, they can pass the commit instead
+    let commit = env::var("GIT_COMMIT").ok().or_else(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    println!(
+        "cargo:rustc-env=BUILD_GIT_COMMIT={}",
+        commit.as_deref().unwrap_or("unknown")
+    );
+
+    // Reproducible builds set this, so rebuilding the same commit gives the same binary
+    let built_at = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("the clock is after 1970")
+                .as_secs()
+        });
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", built_at);
+
+    println!(
+        "cargo:rustc-env=BUILD_MATERIAL_COLORS_VERSION={}",
+        locked_version("material-colors")
+            .as_deref()
+            .unwrap_or("unknown")
+    );
+}
+
+/// The version of a dependency in Cargo.lock, which is the one that gets built.
+fn locked_version(name: &str) -> Option<String> {
+    let lock = fs::read_to_string("Cargo.lock").ok()?;
+    let mut lines = lock.lines();
+    lines.find(|line| *line == format!("name = \"{}\"", name))?;
+    let version = lines.next()?.strip_prefix("version = \"")?;
+
+    Some(version.trim_end_matches('"').to_string())
+}
@@ -0,0 +1,7 @@
+CREATE TABLE palettes (
+    id UUID PRIMARY KEY,
+    base_color TEXT NOT NULL,
+    theme_type TEXT NOT NULL,
+    palette TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
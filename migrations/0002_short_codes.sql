@@ -0,0 +1,7 @@
+ALTER TABLE palettes ADD COLUMN short_code TEXT UNIQUE;
+UPDATE palettes SET short_code = substr(md5(id::text), 1, 8);
+ALTER TABLE palettes ALTER COLUMN short_code SET NOT NULL;
+
+-- Needed to turn the raw palette back into other formats. Palettes stored before this get
+-- numbered names, since the names of the custom colors back then aren't known.
+ALTER TABLE palettes ADD COLUMN color_names TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,10 @@
+CREATE TABLE palette_history (
+    id BIGSERIAL PRIMARY KEY,
+    api_key TEXT NOT NULL,
+    base_color TEXT NOT NULL,
+    theme_type TEXT NOT NULL,
+    palette TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX palette_history_api_key ON palette_history (api_key, created_at DESC);
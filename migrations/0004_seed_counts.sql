@@ -0,0 +1,8 @@
+-- Only the color and theme type, nothing about who asked for it
+CREATE TABLE seed_counts (
+    day DATE NOT NULL DEFAULT CURRENT_DATE,
+    base_color TEXT NOT NULL,
+    theme_type TEXT NOT NULL,
+    count BIGINT NOT NULL DEFAULT 1,
+    PRIMARY KEY (day, base_color, theme_type)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE usage_counts (
+    day DATE NOT NULL DEFAULT CURRENT_DATE,
+    endpoint TEXT NOT NULL,
+    format TEXT NOT NULL,
+    requests BIGINT NOT NULL DEFAULT 0,
+    client_errors BIGINT NOT NULL DEFAULT 0,
+    server_errors BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, endpoint, format)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE favorites (
+    api_key TEXT NOT NULL,
+    palette_id UUID NOT NULL REFERENCES palettes (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (api_key, palette_id)
+);
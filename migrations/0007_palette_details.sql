@@ -0,0 +1,8 @@
+ALTER TABLE palettes
+    ADD COLUMN name TEXT,
+    ADD COLUMN description TEXT,
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}',
+    -- Name of the API key that stored it, which is the only one allowed to change it
+    ADD COLUMN owner TEXT;
+
+CREATE INDEX palettes_tags ON palettes USING GIN (tags);
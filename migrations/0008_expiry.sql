@@ -0,0 +1,10 @@
+ALTER TABLE palettes ADD COLUMN expires_at TIMESTAMPTZ;
+
+CREATE INDEX palettes_expires_at ON palettes (expires_at) WHERE expires_at IS NOT NULL;
+
+-- What's left of expired palettes once they're cleaned up, so they can still answer with a 410
+CREATE TABLE expired_palettes (
+    id UUID PRIMARY KEY,
+    short_code TEXT NOT NULL UNIQUE,
+    expired_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE key_usage (
+    day DATE NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')::DATE,
+    api_key TEXT NOT NULL,
+    requests BIGINT NOT NULL DEFAULT 1,
+    PRIMARY KEY (day, api_key)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE webhooks (
+    api_key TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    -- For signing the payloads, so receivers can tell they came from us
+    secret TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
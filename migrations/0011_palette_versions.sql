@@ -0,0 +1,16 @@
+ALTER TABLE palettes ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE palettes ADD COLUMN regenerated_at TIMESTAMPTZ;
+
+-- Earlier colors of regenerated palettes, for worlds pinned to a version. The current one
+-- is only in palettes.
+CREATE TABLE palette_versions (
+    palette_id UUID NOT NULL REFERENCES palettes (id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    base_color TEXT NOT NULL,
+    theme_type TEXT NOT NULL,
+    palette TEXT NOT NULL,
+    color_names TEXT[] NOT NULL,
+    -- When this version was generated
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (palette_id, version)
+);
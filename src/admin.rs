@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth,
+    cache::{self, CacheStats, Flushed},
+    storage::{Storage, UsageCount, UsageGroup},
+    AppError, AppState,
+};
+
+/// Operator-only endpoints, behind the admin token.
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/admin/loglevel", get(get_log_level).put(set_log_level))
+        .route("/admin/usage", get(usage))
+        .route("/admin/cache/stats", get(cache_stats))
+        .route("/admin/cache/flush", post(flush_cache))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_admin,
+        ))
+        .with_state(state)
+}
+
+/// The current log filter, in `RUST_LOG` syntax.
+#[utoipa::path(
+    get,
+    path = "/admin/loglevel",
+    security(("admin_token" = [])),
+    responses((status = 200, description = "The current filter", body = String))
+)]
+pub async fn get_log_level(State(state): State<AppState>) -> impl IntoResponse {
+    state
+        .log_level
+        .with_current(|filter| filter.to_string())
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Replaces the log filter, e.g. with `debug` or `info,resomaterialyou=trace`.
+/// This doesn't survive a restart, which is kind of the point.
+#[utoipa::path(
+    put,
+    path = "/admin/loglevel",
+    security(("admin_token" = [])),
+    request_body(content = String, content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The new filter", body = String),
+        (status = 400, description = "The filter couldn't be parsed", body = String)
+    )
+)]
+pub async fn set_log_level(State(state): State<AppState>, body: String) -> impl IntoResponse {
+    let filter = match EnvFilter::try_new(body.trim()) {
+        Ok(filter) => filter,
+        Err(err) => {
+            return (StatusCode::BAD_REQUEST, format!("Invalid filter: {}", err)).into_response()
+        }
+    };
+    let new_filter = filter.to_string();
+
+    if let Err(err) = state.log_level.reload(filter) {
+        warn!("Failed to change the log filter: {}", err);
+        return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+    }
+
+    info!("Log filter changed to {:?}", new_filter);
+    new_filter.into_response()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsageQuery {
+    /// How far back to look, defaults to 30
+    days: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Usage {
+    days: i32,
+    per_day: Vec<UsageCount>,
+    per_endpoint: Vec<UsageCount>,
+    per_format: Vec<UsageCount>,
+}
+
+/// Request counts (and how many of them were errors) per day, endpoint and `format`.
+#[utoipa::path(
+    get,
+    path = "/admin/usage",
+    params(UsageQuery),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Usage summary", body = Usage),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn usage(
+    storage: Storage,
+    Query(query): Query<UsageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let days = query.days.unwrap_or(30).max(1);
+
+    Ok(Json(Usage {
+        days,
+        per_day: storage.usage(days, UsageGroup::Day).await?,
+        per_endpoint: storage.usage(days, UsageGroup::Endpoint).await?,
+        per_format: storage.usage(days, UsageGroup::Format).await?,
+    }))
+}
+
+/// How full this instance's caches are, and their hit rates since it started.
+#[utoipa::path(
+    get,
+    path = "/admin/cache/stats",
+    security(("admin_token" = [])),
+    responses((status = 200, description = "Cache statistics", body = CacheStats))
+)]
+pub async fn cache_stats(State(state): State<AppState>) -> Json<CacheStats> {
+    Json(cache::stats(&state))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FlushQuery {
+    /// Also delete every palette in Redis, which all instances share. True by default
+    redis: Option<bool>,
+}
+
+/// Throws away cached palettes, like ones generated before a fix, so they get generated
+/// again. Other instances keep the ones they have in memory until they're flushed too.
+#[utoipa::path(
+    post,
+    path = "/admin/cache/flush",
+    params(FlushQuery),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "How many palettes got thrown away", body = Flushed),
+        (status = 500, description = "Redis couldn't be flushed", body = String)
+    )
+)]
+pub async fn flush_cache(
+    State(state): State<AppState>,
+    Query(query): Query<FlushQuery>,
+) -> Result<Json<Flushed>, AppError> {
+    Ok(Json(
+        cache::flush(&state, query.redis.unwrap_or(true)).await?,
+    ))
+}
@@ -0,0 +1,138 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+use crate::AppState;
+
+/// Only lets requests through with `Authorization: Bearer <admin_token>`.
+/// Without an `admin_token` in the config, the admin endpoints are off entirely.
+pub async fn require_admin(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(admin_token) = state.config.admin_token.as_deref() else {
+        return (
+            StatusCode::FORBIDDEN,
+            "Admin endpoints are disabled, set admin_token to enable them",
+        )
+            .into_response();
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // Comparing digests keeps the comparison from leaking how much of the token was right
+    match provided {
+        Some(token) if Sha256::digest(token) == Sha256::digest(admin_token) => next.run(req).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Missing or wrong admin token",
+        )
+            .into_response(),
+    }
+}
+
+static API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+/// The caller, identified by one of the `api_keys` from the config.
+///
+/// The key can go in an `X-Api-Key` header, or in an `api_key` query parameter for clients that
+/// can't set headers (like Resonite's GET nodes).
+pub struct ApiKey {
+    /// The key's name from the config, which is what gets stored instead of the key itself
+    pub name: String,
+}
+
+/// Like [`ApiKey`], but for endpoints that also work without one. A key that's wrong still
+/// gets rejected, so a typo doesn't silently turn the per-key features off.
+pub struct MaybeApiKey(pub Option<ApiKey>);
+
+fn provided_key(parts: &Parts) -> Option<String> {
+    if let Some(key) = parts.headers.get(&API_KEY) {
+        return key.to_str().ok().map(str::to_owned);
+    }
+
+    // Decoded, so keys with characters that have to be escaped in URLs work too
+    parts.uri.query().and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find_map(|(name, value)| (name == "api_key").then(|| value.into_owned()))
+    })
+}
+
+fn unknown_key() -> Response {
+    (StatusCode::UNAUTHORIZED, "Unknown API key").into_response()
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for MaybeApiKey {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(provided) = provided_key(parts) else {
+            return Ok(Self(None));
+        };
+
+        let provided = Sha256::digest(&provided);
+        state
+            .config
+            .api_keys
+            .iter()
+            .find(|key| Sha256::digest(&key.key) == provided)
+            .map(|key| {
+                Self(Some(ApiKey {
+                    name: key.name.clone(),
+                }))
+            })
+            .ok_or_else(unknown_key)
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiKey {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        match MaybeApiKey::from_request_parts(parts, state).await? {
+            MaybeApiKey(Some(key)) => Ok(key),
+            MaybeApiKey(None) => Err((
+                StatusCode::UNAUTHORIZED,
+                "This needs an API key, in an X-Api-Key header or the api_key parameter",
+            )
+                .into_response()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    fn key_in(uri: &str) -> Option<String> {
+        let (parts, ()) = Request::get(uri).body(()).unwrap().into_parts();
+        provided_key(&parts)
+    }
+
+    #[test]
+    fn query_keys_get_decoded() {
+        assert_eq!(key_in("/v1/getPalette?api_key=abc"), Some("abc".to_owned()));
+        assert_eq!(
+            key_in("/v1/getPalette?base_color=fff&api_key=a%2Bb%26c%3D%3D"),
+            Some("a+b&c==".to_owned())
+        );
+        assert_eq!(key_in("/v1/getPalette?base_color=fff"), None);
+    }
+}
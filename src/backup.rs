@@ -0,0 +1,345 @@
+use axum::{
+    body::Body,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    auth::ApiKey,
+    input,
+    palette::ThemeType,
+    palettes::check_details,
+    storage::{PaletteDetails, PaletteVersion, Storage, StoredPalette},
+    AppError, AppState,
+};
+
+/// Bumped whenever the export format changes in a way older versions can't read.
+const EXPORT_VERSION: u32 = 1;
+/// Bigger libraries can be imported in parts
+const MAX_IMPORT: usize = 1000;
+
+/// Everything stored with an API key, in one file.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Export {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub palettes: Vec<ExportedPalette>,
+}
+
+/// A stored palette with everything needed to store it again somewhere else.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ExportedPalette {
+    pub id: Uuid,
+    pub short_code: String,
+    pub base_color: String,
+    pub theme_type: ThemeType,
+    /// Same format as `/v1/getPalette`
+    pub palette: String,
+    /// What each color in `palette` is, in order
+    pub color_names: Vec<String>,
+    #[serde(flatten)]
+    pub details: PaletteDetails,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The version the colors above are, 1 in exports from before palettes had versions
+    #[serde(default = "first_version")]
+    pub version: i32,
+    #[serde(default)]
+    pub regenerated_at: Option<DateTime<Utc>>,
+    /// Every version before `version`, oldest first, so links pinned to one keep working
+    #[serde(default)]
+    pub versions: Vec<ExportedVersion>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An earlier version of an exported palette.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ExportedVersion {
+    pub version: i32,
+    pub base_color: String,
+    pub theme_type: ThemeType,
+    pub palette: String,
+    pub color_names: Vec<String>,
+    /// When this version was generated
+    pub created_at: DateTime<Utc>,
+}
+
+fn first_version() -> i32 {
+    1
+}
+
+impl ExportedPalette {
+    fn new(stored: StoredPalette, versions: Vec<PaletteVersion>) -> anyhow::Result<Self> {
+        let versions = versions
+            .into_iter()
+            .map(|version| {
+                Ok(ExportedVersion {
+                    version: version.version,
+                    base_color: version.base_color,
+                    theme_type: version.theme_type.parse()?,
+                    palette: version.palette,
+                    color_names: version.color_names,
+                    created_at: version.created_at,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            id: stored.id,
+            short_code: stored.short_code,
+            base_color: stored.base_color,
+            theme_type: stored.theme_type,
+            palette: stored.palette,
+            color_names: stored.color_names,
+            details: stored.details,
+            expires_at: stored.expires_at,
+            version: stored.version,
+            regenerated_at: stored.regenerated_at,
+            versions,
+            created_at: stored.created_at,
+        })
+    }
+}
+
+impl ExportedPalette {
+    /// Turns it back into a palette owned by `owner` and its earlier versions, if everything
+    /// in it makes sense.
+    fn into_stored(self, owner: &str) -> Result<(StoredPalette, Vec<PaletteVersion>), String> {
+        let invalid = |what: &str| format!("Palette {} has an invalid {}", self.id, what);
+
+        let base_color = input::parse_color(&self.base_color).map_err(|_| invalid("base_color"))?;
+        let short_code_valid = (1..=32).contains(&self.short_code.len())
+            && self.short_code.chars().all(|c| c.is_ascii_alphanumeric());
+        if !short_code_valid {
+            return Err(invalid("short_code"));
+        }
+        let mut details = self.details;
+        let tags = std::mem::take(&mut details.tags);
+        details.tags = check_details(&details.name, &details.description, Some(tags))
+            .map_err(|message| format!("Palette {}: {}", self.id, message))?
+            .unwrap_or_default();
+
+        // Versions count up from 1 to the current one, with none missing
+        let numbered = (1..).zip(&self.versions).all(|(n, v)| v.version == n);
+        if self.version < 1 || !numbered || self.versions.len() as i32 != self.version - 1 {
+            return Err(invalid("version"));
+        }
+        let mut versions = Vec::with_capacity(self.versions.len());
+        for version in self.versions {
+            let base_color =
+                input::parse_color(&version.base_color).map_err(|_| invalid("base_color"))?;
+            let version = PaletteVersion {
+                version: version.version,
+                base_color: base_color.to_hex(),
+                theme_type: version.theme_type.as_str().to_owned(),
+                palette: version.palette,
+                color_names: version.color_names,
+                created_at: version.created_at,
+            };
+            version.to_palette().map_err(|_| {
+                format!(
+                    "Version {} of palette {} has colors that don't match its color_names",
+                    version.version, self.id
+                )
+            })?;
+            versions.push(version);
+        }
+
+        let stored = StoredPalette {
+            id: self.id,
+            short_code: self.short_code,
+            base_color: base_color.to_hex(),
+            theme_type: self.theme_type,
+            palette: self.palette,
+            color_names: self.color_names,
+            details,
+            owner: Some(owner.to_owned()),
+            expires_at: self.expires_at,
+            version: self.version,
+            regenerated_at: self.regenerated_at,
+            created_at: self.created_at,
+        };
+        stored.to_palette().map_err(|_| {
+            format!(
+                "Palette {} has colors that don't match its color_names",
+                stored.id
+            )
+        })?;
+
+        Ok((stored, versions))
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportResult {
+    /// IDs of the palettes that got stored
+    pub imported: Vec<Uuid>,
+    pub skipped: Vec<SkippedPalette>,
+}
+
+/// A palette from the import that didn't get stored.
+#[derive(Serialize, ToSchema)]
+pub struct SkippedPalette {
+    pub id: Uuid,
+    /// `expired`, or `taken` when its ID or short code is already used here
+    #[schema(example = "taken")]
+    pub reason: &'static str,
+}
+
+/// Backups of the caller's palettes, which go in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/me/palettes/export", get(export))
+        .route("/me/palettes/import", post(import))
+}
+
+/// Every palette stored with this API key that hasn't expired, oldest first, as a download.
+#[utoipa::path(
+    get,
+    path = "/v1/me/palettes/export",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The key's palettes", body = Export),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn export(key: ApiKey, storage: Storage) -> Result<Response, AppError> {
+    let mut palettes = storage.owned_palettes(&key.name);
+    // Errors before the first palette (like the database being down) still get a 500, later
+    // ones can only cut the download short
+    let first = palettes.recv().await.transpose()?;
+    let exported_at = Utc::now();
+
+    // Written out like an `Export`, a palette at a time
+    let empty = serde_json::to_string(&Export {
+        version: EXPORT_VERSION,
+        exported_at,
+        palettes: Vec::new(),
+    })?;
+    let mut opening = empty
+        .strip_suffix("[]}")
+        .expect("the palettes come last in an export")
+        .to_owned();
+    opening.push('[');
+    if let Some((first, versions)) = first {
+        opening += &palette_chunk(first, versions, true)?;
+    }
+    let rest = ReceiverStream::new(palettes).map(|palette| {
+        let (palette, versions) = palette?;
+        palette_chunk(palette, versions, false)
+    });
+    let body = tokio_stream::once(Ok(opening))
+        .chain(rest)
+        .chain(tokio_stream::once(Ok("]}".to_string())));
+
+    let disposition = format!(
+        "attachment; filename=\"palettes-{}.json\"",
+        exported_at.format("%Y-%m-%d")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// A palette of the export's `palettes`, with the comma in front unless it's the first one.
+fn palette_chunk(
+    palette: StoredPalette,
+    versions: Vec<PaletteVersion>,
+    first: bool,
+) -> anyhow::Result<String> {
+    let mut chunk = if first {
+        String::new()
+    } else {
+        ",".to_string()
+    };
+    chunk += &serde_json::to_string(&ExportedPalette::new(palette, versions)?)?;
+
+    Ok(chunk)
+}
+
+/// Stores the palettes from an export under this API key, keeping their IDs, short codes and
+/// earlier versions so existing links keep working, pinned ones included. Nothing gets stored if any of them is invalid.
+#[utoipa::path(
+    post,
+    path = "/v1/me/palettes/import",
+    request_body = Export,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "What got imported", body = ImportResult),
+        (status = 400, description = "The export isn't valid", body = String),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn import(
+    key: ApiKey,
+    storage: Storage,
+    Json(export): Json<Export>,
+) -> Result<Response, AppError> {
+    if export.version != EXPORT_VERSION {
+        let message = format!(
+            "Can't import version {} exports, only version {}",
+            export.version, EXPORT_VERSION
+        );
+        return Ok((StatusCode::BAD_REQUEST, message).into_response());
+    }
+    if export.palettes.len() > MAX_IMPORT {
+        let message = format!("Can't import more than {} palettes at once", MAX_IMPORT);
+        return Ok((StatusCode::BAD_REQUEST, message).into_response());
+    }
+
+    let now = Utc::now();
+    let mut skipped = Vec::new();
+    let mut palettes = Vec::with_capacity(export.palettes.len());
+    for exported in export.palettes {
+        if exported
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now)
+        {
+            skipped.push(SkippedPalette {
+                id: exported.id,
+                reason: "expired",
+            });
+            continue;
+        }
+        match exported.into_stored(&key.name) {
+            Ok(stored) => palettes.push(stored),
+            Err(message) => return Ok((StatusCode::BAD_REQUEST, message).into_response()),
+        }
+    }
+
+    let results = storage.restore_palettes(&palettes).await?;
+    let mut imported = Vec::new();
+    for ((palette, _), stored) in palettes.iter().zip(results) {
+        if stored {
+            imported.push(palette.id);
+        } else {
+            skipped.push(SkippedPalette {
+                id: palette.id,
+                reason: "taken",
+            });
+        }
+    }
+
+    info!(
+        "Imported {} palettes for {}, skipped {}",
+        imported.len(),
+        key.name,
+        skipped.len()
+    );
+
+    Ok(Json(ImportResult { imported, skipped }).into_response())
+}
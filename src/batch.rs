@@ -0,0 +1,216 @@
+use std::{collections::BTreeMap, convert::Infallible};
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use material_colors::color::Argb;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc,
+    task::{JoinError, JoinSet},
+};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use utoipa::ToSchema;
+
+use crate::{
+    cache,
+    channels::NewSeed,
+    errors::RenderError,
+    formats::{Format, FormatOptions},
+    input,
+    palette::ThemeType,
+    AppState,
+};
+
+/// Batches bigger than this get streamed, so a client doesn't wait for all of them.
+const STREAM_AFTER: usize = 16;
+const GENERATION_FAILED: &str = "Generating the palette failed";
+
+#[derive(Deserialize, ToSchema)]
+pub struct Batch {
+    seeds: Vec<NewSeed>,
+    /// For every palette of the batch
+    #[serde(default)]
+    format: Format,
+}
+
+/// One seed's palette, or why there isn't one.
+#[derive(Serialize, ToSchema)]
+pub struct BatchItem {
+    /// Position of the seed in the request
+    index: usize,
+    base_color: String,
+    /// In the batch's format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    palette: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Batches, which go in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/getPalettes", post(get_palettes))
+}
+
+/// Generates a palette for each seed, several at a time (`batch_concurrency`). A seed that
+/// doesn't parse gets an `error` instead of failing the others.
+///
+/// Up to 16 seeds come back as a JSON array in request order. Bigger batches are streamed
+/// as newline delimited JSON, one item per line as soon as it's done, so in any order.
+#[utoipa::path(
+    post,
+    path = "/v1/getPalettes",
+    params(FormatOptions),
+    request_body = Batch,
+    responses(
+        (status = 200, description = "A palette for each seed", body = [BatchItem]),
+        (status = 400, description = "More seeds than `max_batch_size`", body = String)
+    )
+)]
+pub async fn get_palettes(
+    State(state): State<AppState>,
+    Query(options): Query<FormatOptions>,
+    Json(batch): Json<Batch>,
+) -> Response {
+    let max = state.config.max_batch_size;
+    if batch.seeds.len() > max {
+        let message = format!("A batch can have at most {} seeds", max);
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    let streamed = batch.seeds.len() > STREAM_AFTER;
+    let (sender, receiver) = mpsc::channel(STREAM_AFTER);
+    tokio::spawn(generate_all(state, batch, options, sender));
+
+    if streamed {
+        let lines = ReceiverStream::new(receiver).map(|item| {
+            let mut line = serde_json::to_string(&item).expect("batch items always serialize");
+            line.push('\n');
+            Ok::<_, Infallible>(line)
+        });
+        return (
+            [(header::CONTENT_TYPE, "application/x-ndjson")],
+            Body::from_stream(lines),
+        )
+            .into_response();
+    }
+
+    let mut items: Vec<BatchItem> = ReceiverStream::new(receiver).collect().await;
+    items.sort_by_key(|item| item.index);
+    Json(items).into_response()
+}
+
+/// Sends every seed's item as soon as it's done, with at most `batch_concurrency` being
+/// generated at once. Stops early if the client went away.
+async fn generate_all(
+    state: AppState,
+    batch: Batch,
+    options: FormatOptions,
+    items: mpsc::Sender<BatchItem>,
+) {
+    let concurrency = state.config.batch_concurrency;
+    let format = batch.format;
+    let mut running = JoinSet::new();
+    // Seeds without an item yet, so one whose task failed outright still gets one in the end
+    let mut pending = BTreeMap::new();
+
+    for (index, seed) in batch.seeds.into_iter().enumerate() {
+        if running.len() >= concurrency {
+            if let Some(joined) = running.join_next().await {
+                if send_joined(joined, &mut pending, &items).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        pending.insert(index, seed.base_color.clone());
+        let state = state.clone();
+        let options = options.clone();
+        running.spawn(async move {
+            let base_color = seed.base_color.clone();
+            // Its own task, so a panic only fails this seed and it still gets an item
+            let result = tokio::spawn(generate(state, seed, format, options))
+                .await
+                .unwrap_or_else(|_| Err(GENERATION_FAILED.to_string()));
+
+            BatchItem {
+                index,
+                base_color,
+                palette: result.as_ref().ok().cloned(),
+                error: result.err(),
+            }
+        });
+    }
+
+    while let Some(joined) = running.join_next().await {
+        if send_joined(joined, &mut pending, &items).await.is_err() {
+            return;
+        }
+    }
+
+    for (index, base_color) in pending {
+        let item = BatchItem {
+            index,
+            base_color,
+            palette: None,
+            error: Some(GENERATION_FAILED.to_string()),
+        };
+        if items.send(item).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Sends a finished seed's item. A task that failed doesn't say which seed it was, so that one
+/// stays in `pending`.
+async fn send_joined(
+    joined: Result<BatchItem, JoinError>,
+    pending: &mut BTreeMap<usize, String>,
+    items: &mpsc::Sender<BatchItem>,
+) -> Result<(), mpsc::error::SendError<BatchItem>> {
+    let Ok(item) = joined else {
+        return Ok(());
+    };
+
+    pending.remove(&item.index);
+    items.send(item).await
+}
+
+async fn generate(
+    state: AppState,
+    seed: NewSeed,
+    format: Format,
+    options: FormatOptions,
+) -> Result<String, String> {
+    let Ok(source) = input::parse_color(&seed.base_color) else {
+        return Err("Invalid base_color".to_string());
+    };
+
+    render(&state, source, seed.theme_type, format, &options)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// One seed's palette in `format`. Formats with both themes in one file get both, whichever
+/// one the seed asks for.
+async fn render(
+    state: &AppState,
+    source: Argb,
+    theme_type: ThemeType,
+    format: Format,
+    options: &FormatOptions,
+) -> Result<String, RenderError> {
+    if format.both_themes() {
+        let dark = cache::palette(state, source, ThemeType::Dark).await?;
+        let light = cache::palette(state, source, ThemeType::Light).await?;
+        return Ok(format.render_both(&dark, &light, options));
+    }
+
+    let palette = cache::palette(state, source, theme_type).await?;
+    Ok(format.render(&palette, options))
+}
@@ -0,0 +1,128 @@
+//! Generates palettes the same way the service does, without running it. Handy for baking a
+//! theme into a world's assets at build time:
+//!
+//! ```sh
+//! resomaterialyou-cli gen --color 59EB5C --theme dark --format json > theme.json
+//! ```
+
+use std::io::Write;
+
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+use resomaterialyou::{
+    color_names::Lang,
+    formats::{Format, FormatOptions},
+    input,
+    palette::{self, CustomColorSpec, Palette, ThemeType},
+};
+
+#[derive(Parser)]
+#[command(version, about = "Material You palettes for Resonite, offline")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Writes a palette to stdout, exactly like `GET /v1/getPalette` would return it
+    Gen(Gen),
+}
+
+#[derive(Args)]
+struct Gen {
+    /// Source color as hex, with or without the `#` (3 or 6 digits)
+    #[arg(long)]
+    color: String,
+    /// `dark` or `light`
+    #[arg(long, value_parser = theme_type)]
+    theme: ThemeType,
+    /// `raw`, `json`, `dynvar`, `lines`, `annotated` or `android-xml`
+    #[arg(long, default_value = "raw", value_parser = format)]
+    format: Format,
+    /// Dynamic variable space the `dynvar` lines start with
+    #[arg(long)]
+    dynvar_space: Option<String>,
+    /// With `lines`, puts each color's name in front of it
+    #[arg(long)]
+    names: bool,
+    /// With `json`, adds the closest named color to each one, like `Dark Sea Green`
+    #[arg(long)]
+    color_names: bool,
+    /// The language of `--color-names`: `en`, `de` or `ja`
+    #[arg(long, default_value = "en", value_parser = lang)]
+    lang: Lang,
+    /// A custom color to use instead of the built-in ones, as `name=FF8FD8`, or
+    /// `name=FF8FD8:noblend` to keep it from shifting towards the source color. Can be repeated
+    #[arg(long = "custom-color", value_parser = custom_color)]
+    custom_colors: Vec<CustomColorSpec>,
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Gen(gen) => generate(gen),
+    }
+}
+
+fn generate(gen: Gen) -> anyhow::Result<()> {
+    let source = input::parse_color(&gen.color)
+        .with_context(|| format!("{:?} isn't a hex color", gen.color))?;
+    let custom_colors = if gen.custom_colors.is_empty() {
+        palette::default_custom_colors()
+    } else {
+        gen.custom_colors
+    };
+
+    let options = FormatOptions {
+        dynvar_space: gen.dynvar_space,
+        names: gen.names,
+        color_names: gen.color_names,
+        lang: gen.lang,
+    };
+    let output = if gen.format.both_themes() {
+        // Both themes, like the service writes them
+        let (dark, light) = Palette::generate_both(source, &custom_colors)?;
+        gen.format.render_both(&dark, &light, &options)
+    } else {
+        let palette = Palette::generate(source, gen.theme, &custom_colors)?;
+        gen.format.render(&palette, &options)
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(output.as_bytes())?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn theme_type(s: &str) -> anyhow::Result<ThemeType> {
+    [ThemeType::Dark, ThemeType::Light]
+        .into_iter()
+        .find(|theme_type| theme_type.as_str().eq_ignore_ascii_case(s))
+        .ok_or_else(|| anyhow::anyhow!("has to be dark or light"))
+}
+
+fn lang(s: &str) -> anyhow::Result<Lang> {
+    Lang::ALL
+        .into_iter()
+        .find(|lang| lang.as_str() == s)
+        .ok_or_else(|| anyhow::anyhow!("has to be en, de or ja"))
+}
+
+fn format(s: &str) -> anyhow::Result<Format> {
+    s.parse()
+}
+
+fn custom_color(s: &str) -> anyhow::Result<CustomColorSpec> {
+    let (name, value) = s.split_once('=').context("has to look like name=FF8FD8")?;
+    let (value, blend) = match value.strip_suffix(":noblend") {
+        Some(value) => (value, false),
+        None => (value, true),
+    };
+
+    Ok(CustomColorSpec {
+        name: name.to_string(),
+        value: input::parse_color(value)?,
+        blend,
+    })
+}
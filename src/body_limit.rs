@@ -0,0 +1,24 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Replaces the bare 413 from `RequestBodyLimitLayer` with one that says what the limit is.
+pub async fn explain_limit(State(max_bytes): State<usize>, req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return response;
+    }
+
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!(
+            "Request body is too large, the limit is {} bytes",
+            max_bytes
+        ),
+    )
+        .into_response()
+}
@@ -0,0 +1,773 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use material_colors::color::Argb;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    config::CustomColorConfig,
+    errors::RenderError,
+    input,
+    palette::{GenerationHook, Palette, ThemeType},
+    timing, AppState,
+};
+
+/// How far back the seeds [`warm`] picks from the database go.
+const WARM_POPULAR_DAYS: i32 = 30;
+/// How long [`RedisCache::connect`] waits for Redis to answer at startup.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The request parameters a palette depends on (the custom colors are the same for the whole
+/// instance), normalized so `ff0000`, `FF0000` and `#f00` all end up with the same entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PaletteKey {
+    rgb: [u8; 3],
+    theme_type: ThemeType,
+}
+
+impl PaletteKey {
+    pub fn new(source: Argb, theme_type: ThemeType) -> Self {
+        Self {
+            rgb: [source.red, source.green, source.blue],
+            theme_type,
+        }
+    }
+
+    /// The seed without its alpha, which doesn't change the palette.
+    pub fn source(self) -> Argb {
+        let [red, green, blue] = self.rgb;
+        Argb::new(255, red, green, blue)
+    }
+
+    /// Everything that changes the generated colors goes into the key, so instances running
+    /// a different version or different custom colors never share entries.
+    fn redis_key(self, customs: &str) -> String {
+        format!(
+            "resomaterialyou:{}:palette:{}:{}:{}",
+            env!("CARGO_PKG_VERSION"),
+            self.source().to_hex(),
+            self.theme_type.as_str(),
+            customs
+        )
+    }
+}
+
+/// Recently used palettes in this instance's memory, in front of Redis. Once they take up more
+/// than the budget, the least recently used entries make room for new ones.
+#[derive(Clone)]
+pub struct MemoryCache {
+    budget_bytes: usize,
+    entries: Arc<Mutex<MemoryEntries>>,
+}
+
+struct MemoryEntry {
+    palette: Palette,
+    /// The entries used right before and after this one, which makes the entries a list from
+    /// the least recently used to the most
+    older: Option<PaletteKey>,
+    newer: Option<PaletteKey>,
+}
+
+#[derive(Default)]
+struct MemoryEntries {
+    palettes: HashMap<PaletteKey, MemoryEntry>,
+    /// The ends of the list, so the least recently used entry can be found without looking
+    oldest: Option<PaletteKey>,
+    newest: Option<PaletteKey>,
+    /// What all of `palettes` weighs
+    bytes: usize,
+    /// Since the instance started
+    evictions: u64,
+}
+
+impl MemoryEntries {
+    fn get(&mut self, key: PaletteKey) -> Option<Palette> {
+        let palette = self.palettes.get(&key)?.palette.clone();
+        self.unlink(key);
+        self.link_newest(key);
+
+        Some(palette)
+    }
+
+    /// Returns the palette `key` had before.
+    fn insert(&mut self, key: PaletteKey, palette: Palette) -> Option<Palette> {
+        let entry = MemoryEntry {
+            palette,
+            older: None,
+            newer: None,
+        };
+        let old = match self.palettes.get(&key) {
+            Some(_) => {
+                self.unlink(key);
+                self.palettes.insert(key, entry).map(|old| old.palette)
+            }
+            None => {
+                self.palettes.insert(key, entry);
+                None
+            }
+        };
+        self.link_newest(key);
+
+        old
+    }
+
+    fn remove_oldest(&mut self) -> Option<Palette> {
+        let oldest = self.oldest?;
+        self.unlink(oldest);
+        self.palettes.remove(&oldest).map(|entry| entry.palette)
+    }
+
+    /// Takes `key` out of the list, joining its neighbors.
+    fn unlink(&mut self, key: PaletteKey) {
+        let entry = &self.palettes[&key];
+        let (older, newer) = (entry.older, entry.newer);
+        match older {
+            Some(older) => self.entry(older).newer = newer,
+            None => self.oldest = newer,
+        }
+        match newer {
+            Some(newer) => self.entry(newer).older = older,
+            None => self.newest = older,
+        }
+    }
+
+    /// Puts `key`, which isn't in the list, at its newest end.
+    fn link_newest(&mut self, key: PaletteKey) {
+        let newest = self.newest.replace(key);
+        let entry = self.entry(key);
+        entry.older = newest;
+        entry.newer = None;
+        match newest {
+            Some(newest) => self.entry(newest).newer = Some(key),
+            None => self.oldest = Some(key),
+        }
+    }
+
+    fn entry(&mut self, key: PaletteKey) -> &mut MemoryEntry {
+        self.palettes
+            .get_mut(&key)
+            .expect("the list only has keys that are in palettes")
+    }
+}
+
+impl MemoryCache {
+    /// Doesn't keep anything with a budget of 0.
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            budget_bytes,
+            entries: Default::default(),
+        }
+    }
+
+    fn get(&self, key: PaletteKey) -> Option<Palette> {
+        self.entries.lock().unwrap().get(key)
+    }
+
+    fn insert(&self, key: PaletteKey, palette: &Palette) {
+        let palette = palette.clone();
+        let bytes = weight(&palette);
+        if bytes > self.budget_bytes {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(old) = entries.insert(key, palette) {
+            entries.bytes -= weight(&old);
+        }
+        entries.bytes += bytes;
+
+        let mut evicted = 0;
+        while entries.bytes > self.budget_bytes {
+            let Some(palette) = entries.remove_oldest() else {
+                break;
+            };
+            entries.bytes -= weight(&palette);
+            evicted += 1;
+        }
+
+        entries.evictions += evicted;
+        metrics::counter!("palette_memory_cache_evictions_total").increment(evicted);
+        self.update_gauges(&entries);
+    }
+
+    /// Forgets every palette, returning how many there were.
+    fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let cleared = entries.palettes.len();
+        entries.palettes.clear();
+        entries.oldest = None;
+        entries.newest = None;
+        entries.bytes = 0;
+        self.update_gauges(&entries);
+
+        cleared
+    }
+
+    fn update_gauges(&self, entries: &MemoryEntries) {
+        metrics::gauge!("palette_memory_cache_bytes").set(entries.bytes as f64);
+        metrics::gauge!("palette_memory_cache_budget_bytes").set(self.budget_bytes as f64);
+        metrics::gauge!("palette_memory_cache_entries").set(entries.palettes.len() as f64);
+    }
+}
+
+/// What an entry takes up: the buffers its names and colors are in and its slot in `palettes`,
+/// with its place in the list. Approximate all the same, since what the allocator rounds up to and
+/// the spare room `palettes` keeps aren't counted, so the budget is a little under what the
+/// cache really uses.
+fn weight(palette: &Palette) -> usize {
+    let names: usize = palette.colors.iter().map(|(name, _)| name.capacity()).sum();
+    let colors = palette.colors.capacity() * std::mem::size_of::<(String, Argb)>();
+
+    names + colors + std::mem::size_of::<(PaletteKey, MemoryEntry)>()
+}
+
+/// Hits and misses of each cache since the instance started, the same ones
+/// `palette_cache_requests_total` counts.
+#[derive(Clone, Default)]
+pub struct CacheCounters {
+    counts: Arc<Mutex<HashMap<(&'static str, &'static str), u64>>>,
+}
+
+impl CacheCounters {
+    fn get(&self, cache: &'static str, result: &'static str) -> u64 {
+        let counts = self.counts.lock().unwrap();
+        counts.get(&(cache, result)).copied().unwrap_or_default()
+    }
+}
+
+type Shared = Arc<OnceCell<Result<Palette, RenderError>>>;
+
+/// Palettes being looked up or generated right now, which requests for the same one wait on.
+#[derive(Clone, Default)]
+pub struct InFlight {
+    cells: Arc<Mutex<HashMap<PaletteKey, Shared>>>,
+}
+
+impl InFlight {
+    fn cell(&self, key: PaletteKey) -> Shared {
+        let mut cells = self.cells.lock().unwrap();
+        cells.entry(key).or_default().clone()
+    }
+
+    /// Forgets the palette once it's done, so later requests get it from the memory cache
+    /// (or try again, if it failed). A newer lookup of the same palette is left alone.
+    fn done(&self, key: PaletteKey, cell: &Shared) {
+        let mut cells = self.cells.lock().unwrap();
+        if cells
+            .get(&key)
+            .is_some_and(|current| Arc::ptr_eq(current, cell))
+        {
+            cells.remove(&key);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.cells.lock().unwrap().len()
+    }
+}
+
+/// Redis, shared by every instance and kept across deploys.
+#[derive(Clone)]
+pub struct RedisCache {
+    connection: ConnectionManager,
+    ttl: Duration,
+    /// The custom colors' part of the keys
+    customs: Arc<str>,
+}
+
+/// What gets stored for each palette. The names are needed to bring it back in other formats.
+#[derive(Serialize, Deserialize)]
+struct CachedPalette {
+    names: Vec<String>,
+    palette: String,
+}
+
+impl RedisCache {
+    pub async fn connect(
+        url: &str,
+        ttl: Duration,
+        custom_colors: &[CustomColorConfig],
+    ) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        // The connection manager keeps retrying a Redis that isn't there
+        let connection = tokio::time::timeout(CONNECT_TIMEOUT, ConnectionManager::new(client))
+            .await
+            .map_err(|_| anyhow::anyhow!("no answer within {:?}", CONNECT_TIMEOUT))??;
+
+        Ok(Self {
+            connection,
+            ttl,
+            customs: customs_hash(custom_colors).into(),
+        })
+    }
+
+    /// Whether Redis answers, for the readiness probe.
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        redis::cmd("PING")
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: PaletteKey) -> anyhow::Result<Option<Palette>> {
+        let value: Option<String> = self
+            .connection
+            .clone()
+            .get(key.redis_key(&self.customs))
+            .await?;
+        let Some(value) = value else {
+            return Ok(None);
+        };
+
+        let cached: CachedPalette = serde_json::from_str(&value)?;
+        Ok(Some(Palette::from_wire_string(
+            cached.names,
+            &cached.palette,
+        )?))
+    }
+
+    /// Deletes the entries of other versions and other custom colors, which this instance
+    /// will never read. They'd expire on their own, but only after the TTL, which can be long.
+    ///
+    /// While a new version rolls out, instances still running the old one have to generate
+    /// palettes again.
+    pub async fn delete_stale(&self) -> anyhow::Result<u64> {
+        let current_version = env!("CARGO_PKG_VERSION");
+
+        self.delete_matching(|key| {
+            let mut parts = key.split(':');
+            let version = parts.nth(1);
+            let customs = parts.next_back();
+            version != Some(current_version) || customs != Some(&*self.customs)
+        })
+        .await
+    }
+
+    /// Deletes every palette, of any version. Every instance has to generate them again.
+    async fn delete_all(&self) -> anyhow::Result<u64> {
+        self.delete_matching(|_| true).await
+    }
+
+    async fn delete_matching(&self, matches: impl Fn(&str) -> bool) -> anyhow::Result<u64> {
+        let mut matching = Vec::new();
+        let mut connection = self.connection.clone();
+        let mut keys = connection
+            .scan_match::<_, String>("resomaterialyou:*:palette:*")
+            .await?;
+        while let Some(key) = keys.next_item().await {
+            if matches(&key) {
+                matching.push(key);
+            }
+        }
+        drop(keys);
+
+        let mut deleted = 0;
+        for batch in matching.chunks(500) {
+            deleted += connection.del::<_, u64>(batch).await?;
+        }
+
+        Ok(deleted)
+    }
+
+    async fn set(&self, key: PaletteKey, palette: &Palette) -> anyhow::Result<()> {
+        let value = serde_json::to_string(&CachedPalette {
+            names: palette.names(),
+            palette: palette.to_wire_string(),
+        })?;
+        self.connection
+            .clone()
+            .set_ex::<_, _, ()>(key.redis_key(&self.customs), value, self.ttl.as_secs())
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Short hash of the custom colors, the last part of every key. Values are hashed the way
+/// they parse, so writing one in lowercase doesn't throw away every entry.
+fn customs_hash(custom_colors: &[CustomColorConfig]) -> String {
+    let mut hasher = Sha256::new();
+    for color in custom_colors {
+        let value =
+            input::parse_color(&color.value).map_or_else(|_| color.value.clone(), |v| v.to_hex());
+        hasher.update(format!("{}={}/{};", color.name, value, color.blend));
+    }
+
+    hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Generates a palette, or gets it from memory or Redis if they have it.
+///
+/// Requests for a palette that's already being looked up or generated wait for that one
+/// instead of doing it again, like when everyone in a world asks for its theme as it loads.
+/// Redis being down never fails a request, it just means generating the palette ourselves.
+pub async fn palette(
+    state: &AppState,
+    source: Argb,
+    theme_type: ThemeType,
+) -> Result<Palette, RenderError> {
+    let key = PaletteKey::new(source, theme_type);
+    if let Some(palette) = state.memory_cache.get(key) {
+        record(state, "memory", "hit");
+        return Ok(palette);
+    }
+    record(state, "memory", "miss");
+
+    let cell = state.in_flight.cell(key);
+    let mut led = false;
+    let result = cell
+        .get_or_init(|| {
+            led = true;
+            fetch_or_generate(state, key)
+        })
+        .await
+        .clone();
+    state.in_flight.done(key, &cell);
+    record(state, "in_flight", if led { "miss" } else { "hit" });
+
+    result
+}
+
+/// The part of [`palette`] that only one request per palette does at a time.
+async fn fetch_or_generate(state: &AppState, key: PaletteKey) -> Result<Palette, RenderError> {
+    if let Some(cache) = &state.cache {
+        match cache.get(key).await {
+            Ok(Some(palette)) => {
+                record(state, "redis", "hit");
+                state.memory_cache.insert(key, &palette);
+                return Ok(palette);
+            }
+            Ok(None) => record(state, "redis", "miss"),
+            Err(err) => {
+                record(state, "redis", "error");
+                warn!("Couldn't read from the cache: {}", err);
+            }
+        }
+    }
+
+    let palette = if state.config.speculative_opposite {
+        let (dark, light) = generate_both(state, key.source()).await?;
+        let (palette, opposite) = match key.theme_type {
+            ThemeType::Dark => (dark, light),
+            ThemeType::Light => (light, dark),
+        };
+        store_opposite(state, key, opposite);
+        palette
+    } else {
+        generate(state, key.source(), key.theme_type).await?
+    };
+    state.memory_cache.insert(key, &palette);
+    if let Some(cache) = &state.cache {
+        if let Err(err) = cache.set(key, &palette).await {
+            warn!("Couldn't write to the cache: {}", err);
+        }
+    }
+
+    Ok(palette)
+}
+
+/// Generates a palette on the blocking thread pool, so a burst of them doesn't hold up the
+/// runtime's threads and every cheap request waiting on them.
+pub async fn generate(
+    state: &AppState,
+    source: Argb,
+    theme_type: ThemeType,
+) -> Result<Palette, RenderError> {
+    let custom_colors = state.custom_colors.clone();
+    let timings = timing::current();
+    let palette = tokio::task::spawn_blocking(move || {
+        timing::sync_scope(timings, || {
+            Palette::generate_with(source, theme_type, &custom_colors, &Timed)
+        })
+    })
+    .await??;
+
+    Ok(palette)
+}
+
+/// Like [`generate`], with both theme types at once.
+async fn generate_both(state: &AppState, source: Argb) -> Result<(Palette, Palette), RenderError> {
+    let custom_colors = state.custom_colors.clone();
+    let timings = timing::current();
+    let palettes = tokio::task::spawn_blocking(move || {
+        timing::sync_scope(timings, || {
+            Palette::generate_both_with(source, &custom_colors, &Timed)
+        })
+    })
+    .await??;
+
+    Ok(palettes)
+}
+
+/// Times each step of generating a palette for `X-Timing`, and building the theme for the
+/// metrics too.
+struct Timed;
+
+impl GenerationHook for Timed {
+    fn step<T>(&self, name: &'static str, step: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = step();
+        let elapsed = start.elapsed();
+        if name == "theme_build" {
+            metrics::histogram!("theme_generation_seconds").record(elapsed.as_secs_f64());
+        }
+        timing::record(name, elapsed);
+
+        result
+    }
+}
+
+/// Caches the other theme type of a palette that just got generated, since it's usually
+/// asked for next.
+fn store_opposite(state: &AppState, key: PaletteKey, opposite: Palette) {
+    let opposite_key = PaletteKey {
+        theme_type: match key.theme_type {
+            ThemeType::Dark => ThemeType::Light,
+            ThemeType::Light => ThemeType::Dark,
+        },
+        ..key
+    };
+    state.memory_cache.insert(opposite_key, &opposite);
+
+    if let Some(cache) = state.cache.clone() {
+        tokio::spawn(async move {
+            if let Err(err) = cache.set(opposite_key, &opposite).await {
+                warn!("Couldn't write to the cache: {}", err);
+            }
+        });
+    }
+}
+
+/// Generates the palettes of `warm_seeds` in both theme types, and the most requested
+/// seeds lately, in the background. That way the first requests after a deploy don't all
+/// have to generate them at once.
+pub fn warm(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let start = Instant::now();
+        let mut seeds = Vec::new();
+        for color in &state.config.warm_seeds {
+            let source =
+                input::parse_color(color).expect("warm_seeds get checked when the config loads");
+            seeds.push((source, ThemeType::Dark));
+            seeds.push((source, ThemeType::Light));
+        }
+        if let Some(storage) = &state.storage {
+            let limit = state.config.warm_popular_seeds;
+            match storage.popular_seeds(WARM_POPULAR_DAYS, None, limit).await {
+                Ok(popular) => seeds.extend(popular.iter().filter_map(|seed| {
+                    let source = input::parse_color(&seed.base_color).ok()?;
+                    Some((source, ThemeType::from_str(&seed.theme_type).ok()?))
+                })),
+                Err(err) => warn!(
+                    "Couldn't get the popular seeds to warm the cache with: {}",
+                    err
+                ),
+            }
+        }
+        if seeds.is_empty() {
+            return;
+        }
+
+        let mut warmed = 0;
+        for (source, theme_type) in seeds {
+            match palette(&state, source, theme_type).await {
+                Ok(_) => warmed += 1,
+                Err(err) => warn!("Couldn't warm the cache with {}: {}", source.to_hex(), err),
+            }
+        }
+        info!(
+            "Warmed the cache with {} palettes in {:?}",
+            warmed,
+            start.elapsed()
+        );
+    });
+}
+
+fn record(state: &AppState, cache: &'static str, result: &'static str) {
+    *state
+        .cache_counters
+        .counts
+        .lock()
+        .unwrap()
+        .entry((cache, result))
+        .or_default() += 1;
+    metrics::counter!("palette_cache_requests_total", "cache" => cache, "result" => result)
+        .increment(1);
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CacheStats {
+    memory: MemoryStats,
+    /// Palettes being looked up or generated right now
+    in_flight: usize,
+    /// Whether `redis_url` is set
+    redis: bool,
+    /// Since the instance started, for the memory cache, Redis and waiting on the same palette
+    requests: Vec<CacheRequests>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MemoryStats {
+    entries: usize,
+    bytes: usize,
+    budget_bytes: usize,
+    evictions: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CacheRequests {
+    #[schema(example = "memory")]
+    cache: &'static str,
+    hits: u64,
+    misses: u64,
+    errors: u64,
+    /// Hits out of every request to this cache, null before the first one
+    hit_rate: Option<f64>,
+}
+
+/// How full the caches are and how often they had the palette.
+pub fn stats(state: &AppState) -> CacheStats {
+    let memory = {
+        let entries = state.memory_cache.entries.lock().unwrap();
+        MemoryStats {
+            entries: entries.palettes.len(),
+            bytes: entries.bytes,
+            budget_bytes: state.memory_cache.budget_bytes,
+            evictions: entries.evictions,
+        }
+    };
+
+    let counters = &state.cache_counters;
+    let requests = ["memory", "redis", "in_flight"]
+        .into_iter()
+        .map(|cache| {
+            let hits = counters.get(cache, "hit");
+            let misses = counters.get(cache, "miss");
+            let errors = counters.get(cache, "error");
+            let total = hits + misses + errors;
+            CacheRequests {
+                cache,
+                hits,
+                misses,
+                errors,
+                hit_rate: (total > 0).then(|| hits as f64 / total as f64),
+            }
+        })
+        .collect();
+
+    CacheStats {
+        memory,
+        in_flight: state.in_flight.len(),
+        redis: state.cache.is_some(),
+        requests,
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Flushed {
+    /// Palettes this instance had in memory
+    memory: usize,
+    /// Palettes deleted from Redis, null if it wasn't flushed
+    redis: Option<u64>,
+}
+
+/// Throws away the palettes in this instance's memory and, with `redis`, every palette in
+/// Redis for every instance. Other instances keep what they have in memory.
+pub async fn flush(state: &AppState, redis: bool) -> anyhow::Result<Flushed> {
+    let memory = state.memory_cache.clear();
+    let redis = match (&state.cache, redis) {
+        (Some(cache), true) => Some(cache.delete_all().await?),
+        _ => None,
+    };
+    info!(
+        "Flushed the cache: {} palettes from memory, {:?} from Redis",
+        memory, redis
+    );
+
+    Ok(Flushed { memory, redis })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(blue: u8) -> PaletteKey {
+        PaletteKey::new(Argb::new(255, 0, 0, blue), ThemeType::Dark)
+    }
+
+    #[test]
+    fn hits_keep_an_entry_around() {
+        let palette = Palette {
+            colors: vec![("primary".to_string(), Argb::new(255, 0x59, 0xEB, 0x5C))],
+        };
+        let cache = MemoryCache::new(weight(&palette) * 2);
+
+        cache.insert(key(1), &palette);
+        cache.insert(key(2), &palette);
+        assert!(cache.get(key(1)).is_some());
+        cache.insert(key(3), &palette);
+
+        assert!(cache.get(key(1)).is_some());
+        assert!(cache.get(key(2)).is_none());
+        assert!(cache.get(key(3)).is_some());
+    }
+
+    #[test]
+    fn the_least_recently_used_makes_room() {
+        let palette = Palette {
+            colors: vec![("primary".to_string(), Argb::new(255, 0x59, 0xEB, 0x5C))],
+        };
+        let cache = MemoryCache::new(weight(&palette) * 3);
+
+        for blue in 1..=3 {
+            cache.insert(key(blue), &palette);
+        }
+        // 2, 3, 1 after the hit and 3, 1, 2 after overwriting, then 4 and 5 push out 3 and 1
+        cache.get(key(1));
+        cache.insert(key(2), &palette);
+        cache.insert(key(4), &palette);
+        cache.insert(key(5), &palette);
+
+        let entries = cache.entries.lock().unwrap();
+        let mut order = Vec::new();
+        let mut next = entries.oldest;
+        while let Some(key) = next {
+            order.push(key.rgb[2]);
+            next = entries.palettes[&key].newer;
+        }
+        assert_eq!(order, [2, 4, 5]);
+        assert_eq!(entries.newest, Some(key(5)));
+        assert_eq!(entries.palettes.len(), 3);
+    }
+
+    #[test]
+    fn entries_weigh_what_they_hold_on_to() {
+        let mut name = String::with_capacity(100);
+        name.push_str("primary");
+        let mut palette = Palette {
+            colors: Vec::with_capacity(10),
+        };
+        palette
+            .colors
+            .push((name, Argb::new(255, 0x59, 0xEB, 0x5C)));
+
+        // The cache keeps a copy, which doesn't have the spare room
+        assert!(weight(&palette) > weight(&palette.clone()));
+        assert!(
+            weight(&palette.clone()) >= "primary".len() + std::mem::size_of::<(String, Argb)>()
+        );
+    }
+}
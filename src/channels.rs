@@ -0,0 +1,802 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    async_trait,
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        FromRequestParts, Path, Query, State,
+    },
+    http::{header, request::Parts, HeaderName, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::{broadcast, watch};
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
+use tracing::debug;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    cache,
+    formats::{Format, FormatOptions},
+    input,
+    palette::Palette,
+    palette::ThemeType,
+    rate_limit::{self, ClientIp, RateLimiter},
+    shutdown::Shutdown,
+    AppError, AppState,
+};
+
+const MAX_CHANNEL_LENGTH: usize = 64;
+/// Every channel takes some memory, and anyone can make one by listening to it.
+pub(crate) const MAX_CHANNELS: usize = 10_000;
+/// How many channels one client can make a minute.
+const NEW_CHANNELS_PER_MINUTE: u32 = 30;
+const DEFAULT_WAIT: Duration = Duration::from_secs(25);
+/// How many changes a slow bridge can fall behind before it misses some.
+const UPDATE_BACKLOG: usize = 64;
+
+static REVISION: HeaderName = HeaderName::from_static("x-theme-revision");
+static CHANNEL_TOKEN: HeaderName = HeaderName::from_static("x-channel-token");
+
+/// The theme a channel is showing right now.
+pub struct ChannelTheme {
+    /// Goes up by one with every change, starting at 1
+    pub revision: u64,
+    pub base_color: String,
+    pub theme_type: ThemeType,
+    pub palette: Palette,
+    pub updated_at: DateTime<Utc>,
+}
+
+type Theme = Option<Arc<ChannelTheme>>;
+
+struct Channel {
+    sender: watch::Sender<Theme>,
+    /// Digest of the token needed to change the theme, once someone claimed the channel
+    write_token: Option<[u8; 32]>,
+    /// When it was made or claimed, so new channels don't count as idle before anyone uses them
+    created_at: DateTime<Utc>,
+    /// When someone last got its theme, so channels that are only polled don't count as idle
+    last_read_at: DateTime<Utc>,
+}
+
+impl Channel {
+    fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            sender: watch::channel(None).0,
+            write_token: None,
+            created_at: now,
+            last_read_at: now,
+        }
+    }
+
+    /// Whether `token` lets someone change the channel. Unclaimed channels don't need one.
+    fn may_write(&self, token: Option<&str>) -> bool {
+        match self.write_token {
+            // Comparing digests keeps the comparison from leaking how much of the token was right
+            Some(expected) => token.is_some_and(|token| Sha256::digest(token)[..] == expected),
+            None => true,
+        }
+    }
+}
+
+/// Every channel's changes, for bridges to other protocols.
+pub type Update = (String, Arc<ChannelTheme>);
+
+/// There are [`MAX_CHANNELS`] already, so a new one can't be made until some go idle.
+#[derive(Debug)]
+pub struct TooManyChannels;
+
+impl IntoResponse for TooManyChannels {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "There are too many channels right now, try again later",
+        )
+            .into_response()
+    }
+}
+
+/// The channel, made if it isn't there yet and there's room for it.
+fn channel_entry<'a>(
+    channels: &'a mut HashMap<String, Channel>,
+    channel: &str,
+) -> Result<&'a mut Channel, TooManyChannels> {
+    if !channels.contains_key(channel) && channels.len() >= MAX_CHANNELS {
+        return Err(TooManyChannels);
+    }
+
+    Ok(channels
+        .entry(channel.to_owned())
+        .or_insert_with(Channel::new))
+}
+
+/// Live theme channels, which anyone listening gets every change of. They only live in this
+/// instance's memory, so everyone on a channel has to reach the same instance.
+#[derive(Clone)]
+pub struct Channels {
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    updates: broadcast::Sender<Update>,
+    new_channels: RateLimiter,
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Self {
+            channels: Default::default(),
+            updates: broadcast::channel(UPDATE_BACKLOG).0,
+            new_channels: RateLimiter::new(NEW_CHANNELS_PER_MINUTE, Duration::from_secs(60)),
+        }
+    }
+}
+
+impl Channels {
+    /// How long the client has to wait if it made too many channels lately and this one would
+    /// be new. Channels that are there already are fine.
+    pub fn admit(&self, channel: &str, ip: &ClientIp) -> Result<(), Duration> {
+        if self.channels.lock().unwrap().contains_key(channel) {
+            return Ok(());
+        }
+
+        self.new_channels.check(ip)
+    }
+
+    /// How many new channels clients made lately.
+    pub fn rate_limits(&self) -> &RateLimiter {
+        &self.new_channels
+    }
+
+    /// Listens to a channel, which starts out with its current theme (if it has one).
+    pub fn subscribe(&self, channel: &str) -> Result<watch::Receiver<Theme>, TooManyChannels> {
+        let mut channels = self.channels.lock().unwrap();
+        let channel = channel_entry(&mut channels, channel)?;
+        channel.last_read_at = Utc::now();
+        Ok(channel.sender.subscribe())
+    }
+
+    /// The channel's theme right now.
+    pub fn current(&self, channel: &str) -> Theme {
+        let mut channels = self.channels.lock().unwrap();
+        let channel = channels.get_mut(channel)?;
+        channel.last_read_at = Utc::now();
+        let theme = channel.sender.borrow().clone();
+        theme
+    }
+
+    /// Changes a channel's theme, telling everyone listening.
+    pub fn publish(
+        &self,
+        channel: &str,
+        base_color: String,
+        theme_type: ThemeType,
+        palette: Palette,
+    ) -> Result<Arc<ChannelTheme>, TooManyChannels> {
+        let mut channels = self.channels.lock().unwrap();
+        let entry = channel_entry(&mut channels, channel)?;
+        Ok(self.send(channel, entry, base_color, theme_type, palette))
+    }
+
+    /// Like [`Channels::publish`], but only if `token` lets someone change the channel (`None`
+    /// otherwise). Checked under the same lock, so nobody can claim it in between.
+    pub fn publish_with_token(
+        &self,
+        channel: &str,
+        token: Option<&str>,
+        base_color: String,
+        theme_type: ThemeType,
+        palette: Palette,
+    ) -> Result<Option<Arc<ChannelTheme>>, TooManyChannels> {
+        let mut channels = self.channels.lock().unwrap();
+        let entry = channel_entry(&mut channels, channel)?;
+        if !entry.may_write(token) {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            self.send(channel, entry, base_color, theme_type, palette),
+        ))
+    }
+
+    fn send(
+        &self,
+        name: &str,
+        channel: &Channel,
+        base_color: String,
+        theme_type: ThemeType,
+        palette: Palette,
+    ) -> Arc<ChannelTheme> {
+        let sender = &channel.sender;
+        let revision = sender.borrow().as_ref().map_or(0, |theme| theme.revision) + 1;
+        let theme = Arc::new(ChannelTheme {
+            revision,
+            base_color,
+            theme_type,
+            palette,
+            updated_at: Utc::now(),
+        });
+        sender.send_replace(Some(theme.clone()));
+        // Nobody listening is fine
+        let _ = self.updates.send((name.to_owned(), theme.clone()));
+        metrics::counter!("theme_channel_updates_total").increment(1);
+
+        theme
+    }
+
+    /// Every change on every channel from now on.
+    pub fn updates(&self) -> broadcast::Receiver<Update> {
+        self.updates.subscribe()
+    }
+
+    /// Makes a channel only writable with the returned token, unless someone already claimed it.
+    pub fn claim(&self, channel: &str) -> Result<Option<String>, TooManyChannels> {
+        let mut channels = self.channels.lock().unwrap();
+        let channel = channel_entry(&mut channels, channel)?;
+        if channel.write_token.is_some() {
+            return Ok(None);
+        }
+
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        channel.write_token = Some(Sha256::digest(&token).into());
+        channel.created_at = Utc::now();
+        Ok(Some(token))
+    }
+
+    /// Whether `token` lets someone change the channel. Unclaimed channels don't need one.
+    pub fn may_write(&self, channel: &str, token: Option<&str>) -> bool {
+        let channels = self.channels.lock().unwrap();
+        channels
+            .get(channel)
+            .is_none_or(|channel| channel.may_write(token))
+    }
+
+    /// How many are listening to a channel.
+    pub fn subscribers(&self, channel: &str) -> usize {
+        let channels = self.channels.lock().unwrap();
+        channels
+            .get(channel)
+            .map_or(0, |channel| channel.sender.receiver_count())
+    }
+
+    /// Forgets channels nobody listens to that haven't been made, claimed, changed or read in
+    /// `max_idle`.
+    pub fn remove_idle(&self, max_idle: Duration) -> u64 {
+        let cutoff = Utc::now() - max_idle;
+        let mut channels = self.channels.lock().unwrap();
+        let before = channels.len();
+        channels.retain(|_, channel| {
+            let recent = channel
+                .sender
+                .borrow()
+                .as_ref()
+                .is_some_and(|theme| theme.updated_at > cutoff);
+            channel.sender.receiver_count() > 0
+                || recent
+                || channel.created_at > cutoff
+                || channel.last_read_at > cutoff
+        });
+
+        (before - channels.len()) as u64
+    }
+}
+
+/// Channel names are used in URLs and shouldn't take up a lot of memory.
+pub(crate) fn valid_channel(channel: &str) -> bool {
+    (1..=MAX_CHANNEL_LENGTH).contains(&channel.len())
+        && channel
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub(crate) fn invalid_channel() -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        format!(
+            "Channel names are 1 to {} letters, digits, - or _",
+            MAX_CHANNEL_LENGTH
+        ),
+    )
+        .into_response()
+}
+
+pub(crate) fn too_many_new_channels(retry_after: Duration) -> Response {
+    rate_limit::too_many_requests(retry_after, "Too many new channels, try again later")
+}
+
+pub(crate) fn not_allowed() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        "This channel is claimed, send its token in X-Channel-Token",
+    )
+        .into_response()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SubscribeQuery {
+    /// What every message looks like, defaults to `raw`
+    #[serde(default)]
+    format: Format,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WaitQuery {
+    /// The last revision the client has, defaults to 0 (none)
+    #[serde(default)]
+    since: u64,
+    /// How long to wait for a change, defaults to 25 (capped to just under the request timeout)
+    timeout_secs: Option<u64>,
+    /// What the theme looks like, defaults to `raw`
+    #[serde(default)]
+    format: Format,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewSeed {
+    #[schema(example = "59EB5C")]
+    pub base_color: String,
+    pub theme_type: ThemeType,
+}
+
+/// Someone's current theme, for clients that'd rather ask than listen.
+#[derive(Serialize, ToSchema)]
+pub struct ChannelState {
+    revision: u64,
+    base_color: String,
+    theme_type: ThemeType,
+    /// Same format as `/v1/getPalette`
+    palette: String,
+    /// Whether changing it takes a token
+    claimed: bool,
+    subscribers: usize,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChannelToken {
+    /// Goes in `X-Channel-Token` (or `?token=`) to change the channel's theme
+    token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Published {
+    revision: u64,
+    base_color: String,
+    theme_type: ThemeType,
+    /// How many got the new theme
+    subscribers: usize,
+}
+
+/// Live channels. These stay outside of the versioned API, like short links, so worlds can
+/// keep their socket URLs short.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/ws/theme/:channel", get(subscribe).post(publish))
+        .route("/sse/theme/:channel", get(subscribe_events))
+}
+
+/// The parts of channels that go in the v1 router.
+pub fn api_router() -> Router<AppState> {
+    Router::new()
+        .route("/channels/:channel", get(get_channel).put(put_channel))
+        .route("/channels/:channel/token", post(claim_channel))
+        .route("/channels/:channel/wait", get(wait))
+}
+
+/// Opens a WebSocket that gets the channel's theme, right away if it has one and then on
+/// every change, as a text message in the requested format.
+#[utoipa::path(
+    get,
+    path = "/ws/theme/{channel}",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel"), SubscribeQuery, FormatOptions),
+    responses(
+        (status = 101, description = "Switching to a WebSocket"),
+        (status = 400, description = "Invalid channel name, or not a WebSocket request", body = String),
+        (status = 429, description = "Too many new channels from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many channels to make another one", body = String)
+    )
+)]
+pub async fn subscribe(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    ip: ClientIp,
+    Query(query): Query<SubscribeQuery>,
+    Query(options): Query<FormatOptions>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if !valid_channel(&channel) {
+        return invalid_channel();
+    }
+    if let Err(retry_after) = state.channels.admit(&channel, &ip) {
+        return too_many_new_channels(retry_after);
+    }
+
+    let receiver = match state.channels.subscribe(&channel) {
+        Ok(receiver) => receiver,
+        Err(err) => return err.into_response(),
+    };
+    let shutdown = state.shutdown.clone();
+    upgrade
+        .on_upgrade(move |socket| stream_themes(socket, receiver, shutdown, query.format, options))
+}
+
+async fn stream_themes(
+    mut socket: WebSocket,
+    mut receiver: watch::Receiver<Theme>,
+    shutdown: Shutdown,
+    format: Format,
+    options: FormatOptions,
+) {
+    // The current theme counts as a change for a new subscriber
+    receiver.mark_changed();
+
+    loop {
+        tokio::select! {
+            changed = receiver.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let theme = receiver.borrow_and_update().clone();
+                if let Some(theme) = theme {
+                    let message = Message::Text(format.render(&theme.palette, &options));
+                    if socket.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            // Nothing clients send means anything, but reading is what answers pings
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            () = shutdown.stopping() => {
+                // "Going away", so clients know to reconnect
+                let close = CloseFrame {
+                    code: close_code::AWAY,
+                    reason: "The server is restarting".into(),
+                };
+                let _ = socket.send(Message::Close(Some(close))).await;
+                break;
+            }
+        }
+    }
+
+    debug!("Theme subscriber left");
+}
+
+/// Same as the WebSocket, as Server-Sent Events for browsers. Every `theme` event has the
+/// palette as its data and the channel's revision as its ID.
+#[utoipa::path(
+    get,
+    path = "/sse/theme/{channel}",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel"), SubscribeQuery, FormatOptions),
+    responses(
+        (status = 200, description = "An event stream", body = String, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid channel name", body = String),
+        (status = 429, description = "Too many new channels from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many channels to make another one", body = String)
+    )
+)]
+pub async fn subscribe_events(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    ip: ClientIp,
+    Query(query): Query<SubscribeQuery>,
+    Query(options): Query<FormatOptions>,
+) -> Response {
+    if !valid_channel(&channel) {
+        return invalid_channel();
+    }
+    if let Err(retry_after) = state.channels.admit(&channel, &ip) {
+        return too_many_new_channels(retry_after);
+    }
+
+    let receiver = match state.channels.subscribe(&channel) {
+        Ok(receiver) => receiver,
+        Err(err) => return err.into_response(),
+    };
+    let events = theme_events(receiver, query.format, options);
+    Sse::new(state.shutdown.until_stopping(events))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// The current theme (if there is one), then every change.
+fn theme_events(
+    receiver: watch::Receiver<Theme>,
+    format: Format,
+    options: FormatOptions,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    WatchStream::new(receiver).filter_map(move |theme| {
+        theme.map(|theme| {
+            Ok(Event::default()
+                .event("theme")
+                .id(theme.revision.to_string())
+                .data(format.render(&theme.palette, &options)))
+        })
+    })
+}
+
+/// Long polling, for clients that can't keep a socket open: answers as soon as the channel
+/// has a newer revision than `since`, or with a 204 once the timeout is up. Either way
+/// `X-Theme-Revision` has the revision to send next time.
+#[utoipa::path(
+    get,
+    path = "/v1/channels/{channel}/wait",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel"), WaitQuery, FormatOptions),
+    responses(
+        (status = 200, description = "The new theme, in the requested format", body = String),
+        (status = 204, description = "Nothing changed in time"),
+        (status = 400, description = "Invalid channel name", body = String),
+        (status = 429, description = "Too many new channels from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many channels to make another one", body = String)
+    )
+)]
+pub async fn wait(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    ip: ClientIp,
+    Query(query): Query<WaitQuery>,
+    Query(options): Query<FormatOptions>,
+) -> Response {
+    if !valid_channel(&channel) {
+        return invalid_channel();
+    }
+    if let Err(retry_after) = state.channels.admit(&channel, &ip) {
+        return too_many_new_channels(retry_after);
+    }
+
+    // Waiting past the request timeout would only get the client a 408
+    let max_wait = Duration::from_secs(state.config.request_timeout_secs.saturating_sub(1));
+    let wait = query
+        .timeout_secs
+        .map_or(DEFAULT_WAIT, Duration::from_secs)
+        .min(max_wait);
+    let deadline = tokio::time::Instant::now() + wait;
+
+    let mut receiver = match state.channels.subscribe(&channel) {
+        Ok(receiver) => receiver,
+        Err(err) => return err.into_response(),
+    };
+    loop {
+        let theme = receiver.borrow_and_update().clone();
+        if let Some(theme) = theme.filter(|theme| theme.revision > query.since) {
+            let body = query.format.render(&theme.palette, &options);
+            return (
+                [
+                    (REVISION.clone(), HeaderValue::from(theme.revision)),
+                    (
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(query.format.content_type()),
+                    ),
+                ],
+                body,
+            )
+                .into_response();
+        }
+
+        // Stopping the server answers like a timeout, so the client just asks again
+        let changed = tokio::select! {
+            changed = tokio::time::timeout_at(deadline, receiver.changed()) => changed.ok(),
+            () = state.shutdown.stopping() => None,
+        };
+        if !matches!(changed, Some(Ok(()))) {
+            let revision = receiver.borrow().as_ref().map_or(0, |theme| theme.revision);
+            return (
+                StatusCode::NO_CONTENT,
+                [(REVISION.clone(), HeaderValue::from(revision))],
+            )
+                .into_response();
+        }
+    }
+}
+
+/// Who wants to change a channel's theme, with the channel's token if it's claimed. The token
+/// can go in an `X-Channel-Token` header or a `token` query parameter.
+pub struct WriteToken(pub(crate) Option<String>);
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for WriteToken {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(token) = parts.headers.get(&CHANNEL_TOKEN) {
+            return Ok(Self(token.to_str().ok().map(str::to_owned)));
+        }
+
+        let query = Query::<TokenQuery>::try_from_uri(&parts.uri).ok();
+        Ok(Self(query.and_then(|Query(query)| query.token)))
+    }
+}
+
+/// Changes a channel's theme, which everyone on its WebSocket or event stream gets right away.
+#[utoipa::path(
+    post,
+    path = "/ws/theme/{channel}",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel")),
+    request_body = NewSeed,
+    responses(
+        (status = 200, description = "The new theme went out", body = Published),
+        (status = 400, description = "Invalid channel name or base color", body = String),
+        (status = 403, description = "The channel is claimed, and the token is missing or wrong", body = String),
+        (status = 429, description = "Too many new channels from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many channels to make another one", body = String)
+    )
+)]
+pub async fn publish(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    ip: ClientIp,
+    token: WriteToken,
+    Json(seed): Json<NewSeed>,
+) -> Result<Response, AppError> {
+    set_theme(&state, &channel, &ip, token, seed).await
+}
+
+/// Changes a channel's theme, like `POST /ws/theme/{channel}`.
+#[utoipa::path(
+    put,
+    path = "/v1/channels/{channel}",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel")),
+    request_body = NewSeed,
+    responses(
+        (status = 200, description = "The new theme went out", body = Published),
+        (status = 400, description = "Invalid channel name or base color", body = String),
+        (status = 403, description = "The channel is claimed, and the token is missing or wrong", body = String),
+        (status = 429, description = "Too many new channels from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many channels to make another one", body = String)
+    )
+)]
+pub async fn put_channel(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    ip: ClientIp,
+    token: WriteToken,
+    Json(seed): Json<NewSeed>,
+) -> Result<Response, AppError> {
+    set_theme(&state, &channel, &ip, token, seed).await
+}
+
+async fn set_theme(
+    state: &AppState,
+    channel: &str,
+    ip: &ClientIp,
+    WriteToken(token): WriteToken,
+    seed: NewSeed,
+) -> Result<Response, AppError> {
+    if !valid_channel(channel) {
+        return Ok(invalid_channel());
+    }
+    if let Err(retry_after) = state.channels.admit(channel, ip) {
+        return Ok(too_many_new_channels(retry_after));
+    }
+    let Ok(source) = input::parse_color(&seed.base_color) else {
+        return Ok((StatusCode::BAD_REQUEST, "Invalid base_color").into_response());
+    };
+
+    let palette = cache::palette(state, source, seed.theme_type).await?;
+    let theme = match state.channels.publish_with_token(
+        channel,
+        token.as_deref(),
+        source.to_hex(),
+        seed.theme_type,
+        palette,
+    ) {
+        Ok(Some(theme)) => theme,
+        Ok(None) => return Ok(not_allowed()),
+        Err(err) => return Ok(err.into_response()),
+    };
+
+    Ok(Json(Published {
+        revision: theme.revision,
+        base_color: theme.base_color.clone(),
+        theme_type: theme.theme_type,
+        subscribers: state.channels.subscribers(channel),
+    })
+    .into_response())
+}
+
+/// The theme a channel is showing.
+#[utoipa::path(
+    get,
+    path = "/v1/channels/{channel}",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel")),
+    responses(
+        (status = 200, description = "The channel's theme", body = ChannelState),
+        (status = 400, description = "Invalid channel name", body = String),
+        (status = 404, description = "Nobody set a theme on the channel yet", body = String)
+    )
+)]
+pub async fn get_channel(State(state): State<AppState>, Path(channel): Path<String>) -> Response {
+    if !valid_channel(&channel) {
+        return invalid_channel();
+    }
+
+    match state.channels.current(&channel) {
+        Some(theme) => Json(ChannelState {
+            revision: theme.revision,
+            base_color: theme.base_color.clone(),
+            theme_type: theme.theme_type,
+            palette: theme.palette.to_wire_string(),
+            claimed: !state.channels.may_write(&channel, None),
+            subscribers: state.channels.subscribers(&channel),
+            updated_at: theme.updated_at,
+        })
+        .into_response(),
+        None => (StatusCode::NOT_FOUND, "This channel has no theme yet").into_response(),
+    }
+}
+
+/// Claims a channel, so only whoever has the returned token can change its theme from now
+/// on (until the channel gets forgotten for being idle).
+#[utoipa::path(
+    post,
+    path = "/v1/channels/{channel}/token",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel")),
+    responses(
+        (status = 201, description = "The channel's write token", body = ChannelToken),
+        (status = 400, description = "Invalid channel name", body = String),
+        (status = 409, description = "Someone claimed the channel already", body = String),
+        (status = 429, description = "Too many new channels from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many channels to make another one", body = String)
+    )
+)]
+pub async fn claim_channel(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    ip: ClientIp,
+) -> Response {
+    if !valid_channel(&channel) {
+        return invalid_channel();
+    }
+    if let Err(retry_after) = state.channels.admit(&channel, &ip) {
+        return too_many_new_channels(retry_after);
+    }
+
+    match state.channels.claim(&channel) {
+        Ok(Some(token)) => (StatusCode::CREATED, Json(ChannelToken { token })).into_response(),
+        Ok(None) => (StatusCode::CONFLICT, "This channel is claimed already").into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_someone_reads_are_kept() {
+        let channels = Channels::default();
+        channels.claim("forgotten").unwrap();
+        channels.claim("polled").unwrap();
+        let long_ago = Utc::now() - Duration::from_secs(2 * 60 * 60);
+        for channel in channels.channels.lock().unwrap().values_mut() {
+            channel.created_at = long_ago;
+            channel.last_read_at = long_ago;
+        }
+
+        assert!(channels.current("polled").is_none());
+        assert_eq!(channels.remove_idle(Duration::from_secs(60 * 60)), 1);
+        assert!(!channels.may_write("polled", None));
+        assert!(channels.may_write("forgotten", None));
+    }
+}
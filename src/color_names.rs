@@ -0,0 +1,603 @@
+use material_colors::color::{Argb, Lab};
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::ToSchema;
+
+/// A list of named colors to look for matches in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReferenceSet {
+    /// The CSS named colors, like `Coral`
+    #[default]
+    Css,
+    /// sRGB approximations of the Pantone Colors of the Year, like `Classic Blue`. Not the real
+    /// thing, but close enough to point a printer in the right direction
+    PantoneIsh,
+    /// The colors of Resonite's own UI, like `Hero Green`
+    Resonite,
+}
+
+impl ReferenceSet {
+    fn colors(self) -> &'static [(&'static str, u32)] {
+        match self {
+            ReferenceSet::Css => CSS_COLORS,
+            ReferenceSet::PantoneIsh => PANTONE_ISH_COLORS,
+            ReferenceSet::Resonite => RESONITE_COLORS,
+        }
+    }
+
+    /// The names of [`ReferenceSet::colors`] in another language, `None` for English and for the
+    /// sets of proper names.
+    fn translated_names(self, lang: Lang) -> Option<&'static [&'static str]> {
+        match (self, lang) {
+            (ReferenceSet::Css, Lang::De) => Some(&CSS_NAMES_DE),
+            (ReferenceSet::Css, Lang::Ja) => Some(&CSS_NAMES_JA),
+            _ => None,
+        }
+    }
+}
+
+/// The language names are in. Only the CSS names get translated: the Pantone-like and Resonite
+/// ones are proper names, which nobody translates either. Languages there are no names in fall
+/// back to English rather than being an error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+    Ja,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 3] = [Lang::En, Lang::De, Lang::Ja];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+            Lang::Ja => "ja",
+        }
+    }
+
+    /// The language of a tag like `de` or `de-AT`, or English for ones there are no names in.
+    pub fn from_tag(tag: &str) -> Self {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        Self::ALL
+            .into_iter()
+            .find(|lang| lang.as_str().eq_ignore_ascii_case(primary))
+            .unwrap_or_default()
+    }
+}
+
+impl<'de> Deserialize<'de> for Lang {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tag = String::deserialize(deserializer)?;
+        Ok(Self::from_tag(&tag))
+    }
+}
+
+/// The CSS named colors, without the duplicates (`aqua` is `Cyan`, `fuchsia` is `Magenta`,
+/// `grey` is `Gray`), written the way people say them.
+const CSS_COLORS: &[(&str, u32)] = &[
+    ("Alice Blue", 0xF0F8FF),
+    ("Antique White", 0xFAEBD7),
+    ("Aquamarine", 0x7FFFD4),
+    ("Azure", 0xF0FFFF),
+    ("Beige", 0xF5F5DC),
+    ("Bisque", 0xFFE4C4),
+    ("Black", 0x000000),
+    ("Blanched Almond", 0xFFEBCD),
+    ("Blue", 0x0000FF),
+    ("Blue Violet", 0x8A2BE2),
+    ("Brown", 0xA52A2A),
+    ("Burlywood", 0xDEB887),
+    ("Cadet Blue", 0x5F9EA0),
+    ("Chartreuse", 0x7FFF00),
+    ("Chocolate", 0xD2691E),
+    ("Coral", 0xFF7F50),
+    ("Cornflower Blue", 0x6495ED),
+    ("Cornsilk", 0xFFF8DC),
+    ("Crimson", 0xDC143C),
+    ("Cyan", 0x00FFFF),
+    ("Dark Blue", 0x00008B),
+    ("Dark Cyan", 0x008B8B),
+    ("Dark Goldenrod", 0xB8860B),
+    ("Dark Gray", 0xA9A9A9),
+    ("Dark Green", 0x006400),
+    ("Dark Khaki", 0xBDB76B),
+    ("Dark Magenta", 0x8B008B),
+    ("Dark Olive Green", 0x556B2F),
+    ("Dark Orange", 0xFF8C00),
+    ("Dark Orchid", 0x9932CC),
+    ("Dark Red", 0x8B0000),
+    ("Dark Salmon", 0xE9967A),
+    ("Dark Sea Green", 0x8FBC8F),
+    ("Dark Slate Blue", 0x483D8B),
+    ("Dark Slate Gray", 0x2F4F4F),
+    ("Dark Turquoise", 0x00CED1),
+    ("Dark Violet", 0x9400D3),
+    ("Deep Pink", 0xFF1493),
+    ("Deep Sky Blue", 0x00BFFF),
+    ("Dim Gray", 0x696969),
+    ("Dodger Blue", 0x1E90FF),
+    ("Firebrick", 0xB22222),
+    ("Floral White", 0xFFFAF0),
+    ("Forest Green", 0x228B22),
+    ("Gainsboro", 0xDCDCDC),
+    ("Ghost White", 0xF8F8FF),
+    ("Gold", 0xFFD700),
+    ("Goldenrod", 0xDAA520),
+    ("Gray", 0x808080),
+    ("Green", 0x008000),
+    ("Green Yellow", 0xADFF2F),
+    ("Honeydew", 0xF0FFF0),
+    ("Hot Pink", 0xFF69B4),
+    ("Indian Red", 0xCD5C5C),
+    ("Indigo", 0x4B0082),
+    ("Ivory", 0xFFFFF0),
+    ("Khaki", 0xF0E68C),
+    ("Lavender", 0xE6E6FA),
+    ("Lavender Blush", 0xFFF0F5),
+    ("Lawn Green", 0x7CFC00),
+    ("Lemon Chiffon", 0xFFFACD),
+    ("Light Blue", 0xADD8E6),
+    ("Light Coral", 0xF08080),
+    ("Light Cyan", 0xE0FFFF),
+    ("Light Goldenrod Yellow", 0xFAFAD2),
+    ("Light Gray", 0xD3D3D3),
+    ("Light Green", 0x90EE90),
+    ("Light Pink", 0xFFB6C1),
+    ("Light Salmon", 0xFFA07A),
+    ("Light Sea Green", 0x20B2AA),
+    ("Light Sky Blue", 0x87CEFA),
+    ("Light Slate Gray", 0x778899),
+    ("Light Steel Blue", 0xB0C4DE),
+    ("Light Yellow", 0xFFFFE0),
+    ("Lime", 0x00FF00),
+    ("Lime Green", 0x32CD32),
+    ("Linen", 0xFAF0E6),
+    ("Magenta", 0xFF00FF),
+    ("Maroon", 0x800000),
+    ("Medium Aquamarine", 0x66CDAA),
+    ("Medium Blue", 0x0000CD),
+    ("Medium Orchid", 0xBA55D3),
+    ("Medium Purple", 0x9370DB),
+    ("Medium Sea Green", 0x3CB371),
+    ("Medium Slate Blue", 0x7B68EE),
+    ("Medium Spring Green", 0x00FA9A),
+    ("Medium Turquoise", 0x48D1CC),
+    ("Medium Violet Red", 0xC71585),
+    ("Midnight Blue", 0x191970),
+    ("Mint Cream", 0xF5FFFA),
+    ("Misty Rose", 0xFFE4E1),
+    ("Moccasin", 0xFFE4B5),
+    ("Navajo White", 0xFFDEAD),
+    ("Navy", 0x000080),
+    ("Old Lace", 0xFDF5E6),
+    ("Olive", 0x808000),
+    ("Olive Drab", 0x6B8E23),
+    ("Orange", 0xFFA500),
+    ("Orange Red", 0xFF4500),
+    ("Orchid", 0xDA70D6),
+    ("Pale Goldenrod", 0xEEE8AA),
+    ("Pale Green", 0x98FB98),
+    ("Pale Turquoise", 0xAFEEEE),
+    ("Pale Violet Red", 0xDB7093),
+    ("Papaya Whip", 0xFFEFD5),
+    ("Peach Puff", 0xFFDAB9),
+    ("Peru", 0xCD853F),
+    ("Pink", 0xFFC0CB),
+    ("Plum", 0xDDA0DD),
+    ("Powder Blue", 0xB0E0E6),
+    ("Purple", 0x800080),
+    ("Rebecca Purple", 0x663399),
+    ("Red", 0xFF0000),
+    ("Rosy Brown", 0xBC8F8F),
+    ("Royal Blue", 0x4169E1),
+    ("Saddle Brown", 0x8B4513),
+    ("Salmon", 0xFA8072),
+    ("Sandy Brown", 0xF4A460),
+    ("Sea Green", 0x2E8B57),
+    ("Seashell", 0xFFF5EE),
+    ("Sienna", 0xA0522D),
+    ("Silver", 0xC0C0C0),
+    ("Sky Blue", 0x87CEEB),
+    ("Slate Blue", 0x6A5ACD),
+    ("Slate Gray", 0x708090),
+    ("Snow", 0xFFFAFA),
+    ("Spring Green", 0x00FF7F),
+    ("Steel Blue", 0x4682B4),
+    ("Tan", 0xD2B48C),
+    ("Teal", 0x008080),
+    ("Thistle", 0xD8BFD8),
+    ("Tomato", 0xFF6347),
+    ("Turquoise", 0x40E0D0),
+    ("Violet", 0xEE82EE),
+    ("Wheat", 0xF5DEB3),
+    ("White", 0xFFFFFF),
+    ("White Smoke", 0xF5F5F5),
+    ("Yellow", 0xFFFF00),
+    ("Yellow Green", 0x9ACD32),
+];
+
+/// [`CSS_COLORS`] in German, in the same order.
+const CSS_NAMES_DE: [&str; CSS_COLORS.len()] = [
+    "Aliceblau",
+    "Antikweiß",
+    "Aquamarin",
+    "Azurblau",
+    "Beige",
+    "Biskuit",
+    "Schwarz",
+    "Mandelweiß",
+    "Blau",
+    "Blauviolett",
+    "Braun",
+    "Gelbbraun",
+    "Kadettblau",
+    "Chartreuse",
+    "Schokolade",
+    "Koralle",
+    "Kornblumenblau",
+    "Maisseide",
+    "Karmesinrot",
+    "Cyan",
+    "Dunkelblau",
+    "Dunkelcyan",
+    "Dunkles Goldrutengelb",
+    "Dunkelgrau",
+    "Dunkelgrün",
+    "Dunkelkhaki",
+    "Dunkelmagenta",
+    "Dunkles Olivgrün",
+    "Dunkelorange",
+    "Dunkle Orchidee",
+    "Dunkelrot",
+    "Dunkles Lachsrosa",
+    "Dunkles Seegrün",
+    "Dunkles Schieferblau",
+    "Dunkles Schiefergrau",
+    "Dunkeltürkis",
+    "Dunkelviolett",
+    "Tiefrosa",
+    "Tiefes Himmelblau",
+    "Trübgrau",
+    "Dodgerblau",
+    "Ziegelrot",
+    "Blütenweiß",
+    "Waldgrün",
+    "Gainsboro",
+    "Geisterweiß",
+    "Gold",
+    "Goldrutengelb",
+    "Grau",
+    "Grün",
+    "Grüngelb",
+    "Honigmelone",
+    "Leuchtendes Rosa",
+    "Indischrot",
+    "Indigo",
+    "Elfenbein",
+    "Khaki",
+    "Lavendel",
+    "Lavendelrosa",
+    "Rasengrün",
+    "Zitronencreme",
+    "Hellblau",
+    "Helles Korallenrot",
+    "Hellcyan",
+    "Helles Goldrutengelb",
+    "Hellgrau",
+    "Hellgrün",
+    "Hellrosa",
+    "Helles Lachsrosa",
+    "Helles Seegrün",
+    "Helles Himmelblau",
+    "Helles Schiefergrau",
+    "Helles Stahlblau",
+    "Hellgelb",
+    "Limette",
+    "Limettengrün",
+    "Leinen",
+    "Magenta",
+    "Kastanienbraun",
+    "Mittleres Aquamarin",
+    "Mittelblau",
+    "Mittlere Orchidee",
+    "Mittleres Lila",
+    "Mittleres Seegrün",
+    "Mittleres Schieferblau",
+    "Mittleres Frühlingsgrün",
+    "Mitteltürkis",
+    "Mittleres Violettrot",
+    "Mitternachtsblau",
+    "Minzcreme",
+    "Altrosa",
+    "Mokassin",
+    "Navajoweiß",
+    "Marineblau",
+    "Alte Spitze",
+    "Oliv",
+    "Olivgrau",
+    "Orange",
+    "Orangerot",
+    "Orchidee",
+    "Blasses Goldrutengelb",
+    "Blassgrün",
+    "Blasstürkis",
+    "Blasses Violettrot",
+    "Papayacreme",
+    "Pfirsich",
+    "Peru",
+    "Rosa",
+    "Pflaume",
+    "Puderblau",
+    "Lila",
+    "Rebecca-Lila",
+    "Rot",
+    "Rosenholz",
+    "Königsblau",
+    "Sattelbraun",
+    "Lachs",
+    "Sandbraun",
+    "Seegrün",
+    "Muschelweiß",
+    "Siena",
+    "Silber",
+    "Himmelblau",
+    "Schieferblau",
+    "Schiefergrau",
+    "Schnee",
+    "Frühlingsgrün",
+    "Stahlblau",
+    "Hellbraun",
+    "Petrol",
+    "Distel",
+    "Tomate",
+    "Türkis",
+    "Violett",
+    "Weizen",
+    "Weiß",
+    "Rauchweiß",
+    "Gelb",
+    "Gelbgrün",
+];
+
+/// [`CSS_COLORS`] in Japanese, in the same order, mostly the katakana names Japanese CSS
+/// references use.
+const CSS_NAMES_JA: [&str; CSS_COLORS.len()] = [
+    "アリスブルー",
+    "アンティークホワイト",
+    "アクアマリン",
+    "アズール",
+    "ベージュ",
+    "ビスク",
+    "黒",
+    "ブランチドアーモンド",
+    "青",
+    "青紫",
+    "茶色",
+    "バーリーウッド",
+    "カデットブルー",
+    "シャルトリューズ",
+    "チョコレート",
+    "コーラル",
+    "コーンフラワーブルー",
+    "コーンシルク",
+    "クリムゾン",
+    "シアン",
+    "ダークブルー",
+    "ダークシアン",
+    "ダークゴールデンロッド",
+    "ダークグレー",
+    "ダークグリーン",
+    "ダークカーキ",
+    "ダークマゼンタ",
+    "ダークオリーブグリーン",
+    "ダークオレンジ",
+    "ダークオーキッド",
+    "ダークレッド",
+    "ダークサーモン",
+    "ダークシーグリーン",
+    "ダークスレートブルー",
+    "ダークスレートグレー",
+    "ダークターコイズ",
+    "ダークバイオレット",
+    "ディープピンク",
+    "ディープスカイブルー",
+    "ディムグレー",
+    "ドジャーブルー",
+    "ファイアブリック",
+    "フローラルホワイト",
+    "フォレストグリーン",
+    "ゲインズボロ",
+    "ゴーストホワイト",
+    "金色",
+    "ゴールデンロッド",
+    "灰色",
+    "緑",
+    "グリーンイエロー",
+    "ハニーデュー",
+    "ホットピンク",
+    "インディアンレッド",
+    "インディゴ",
+    "アイボリー",
+    "カーキ",
+    "ラベンダー",
+    "ラベンダーブラッシュ",
+    "ローングリーン",
+    "レモンシフォン",
+    "ライトブルー",
+    "ライトコーラル",
+    "ライトシアン",
+    "ライトゴールデンロッドイエロー",
+    "ライトグレー",
+    "ライトグリーン",
+    "ライトピンク",
+    "ライトサーモン",
+    "ライトシーグリーン",
+    "ライトスカイブルー",
+    "ライトスレートグレー",
+    "ライトスチールブルー",
+    "ライトイエロー",
+    "ライム",
+    "ライムグリーン",
+    "リネン",
+    "マゼンタ",
+    "マルーン",
+    "ミディアムアクアマリン",
+    "ミディアムブルー",
+    "ミディアムオーキッド",
+    "ミディアムパープル",
+    "ミディアムシーグリーン",
+    "ミディアムスレートブルー",
+    "ミディアムスプリンググリーン",
+    "ミディアムターコイズ",
+    "ミディアムバイオレットレッド",
+    "ミッドナイトブルー",
+    "ミントクリーム",
+    "ミスティローズ",
+    "モカシン",
+    "ナバホホワイト",
+    "紺色",
+    "オールドレース",
+    "オリーブ",
+    "オリーブドラブ",
+    "オレンジ",
+    "オレンジレッド",
+    "オーキッド",
+    "ペールゴールデンロッド",
+    "ペールグリーン",
+    "ペールターコイズ",
+    "ペールバイオレットレッド",
+    "パパイヤホイップ",
+    "ピーチパフ",
+    "ペルー",
+    "ピンク",
+    "プラム",
+    "パウダーブルー",
+    "紫",
+    "レベッカパープル",
+    "赤",
+    "ロージーブラウン",
+    "ロイヤルブルー",
+    "サドルブラウン",
+    "サーモン",
+    "サンディブラウン",
+    "シーグリーン",
+    "シーシェル",
+    "シエナ",
+    "銀色",
+    "スカイブルー",
+    "スレートブルー",
+    "スレートグレー",
+    "スノー",
+    "スプリンググリーン",
+    "スチールブルー",
+    "タン",
+    "ティール",
+    "シッスル",
+    "トマト",
+    "ターコイズ",
+    "バイオレット",
+    "ウィート",
+    "白",
+    "ホワイトスモーク",
+    "黄色",
+    "黄緑",
+];
+
+/// The Colors of the Year from 2000 on, as the sRGB values they're usually shown with.
+const PANTONE_ISH_COLORS: &[(&str, u32)] = &[
+    ("Cerulean", 0x9BB7D4),
+    ("Fuchsia Rose", 0xC74375),
+    ("True Red", 0xBF1932),
+    ("Aqua Sky", 0x7BC4C4),
+    ("Tigerlily", 0xE2583E),
+    ("Blue Turquoise", 0x53B0AE),
+    ("Sand Dollar", 0xDECDBE),
+    ("Chili Pepper", 0x9B1B30),
+    ("Blue Iris", 0x5A5B9F),
+    ("Mimosa", 0xF0C05A),
+    ("Turquoise", 0x45B5AA),
+    ("Honeysuckle", 0xD94F70),
+    ("Tangerine Tango", 0xDD4124),
+    ("Emerald", 0x009473),
+    ("Radiant Orchid", 0xB163A3),
+    ("Marsala", 0x955251),
+    ("Rose Quartz", 0xF7CAC9),
+    ("Serenity", 0x92A8D1),
+    ("Greenery", 0x88B04B),
+    ("Ultra Violet", 0x5F4B8B),
+    ("Living Coral", 0xFF6F61),
+    ("Classic Blue", 0x0F4C81),
+    ("Illuminating", 0xF5DF4D),
+    ("Ultimate Gray", 0x939597),
+    ("Very Peri", 0x6667AB),
+    ("Viva Magenta", 0xBB2649),
+    ("Peach Fuzz", 0xFFBE98),
+];
+
+/// Resonite's UI colors: the bright hero colors (which the default custom colors are), their
+/// darker mid versions and the neutrals.
+const RESONITE_COLORS: &[(&str, u32)] = &[
+    ("Hero Yellow", 0xF8F770),
+    ("Hero Green", 0x59EB5C),
+    ("Hero Red", 0xFF7676),
+    ("Hero Purple", 0xBA64F2),
+    ("Hero Cyan", 0x61D1FA),
+    ("Hero Orange", 0xE69E50),
+    ("Mid Yellow", 0x484A2C),
+    ("Mid Green", 0x24512C),
+    ("Mid Red", 0x5D323A),
+    ("Mid Purple", 0x492F64),
+    ("Mid Cyan", 0x284C5D),
+    ("Mid Orange", 0x48392A),
+    ("Neutral Dark", 0x11151D),
+    ("Neutral Mid", 0x86888B),
+    ("Neutral Light", 0xE1E1E0),
+];
+
+/// The named color closest to another one.
+#[derive(Debug, Clone, Copy)]
+pub struct NamedColor {
+    pub name: &'static str,
+    pub value: Argb,
+    /// How far apart the two are in CIELAB (ΔE 1976), 0 if it's exactly the named color.
+    /// Under about 2.3 is hard to tell apart
+    pub distance: f64,
+}
+
+/// The CSS named color that looks the most like `color`, named in `lang`.
+pub fn nearest(color: Argb, lang: Lang) -> NamedColor {
+    closest(color, ReferenceSet::Css, lang)
+        .next()
+        .expect("there are named colors")
+}
+
+/// Every color of `set`, the ones looking the most like `color` first, named in `lang`.
+pub fn closest(color: Argb, set: ReferenceSet, lang: Lang) -> impl Iterator<Item = NamedColor> {
+    let lab = Lab::from(color);
+    let names = set.translated_names(lang);
+    let mut matches: Vec<NamedColor> = set
+        .colors()
+        .iter()
+        .enumerate()
+        .map(|(i, &(name, value))| {
+            let name = names.map_or(name, |names| names[i]);
+            let value = Argb::new(255, (value >> 16) as u8, (value >> 8) as u8, value as u8);
+            let other = Lab::from(value);
+            let distance =
+                ((lab.l - other.l).powi(2) + (lab.a - other.a).powi(2) + (lab.b - other.b).powi(2))
+                    .sqrt();
+            NamedColor {
+                name,
+                value,
+                distance,
+            }
+        })
+        .collect();
+    matches.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+    matches.into_iter()
+}
@@ -0,0 +1,526 @@
+use std::{net::IpAddr, path::PathBuf, str::FromStr};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use figment::{
+    providers::{Env, Format, Serialized, Toml},
+    value::{Dict, Value},
+    Figment,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    discord,
+    formats::{self, FormatOptions},
+    input,
+    palette::{CustomColorSpec, ThemeType, DEFAULT_CUSTOM_COLORS},
+};
+
+/// Environment variables we read before there was a config file, still accepted as-is.
+const LEGACY_ENV_VARS: &[&str] = &[
+    "BIND_ADDRESS",
+    "PORT",
+    "MAX_CONCURRENT_REQUESTS",
+    "LOG_FORMAT",
+    "SENTRY_DSN",
+    "SENTRY_ENVIRONMENT",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+    "OTEL_TRACES_FILTER",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// One of the fixed colors appended after the scheme.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CustomColorConfig {
+    pub name: String,
+    /// Hex, same as `base_color`
+    pub value: String,
+    /// Whether to shift the color towards the source color
+    pub blend: bool,
+}
+
+impl CustomColorConfig {
+    pub fn parse_all(colors: &[Self]) -> anyhow::Result<Vec<CustomColorSpec>> {
+        colors
+            .iter()
+            .map(|color| {
+                Ok(CustomColorSpec {
+                    name: color.name.clone(),
+                    value: input::parse_color(&color.value)?,
+                    blend: color.blend,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Lets someone use the per-key features, like history.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKeyConfig {
+    /// What the key's data is stored under, so the key itself can be changed
+    pub name: String,
+    pub key: String,
+    /// Requests per day (UTC), instead of `default_daily_quota`
+    pub daily_quota: Option<i64>,
+    /// Every palette generated with the key gets posted to this Discord webhook
+    pub discord_webhook: Option<String>,
+}
+
+/// A Resonite account to write cloud variables with.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ResoniteConfig {
+    #[serde(default = "default_resonite_api_url")]
+    pub api_url: String,
+    /// `U-...`
+    pub user_id: String,
+    pub token: String,
+}
+
+pub fn default_resonite_api_url() -> String {
+    "https://api.resonite.com".to_string()
+}
+
+/// An MQTT broker that gets every channel's theme changes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// `{channel}` gets replaced with the channel's name
+    #[serde(default = "default_mqtt_topic")]
+    pub topic: String,
+    /// Only mirror these channels, every channel if empty
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub format: formats::Format,
+    #[serde(flatten)]
+    pub format_options: FormatOptions,
+    /// So devices that connect later still get the current theme
+    #[serde(default = "default_true")]
+    pub retain: bool,
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_mqtt_client_id() -> String {
+    "resomaterialyou".to_string()
+}
+
+fn default_mqtt_topic() -> String {
+    "resomaterialyou/theme/{channel}".to_string()
+}
+
+/// Something listening for OSC, like a lighting rig, that gets every channel's theme changes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OscConfig {
+    pub host: String,
+    #[serde(default = "default_osc_port")]
+    pub port: u16,
+    /// `{channel}` and `{color}` get replaced with the channel's and the color's name
+    #[serde(default = "default_osc_address")]
+    pub address: String,
+    /// Only send these channels, every channel if empty
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+fn default_osc_port() -> u16 {
+    9000
+}
+
+fn default_osc_address() -> String {
+    "/resomaterialyou/{channel}/{color}".to_string()
+}
+
+/// A Twitch chat whose `!theme <color>` commands change a channel's theme.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TwitchConfig {
+    /// The streamer's Twitch name
+    pub twitch_channel: String,
+    /// The theme channel chat controls
+    pub channel: String,
+    /// For commands that don't say `light` or `dark`
+    #[serde(default = "default_twitch_theme_type")]
+    pub theme_type: ThemeType,
+    /// How long after a change the next one is allowed
+    #[serde(default = "default_twitch_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// How long each viewer has to wait between their own changes
+    #[serde(default = "default_twitch_user_cooldown_secs")]
+    pub user_cooldown_secs: u64,
+    #[serde(default = "default_twitch_server")]
+    pub server: String,
+    /// Log in as this account instead of anonymously, needs `oauth_token` too
+    pub nick: Option<String>,
+    pub oauth_token: Option<String>,
+}
+
+fn default_twitch_theme_type() -> ThemeType {
+    ThemeType::Dark
+}
+
+fn default_twitch_cooldown_secs() -> u64 {
+    10
+}
+
+fn default_twitch_user_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_twitch_server() -> String {
+    "irc.chat.twitch.tv:6667".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// PEM files for serving HTTPS ourselves.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// Connection settings for standalone mode, for tuning to clients that poll a lot.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Keep HTTP/1 connections open between requests
+    pub keep_alive: bool,
+    /// How long clients get to send a request's headers
+    pub header_read_timeout_secs: u64,
+    /// Ping HTTP/2 clients this often to find dead connections, off if unset
+    pub http2_keep_alive_interval_secs: Option<u64>,
+    /// How long to wait for the answer to a ping before closing the connection
+    pub http2_keep_alive_timeout_secs: u64,
+    /// Requests one HTTP/2 connection can have going at once
+    pub http2_max_concurrent_streams: u32,
+    /// How long requests still going and writes in the background get to finish after a
+    /// shutdown signal, before the server exits anyway
+    pub shutdown_grace_secs: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive: true,
+            header_read_timeout_secs: 30,
+            http2_keep_alive_interval_secs: None,
+            http2_keep_alive_timeout_secs: 20,
+            http2_max_concurrent_streams: 200,
+            shutdown_grace_secs: 10,
+        }
+    }
+}
+
+/// How much work `/v1/seedColors` may do per image, trading quality against CPU.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ImageSeedConfig {
+    /// Images get averaged down to about this many pixels before quantizing (1024 to 262144)
+    pub max_pixels: u32,
+    /// Refining passes of the quantizer (1 to 20)
+    pub quantizer_iterations: i32,
+}
+
+impl Default for ImageSeedConfig {
+    fn default() -> Self {
+        Self {
+            max_pixels: 128 * 128,
+            quantizer_iterations: 5,
+        }
+    }
+}
+
+/// Parts of the API that have a replacement, announced to clients in `Deprecation` and
+/// `Sunset` headers once these are set.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeprecationConfig {
+    /// Since when the unversioned paths (like `/getPalette`) are deprecated in favor of `/v1`
+    pub unversioned_since: Option<DateTime<Utc>>,
+    /// When the unversioned paths stop working
+    pub unversioned_sunset: Option<DateTime<Utc>>,
+}
+
+/// Optional parts of the service that can be switched off.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Features {
+    /// `/metrics`
+    pub metrics: bool,
+    /// `/openapi.json` and `/docs`
+    pub docs: bool,
+    /// `/picker/{channel}`
+    pub picker: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            metrics: true,
+            docs: true,
+            picker: true,
+        }
+    }
+}
+
+/// Everything that can be configured.
+///
+/// Loaded from (later ones win) the defaults, `resomaterialyou.toml` (or the file in
+/// `RESOMATERIALYOU_CONFIG`), Shuttle secrets, and `RESOMATERIALYOU_*` environment variables.
+/// Nested keys use `__` in environment variables, like `RESOMATERIALYOU_FEATURES__DOCS=false`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Only used in standalone mode, Shuttle tells us where to listen
+    pub bind_address: IpAddr,
+    pub port: u16,
+    /// Serve HTTPS directly, for when there's no reverse proxy in front (standalone only)
+    pub tls: Option<TlsConfig>,
+    pub http: HttpConfig,
+    /// Requests running longer than this get a 408
+    pub request_timeout_secs: u64,
+    /// Requests beyond this many in flight get a 503
+    pub max_concurrent_requests: usize,
+    /// Request bodies bigger than this get a 413
+    pub max_body_bytes: usize,
+    /// Take clients' addresses from `X-Forwarded-For` for per-client limits, for when a
+    /// reverse proxy is in front. Anyone can send that header when there isn't one!
+    pub trust_forwarded_for: bool,
+    /// `max-age` for palette responses
+    pub cache_max_age_secs: u64,
+    /// Postgres for stored palettes (standalone only, Shuttle provides one)
+    pub database_url: Option<String>,
+    /// Redis to cache generated palettes in, shared between instances
+    pub redis_url: Option<String>,
+    /// How long palettes stay in Redis
+    pub redis_ttl_secs: u64,
+    /// Roughly how much memory recently used palettes may take up, 0 turns that off
+    pub memory_cache_bytes: usize,
+    /// Also cache the other theme type of every palette that gets generated, which costs
+    /// almost nothing since both come from the same theme
+    pub speculative_opposite: bool,
+    /// Seeds to generate both palettes of at startup, so they're cached right away
+    pub warm_seeds: Vec<String>,
+    /// Also generate this many of the most requested seeds at startup, if there's a database
+    pub warm_popular_seeds: i64,
+    /// Most seeds in one `/v1/getPalettes` request
+    pub max_batch_size: usize,
+    /// How many palettes of a batch get generated at once
+    pub batch_concurrency: usize,
+    pub custom_colors: Vec<CustomColorConfig>,
+    pub features: Features,
+    pub deprecations: DeprecationConfig,
+    pub image_seeds: ImageSeedConfig,
+    /// Bearer token for the `/admin` endpoints, which are disabled without one
+    pub admin_token: Option<String>,
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// Requests per day (UTC) for API keys without their own `daily_quota`, unlimited if unset
+    pub default_daily_quota: Option<i64>,
+    /// How many generated palettes to remember per API key
+    pub history_length: i64,
+    /// Default account for writing cloud variables, callers can also bring their own
+    pub resonite: Option<ResoniteConfig>,
+    pub mqtt: Option<MqttConfig>,
+    pub osc: Option<OscConfig>,
+    pub twitch: Option<TwitchConfig>,
+    pub log_format: LogFormat,
+    pub sentry_dsn: Option<String>,
+    pub sentry_environment: Option<String>,
+    /// Traces only get exported when this is set
+    pub otel_exporter_otlp_endpoint: Option<String>,
+    /// `EnvFilter` directives for exported traces
+    pub otel_traces_filter: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // Define some fixed colors to make people's lives easier
+        Self {
+            bind_address: IpAddr::from([0, 0, 0, 0]),
+            port: 8000,
+            tls: None,
+            http: HttpConfig::default(),
+            request_timeout_secs: 10,
+            max_concurrent_requests: 256,
+            max_body_bytes: 4 * 1024 * 1024,
+            trust_forwarded_for: false,
+            cache_max_age_secs: 604800,
+            database_url: None,
+            redis_url: None,
+            redis_ttl_secs: 86400,
+            memory_cache_bytes: 4 * 1024 * 1024,
+            speculative_opposite: true,
+            warm_seeds: Vec::new(),
+            warm_popular_seeds: 50,
+            max_batch_size: 256,
+            batch_concurrency: 4,
+            custom_colors: DEFAULT_CUSTOM_COLORS
+                .iter()
+                .map(|&(name, value, blend)| CustomColorConfig {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    blend,
+                })
+                .collect(),
+            features: Features::default(),
+            deprecations: DeprecationConfig::default(),
+            image_seeds: ImageSeedConfig::default(),
+            admin_token: None,
+            api_keys: Vec::new(),
+            default_daily_quota: None,
+            history_length: 50,
+            resonite: None,
+            mqtt: None,
+            osc: None,
+            twitch: None,
+            log_format: LogFormat::Text,
+            sentry_dsn: None,
+            sentry_environment: None,
+            otel_exporter_otlp_endpoint: None,
+            otel_traces_filter: "resomaterialyou=debug,tower_http=debug".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from the file and environment.
+    #[cfg_attr(feature = "shuttle", allow(dead_code))]
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_with_secrets(std::iter::empty())
+    }
+
+    /// Like [`Config::load`], with Shuttle secrets (or anything else flat) layered on top of the file.
+    pub fn load_with_secrets(
+        secrets: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<Self> {
+        let path = std::env::var("RESOMATERIALYOU_CONFIG")
+            .unwrap_or_else(|_| "resomaterialyou.toml".to_string());
+
+        // Parse secrets the same way figment parses environment variables, so numbers stay numbers
+        let secrets: Dict = secrets
+            .into_iter()
+            .map(|(key, value)| {
+                let value = Value::from_str(&value).unwrap_or_else(|v| match v {});
+                (key.to_lowercase(), value)
+            })
+            .collect();
+
+        let config: Config = Figment::from(Serialized::defaults(Config::default()))
+            .merge(Toml::file(&path))
+            .merge(Serialized::defaults(secrets))
+            .merge(Env::raw().only(LEGACY_ENV_VARS))
+            .merge(Env::prefixed("RESOMATERIALYOU_").split("__"))
+            .extract()
+            .with_context(|| format!("invalid configuration (config file: {})", path))?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.max_concurrent_requests > 0,
+            "max_concurrent_requests has to be at least 1"
+        );
+        anyhow::ensure!(
+            self.request_timeout_secs > 0,
+            "request_timeout_secs has to be at least 1"
+        );
+
+        anyhow::ensure!(
+            self.http.header_read_timeout_secs > 0,
+            "http.header_read_timeout_secs has to be at least 1"
+        );
+        anyhow::ensure!(
+            self.http.shutdown_grace_secs > 0,
+            "http.shutdown_grace_secs has to be at least 1"
+        );
+        anyhow::ensure!(self.history_length >= 0, "history_length can't be negative");
+        anyhow::ensure!(
+            (1..=4096).contains(&self.max_batch_size),
+            "max_batch_size has to be between 1 and 4096"
+        );
+        anyhow::ensure!(
+            (1..=64).contains(&self.batch_concurrency),
+            "batch_concurrency has to be between 1 and 64"
+        );
+        anyhow::ensure!(
+            (1024..=262_144).contains(&self.image_seeds.max_pixels),
+            "image_seeds.max_pixels has to be between 1024 and 262144"
+        );
+        anyhow::ensure!(
+            (1..=20).contains(&self.image_seeds.quantizer_iterations),
+            "image_seeds.quantizer_iterations has to be between 1 and 20"
+        );
+
+        for (i, key) in self.api_keys.iter().enumerate() {
+            anyhow::ensure!(
+                !key.key.is_empty(),
+                "API key {:?} has an empty key",
+                key.name
+            );
+            anyhow::ensure!(
+                !self.api_keys[..i]
+                    .iter()
+                    .any(|other| other.name == key.name),
+                "there's more than one API key named {:?}",
+                key.name
+            );
+            if let Some(url) = &key.discord_webhook {
+                anyhow::ensure!(
+                    discord::valid_webhook_url(url),
+                    "API key {:?} has a discord_webhook that isn't a Discord webhook URL",
+                    key.name
+                );
+            }
+        }
+
+        if let DeprecationConfig {
+            unversioned_since: Some(since),
+            unversioned_sunset: Some(sunset),
+        } = &self.deprecations
+        {
+            anyhow::ensure!(
+                sunset > since,
+                "deprecations.unversioned_sunset has to be after unversioned_since"
+            );
+        }
+
+        anyhow::ensure!(
+            self.warm_popular_seeds >= 0,
+            "warm_popular_seeds can't be negative"
+        );
+        for color in &self.warm_seeds {
+            input::parse_color(color)
+                .with_context(|| format!("warm_seeds has an invalid color {:?}", color))?;
+        }
+
+        for color in &self.custom_colors {
+            input::parse_color(&color.value).with_context(|| {
+                format!(
+                    "custom color {:?} has an invalid value {:?}",
+                    color.name, color.value
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+}
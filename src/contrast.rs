@@ -0,0 +1,290 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
+    routing::get,
+    Router,
+};
+use material_colors::{color::Argb, contrast};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::MaybeApiKey,
+    cache, http_cache,
+    palette::{Hex, Palette, ThemeType},
+    seed_source::{self, SeedQuery},
+    AppError, AppState,
+};
+
+/// WCAG's minimum ratios: AA for normal text, AA for large (or bold) text and AAA.
+const AA: f64 = 4.5;
+const AA_LARGE: f64 = 3.0;
+const AAA: f64 = 7.0;
+/// Colors that aren't for putting anything on.
+const NOT_BACKGROUNDS: [&str; 4] = ["outline", "outline_variant", "shadow", "scrim"];
+
+/// Contrast ratios between every on color and every background of a palette.
+#[derive(Serialize, ToSchema)]
+pub struct ContrastTable {
+    #[schema(example = "59eb5c")]
+    seed_color: String,
+    theme_type: ThemeType,
+    thresholds: Thresholds,
+    /// The columns of `matrix`
+    #[schema(example = json!(["primary", "primary_container", "surface"]))]
+    backgrounds: Vec<String>,
+    matrix: Vec<ContrastRow>,
+    /// The pairs the scheme is made for, like `on_primary` on `primary`
+    pairs: Vec<ContrastPair>,
+    /// Whether every pair in `pairs` reaches AA
+    passes: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Thresholds {
+    #[schema(example = 4.5)]
+    aa: f64,
+    #[schema(example = 3.0)]
+    aa_large: f64,
+    #[schema(example = 7.0)]
+    aaa: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ContrastRow {
+    #[schema(example = "on_primary")]
+    foreground: String,
+    /// The ratio with each of `backgrounds`, from 1 to 21
+    #[schema(example = json!([8.21, 1.9, 1.16]))]
+    ratios: Vec<f64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ContrastPair {
+    #[schema(example = "on_primary")]
+    foreground: String,
+    #[schema(example = "primary")]
+    background: String,
+    #[schema(example = 8.21)]
+    ratio: f64,
+    level: Level,
+}
+
+/// The best WCAG level a ratio reaches.
+#[derive(Serialize, ToSchema)]
+pub enum Level {
+    #[serde(rename = "AAA")]
+    Aaa,
+    #[serde(rename = "AA")]
+    Aa,
+    /// Only fine for large text
+    #[serde(rename = "AA-large")]
+    AaLarge,
+    #[serde(rename = "fail")]
+    Fail,
+}
+
+impl Level {
+    fn of(ratio: f64) -> Self {
+        if ratio >= AAA {
+            Self::Aaa
+        } else if ratio >= AA {
+            Self::Aa
+        } else if ratio >= AA_LARGE {
+            Self::AaLarge
+        } else {
+            Self::Fail
+        }
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/getPaletteContrast", get(get_palette_contrast))
+}
+
+/// Audits a whole palette at once: the contrast ratio of every on color (and the inverse
+/// colors) against every background, and the level each pair the scheme is made for reaches.
+#[utoipa::path(
+    get,
+    path = "/v1/getPaletteContrast",
+    params(SeedQuery),
+    responses(
+        (status = 200, description = "The contrast ratios", body = ContrastTable),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`base_color` is missing or isn't a hex color", body = String)
+    )
+)]
+pub async fn get_palette_contrast(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    headers: HeaderMap,
+    Query(query): Query<SeedQuery>,
+) -> Result<Response, AppError> {
+    let seed = match seed_source::resolve(
+        &state,
+        key.as_ref(),
+        query.seed,
+        query.base_color.as_deref(),
+    )
+    .await
+    {
+        Ok(seed) => seed,
+        Err(response) => return Ok(response),
+    };
+    let palette = cache::palette(&state, seed.color, query.theme_type).await?;
+
+    let table = contrast_table(&palette, seed.color, query.theme_type);
+    let max_age = if seed.deterministic {
+        state.config.cache_max_age_secs
+    } else {
+        0
+    };
+    Ok(http_cache::cacheable(
+        &headers,
+        serde_json::to_string(&table)?,
+        "application/json",
+        http_cache::Caching::for_caller(key.as_ref(), max_age),
+    ))
+}
+
+fn contrast_table(palette: &Palette, seed: Argb, theme_type: ThemeType) -> ContrastTable {
+    let (foregrounds, backgrounds): (Vec<_>, Vec<_>) = palette
+        .colors
+        .iter()
+        .filter(|(name, _)| !NOT_BACKGROUNDS.contains(&name.as_str()))
+        .partition(|(name, _)| is_foreground(name));
+
+    let matrix = foregrounds
+        .iter()
+        .map(|(foreground, fg)| ContrastRow {
+            foreground: foreground.clone(),
+            ratios: backgrounds
+                .iter()
+                .map(|(_, bg)| rounded(ratio(*fg, *bg)))
+                .collect(),
+        })
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (foreground, fg) in &foregrounds {
+        for (background, bg) in &backgrounds {
+            if is_made_for(foreground, background) {
+                // 4.496 isn't AA, even though it shows as 4.5
+                let ratio = ratio(*fg, *bg);
+                pairs.push(ContrastPair {
+                    foreground: foreground.clone(),
+                    background: background.clone(),
+                    ratio: rounded(ratio),
+                    level: Level::of(ratio),
+                });
+            }
+        }
+    }
+
+    ContrastTable {
+        seed_color: Hex(seed).to_string(),
+        theme_type,
+        thresholds: Thresholds {
+            aa: AA,
+            aa_large: AA_LARGE,
+            aaa: AAA,
+        },
+        backgrounds: backgrounds
+            .into_iter()
+            .map(|(name, _)| name.clone())
+            .collect(),
+        matrix,
+        passes: pairs
+            .iter()
+            .all(|pair| matches!(pair.level, Level::Aaa | Level::Aa)),
+        pairs,
+    }
+}
+
+fn is_foreground(name: &str) -> bool {
+    name.starts_with("on_") || name == "inverse_on_surface" || name == "inverse_primary"
+}
+
+/// Whether the scheme means `foreground` to go on `background`.
+fn is_made_for(foreground: &str, background: &str) -> bool {
+    match foreground {
+        "on_surface" | "on_surface_variant" => background.starts_with("surface"),
+        "inverse_on_surface" | "inverse_primary" => background == "inverse_surface",
+        _ => {
+            let Some(meant_for) = foreground.strip_prefix("on_") else {
+                return false;
+            };
+            // The fixed colors' on colors go on both the fixed and the dim one
+            let meant_for = match meant_for.strip_suffix("_variant") {
+                Some(fixed) if fixed.ends_with("_fixed") => fixed,
+                _ => meant_for,
+            };
+            if meant_for.ends_with("_fixed") {
+                background == meant_for || background == format!("{}_dim", meant_for)
+            } else {
+                background == meant_for
+            }
+        }
+    }
+}
+
+fn ratio(a: Argb, b: Argb) -> f64 {
+    contrast::ratio_of_tones(a.as_lstar(), b.as_lstar())
+}
+
+/// Two decimals are plenty to show.
+fn rounded(ratio: f64) -> f64 {
+    (ratio * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn on_colors_go_on_their_color() {
+        assert!(is_made_for("on_primary", "primary"));
+        assert!(is_made_for("on_primary_container", "primary_container"));
+        assert!(!is_made_for("on_primary", "primary_container"));
+        assert!(!is_made_for("on_primary", "secondary"));
+        assert!(is_made_for("on_surface", "surface_container_high"));
+        assert!(is_made_for("on_surface_variant", "surface"));
+        assert!(!is_made_for("on_surface", "primary"));
+    }
+
+    #[test]
+    fn fixed_on_colors_go_on_the_fixed_and_the_dim_one() {
+        for foreground in ["on_primary_fixed", "on_primary_fixed_variant"] {
+            assert!(is_made_for(foreground, "primary_fixed"), "{}", foreground);
+            assert!(
+                is_made_for(foreground, "primary_fixed_dim"),
+                "{}",
+                foreground
+            );
+            assert!(!is_made_for(foreground, "primary"), "{}", foreground);
+            assert!(!is_made_for(foreground, "tertiary_fixed"), "{}", foreground);
+        }
+        assert!(!is_made_for(
+            "on_primary_fixed_variant",
+            "primary_fixed_variant"
+        ));
+    }
+
+    #[test]
+    fn inverse_colors_go_on_the_inverse_surface() {
+        assert!(is_made_for("inverse_primary", "inverse_surface"));
+        assert!(is_made_for("inverse_on_surface", "inverse_surface"));
+        assert!(!is_made_for("inverse_primary", "primary"));
+        assert!(!is_made_for("inverse_on_surface", "surface"));
+    }
+
+    #[test]
+    fn levels_go_by_the_unrounded_ratio() {
+        assert!(matches!(Level::of(4.496), Level::AaLarge));
+        assert_eq!(rounded(4.496), 4.5);
+        assert!(matches!(Level::of(4.5), Level::Aa));
+        assert!(matches!(Level::of(7.0), Level::Aaa));
+        assert!(matches!(Level::of(2.99), Level::Fail));
+    }
+}
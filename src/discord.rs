@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::multipart::{Form, Part};
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::{
+    palette::{Palette, ThemeType},
+    render, AppState,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const IMAGE_NAME: &str = "palette.png";
+/// Shown as fields under the image, the rest are only in the image.
+const KEY_COLORS: &[&str] = &["primary", "secondary", "tertiary", "background"];
+/// How long the same palette from the same key isn't posted again, since every request for
+/// it (cache hits too) would post it otherwise
+const REPOST_AFTER: Duration = Duration::from_secs(10 * 60);
+/// Discord takes 30 messages a minute per webhook, leaving some for whatever else posts to it
+const POSTS_PER_MINUTE: u32 = 25;
+const WEBHOOK_WINDOW: Duration = Duration::from_secs(60);
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+#[derive(Default)]
+struct Recent {
+    /// When each (key, base color, theme type) was last posted
+    palettes: HashMap<(String, String, ThemeType), Instant>,
+    webhooks: HashMap<String, Window>,
+}
+
+/// What got posted lately, so the same palette isn't posted over and over and no webhook
+/// goes over Discord's limit.
+#[derive(Clone, Default)]
+pub struct Posts {
+    recent: Arc<Mutex<Recent>>,
+}
+
+impl Posts {
+    /// Counts a post, or says why it shouldn't happen.
+    fn admit(
+        &self,
+        key_name: &str,
+        webhook_url: &str,
+        base_color: &str,
+        theme_type: ThemeType,
+    ) -> Result<(), &'static str> {
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        let palette = (key_name.to_owned(), base_color.to_owned(), theme_type);
+        if let Some(posted) = recent.palettes.get(&palette) {
+            if now.duration_since(*posted) < REPOST_AFTER {
+                return Err("duplicate");
+            }
+        }
+
+        let window = recent
+            .webhooks
+            .entry(webhook_url.to_owned())
+            .or_insert(Window {
+                started: now,
+                count: 0,
+            });
+        if now.duration_since(window.started) >= WEBHOOK_WINDOW {
+            window.started = now;
+            window.count = 0;
+        }
+        if window.count >= POSTS_PER_MINUTE {
+            return Err("rate_limited");
+        }
+
+        window.count += 1;
+        recent.palettes.insert(palette, now);
+        Ok(())
+    }
+
+    /// Forgets palettes that may be posted again and webhook windows that are over.
+    pub fn remove_expired(&self) -> u64 {
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        let before = recent.palettes.len() + recent.webhooks.len();
+        recent
+            .palettes
+            .retain(|_, posted| now.duration_since(*posted) < REPOST_AFTER);
+        recent
+            .webhooks
+            .retain(|_, window| now.duration_since(window.started) < WEBHOOK_WINDOW);
+
+        (before - recent.palettes.len() - recent.webhooks.len()) as u64
+    }
+}
+
+/// We'd post anywhere otherwise, so only Discord's own webhook URLs are allowed in the config.
+pub fn valid_webhook_url(url: &str) -> bool {
+    [
+        "https://discord.com/api/webhooks/",
+        "https://discordapp.com/api/webhooks/",
+    ]
+    .iter()
+    .any(|prefix| url.starts_with(prefix))
+}
+
+/// Posts the palette to a Discord webhook as an embed with a swatch image, without making
+/// anyone wait for it. Skipped when `key_name` posted the same palette lately or the webhook
+/// is at its limit.
+pub fn post_palette(
+    state: &AppState,
+    key_name: &str,
+    webhook_url: String,
+    base_color: String,
+    theme_type: ThemeType,
+    palette: &Palette,
+) {
+    if let Err(reason) = state
+        .discord_posts
+        .admit(key_name, &webhook_url, &base_color, theme_type)
+    {
+        metrics::counter!("discord_posts_total", "result" => reason).increment(1);
+        debug!("Not posting #{} to Discord: {}", base_color, reason);
+        return;
+    }
+
+    let client = state.http.clone();
+    let swatches = palette.clone();
+    let fields: Vec<_> = palette
+        .colors
+        .iter()
+        .filter(|(name, _)| KEY_COLORS.contains(&name.as_str()))
+        .map(|(name, color)| json!({"name": name, "value": format!("`#{}`", color.to_hex()), "inline": true}))
+        .collect();
+
+    tokio::spawn(async move {
+        let result = async {
+            let image =
+                tokio::task::spawn_blocking(move || render::swatches(&swatches).to_png()).await??;
+            let embed = json!({
+                "title": format!("#{} ({})", base_color, theme_type.as_str()),
+                "color": u32::from_str_radix(&base_color, 16)?,
+                "image": {"url": format!("attachment://{}", IMAGE_NAME)},
+                "fields": fields,
+            });
+            let payload = json!({
+                "embeds": [embed],
+                "attachments": [{"id": 0, "filename": IMAGE_NAME}],
+            });
+            let form = Form::new().text("payload_json", payload.to_string()).part(
+                "files[0]",
+                Part::bytes(image)
+                    .file_name(IMAGE_NAME)
+                    .mime_str("image/png")?,
+            );
+
+            client
+                .post(&webhook_url)
+                .timeout(TIMEOUT)
+                .multipart(form)
+                .send()
+                .await?
+                .error_for_status()?;
+
+            anyhow::Ok(())
+        }
+        .await;
+
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        metrics::counter!("discord_posts_total", "result" => outcome).increment(1);
+        match result {
+            Ok(()) => debug!("Posted #{} to Discord", base_color),
+            Err(err) => warn!("Couldn't post #{} to Discord: {}", base_color, err),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEBHOOK: &str = "https://discord.com/api/webhooks/1/token";
+
+    #[test]
+    fn the_same_palette_is_posted_once() {
+        let posts = Posts::default();
+
+        assert!(posts
+            .admit("world", WEBHOOK, "59EB5C", ThemeType::Dark)
+            .is_ok());
+        assert_eq!(
+            posts.admit("world", WEBHOOK, "59EB5C", ThemeType::Dark),
+            Err("duplicate")
+        );
+        assert!(posts
+            .admit("world", WEBHOOK, "59EB5C", ThemeType::Light)
+            .is_ok());
+        assert!(posts
+            .admit("other", WEBHOOK, "59EB5C", ThemeType::Dark)
+            .is_ok());
+    }
+
+    #[test]
+    fn webhooks_stay_under_discords_limit() {
+        let posts = Posts::default();
+
+        for i in 0..POSTS_PER_MINUTE {
+            let color = format!("{:06X}", i);
+            assert!(posts
+                .admit("world", WEBHOOK, &color, ThemeType::Dark)
+                .is_ok());
+        }
+        assert_eq!(
+            posts.admit("world", WEBHOOK, "FFFFFF", ThemeType::Dark),
+            Err("rate_limited")
+        );
+        assert_eq!(posts.remove_expired(), 0);
+    }
+}
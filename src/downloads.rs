@@ -0,0 +1,133 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::Response,
+    routing::get,
+    Router,
+};
+
+use crate::{
+    auth::{ApiKey, MaybeApiKey},
+    cache, http_cache,
+    palette::Hex,
+    palette_files,
+    seed_source::{self, SeedQuery},
+    selection::Selection,
+    AppError, AppState,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/palette.ase", get(ase))
+        .route("/palette.gpl", get(gpl))
+}
+
+/// The palette as an Adobe Swatch Exchange file, for Photoshop and Illustrator.
+#[utoipa::path(
+    get,
+    path = "/v1/palette.ase",
+    params(SeedQuery, Selection),
+    responses(
+        (status = 200, description = "The swatches", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`base_color` is missing or isn't a hex color, or `fields` has a color that doesn't exist", body = String)
+    )
+)]
+pub async fn ase(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    headers: HeaderMap,
+    Query(query): Query<SeedQuery>,
+    Query(selection): Query<Selection>,
+) -> Result<Response, AppError> {
+    download(&state, key, &headers, &query, &selection, File::Ase).await
+}
+
+/// The palette as a GIMP palette, which GIMP, Krita and Inkscape can load.
+#[utoipa::path(
+    get,
+    path = "/v1/palette.gpl",
+    params(SeedQuery, Selection),
+    responses(
+        (status = 200, description = "The palette", body = String, content_type = "text/plain"),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`base_color` is missing or isn't a hex color, or `fields` has a color that doesn't exist", body = String)
+    )
+)]
+pub async fn gpl(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    headers: HeaderMap,
+    Query(query): Query<SeedQuery>,
+    Query(selection): Query<Selection>,
+) -> Result<Response, AppError> {
+    download(&state, key, &headers, &query, &selection, File::Gpl).await
+}
+
+#[derive(Clone, Copy)]
+enum File {
+    Ase,
+    Gpl,
+}
+
+async fn download(
+    state: &AppState,
+    key: Option<ApiKey>,
+    headers: &HeaderMap,
+    query: &SeedQuery,
+    selection: &Selection,
+    file: File,
+) -> Result<Response, AppError> {
+    let seed =
+        match seed_source::resolve(state, key.as_ref(), query.seed, query.base_color.as_deref())
+            .await
+        {
+            Ok(seed) => seed,
+            Err(response) => return Ok(response),
+        };
+
+    let palette = cache::palette(state, seed.color, query.theme_type).await?;
+    let palette = selection.apply(&palette, &state.custom_colors)?;
+
+    let theme = query.theme_type.as_str();
+    let title = format!("ResoMaterialYou {} {}", Hex(seed.color), theme);
+    let max_age = if seed.deterministic {
+        state.config.cache_max_age_secs
+    } else {
+        0
+    };
+    let caching = http_cache::Caching::for_caller(key.as_ref(), max_age);
+    let (mut response, extension) = match file {
+        File::Ase => (
+            http_cache::cacheable(
+                headers,
+                palette_files::ase(&palette, &title),
+                "application/octet-stream",
+                caching,
+            ),
+            "ase",
+        ),
+        File::Gpl => (
+            http_cache::cacheable(
+                headers,
+                palette_files::gpl(&palette, &title),
+                "text/plain; charset=utf-8",
+                caching,
+            ),
+            "gpl",
+        ),
+    };
+
+    let disposition = format!(
+        "attachment; filename=\"theme-{}-{}.{}\"",
+        Hex(seed.color),
+        theme.to_lowercase(),
+        extension
+    );
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition).expect("the file name is hex and a theme type"),
+    );
+
+    Ok(response)
+}
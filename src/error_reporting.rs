@@ -0,0 +1,87 @@
+use std::{sync::OnceLock, time::Duration};
+
+use axum::{extract::Request, http::header, middleware::Next, response::Response};
+use sentry::ClientInitGuard;
+
+use crate::{config::Config, telemetry};
+
+// Dropping the guard shuts the client down, so it has to live as long as the service
+static GUARD: OnceLock<ClientInitGuard> = OnceLock::new();
+
+/// Starts reporting errors and panics to Sentry, if `sentry_dsn` is set.
+pub fn init(config: &Config) {
+    let Some(dsn) = config.sentry_dsn.clone().filter(|dsn| !dsn.is_empty()) else {
+        return;
+    };
+
+    let guard = sentry::init((
+        dsn,
+        sentry::ClientOptions {
+            release: sentry::release_name!(),
+            environment: config.sentry_environment.clone().map(Into::into),
+            ..Default::default()
+        },
+    ));
+    let _ = GUARD.set(guard);
+}
+
+/// Sends an error to Sentry. Does nothing if Sentry isn't set up.
+pub fn capture(err: &anyhow::Error) {
+    sentry::integrations::anyhow::capture_anyhow(err);
+}
+
+/// Like [`capture`], for errors that aren't wrapped in `anyhow`.
+pub fn capture_error(err: &(dyn std::error::Error + 'static)) {
+    sentry::capture_error(err);
+}
+
+/// Tells Sentry about the request without the credentials in it, instead of everything
+/// `SentryHttpLayer` would send (which has to be outside of this).
+pub async fn redact_request(req: Request, next: Next) -> Response {
+    let url = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| {
+            format!("http://{}{}", host, telemetry::redacted_uri(req.uri()))
+                .parse()
+                .ok()
+        });
+    let headers = req
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = if telemetry::is_secret_header(name) {
+                "[redacted]"
+            } else {
+                value.to_str().unwrap_or_default()
+            };
+            (name.to_string(), value.to_owned())
+        })
+        .collect();
+    let request = sentry::protocol::Request {
+        method: Some(req.method().to_string()),
+        url,
+        headers,
+        ..Default::default()
+    };
+
+    sentry::configure_scope(|scope| {
+        if let Some(transaction) = scope.get_span() {
+            transaction.set_request(request.clone());
+        }
+        scope.add_event_processor(move |mut event| {
+            event.request = Some(request.clone());
+            Some(event)
+        });
+    });
+
+    next.run(req).await
+}
+
+/// Waits (briefly) for queued events to be sent, so they aren't lost on shutdown.
+pub fn flush() {
+    if let Some(guard) = GUARD.get() {
+        guard.flush(Some(Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,210 @@
+use std::{error::Error, fmt};
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use tracing::{debug, error, warn};
+
+use crate::{error_reporting, request_id};
+
+/// How long clients should wait before retrying an error that's likely to go away.
+const RETRY_AFTER_SECS: &str = "2";
+
+/// Something wrong with the request itself, so trying again won't help.
+#[derive(Debug, Clone)]
+pub struct InputError(String);
+
+impl InputError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+
+    pub fn invalid_color(field: &str) -> Self {
+        Self(format!("Invalid {}", field))
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for InputError {}
+
+impl IntoResponse for InputError {
+    fn into_response(self) -> Response {
+        debug!("Bad request: {}", self);
+        count("input");
+        (StatusCode::BAD_REQUEST, self.0).into_response()
+    }
+}
+
+/// A service we call, like Resonite, failing or not answering.
+#[derive(Debug, Clone)]
+pub struct UpstreamError {
+    pub service: &'static str,
+    message: String,
+    /// Whether the same call might work in a bit, like after a timeout or a 5xx
+    pub retryable: bool,
+}
+
+impl UpstreamError {
+    pub fn from_reqwest(service: &'static str, err: &reqwest::Error) -> Self {
+        let (message, retryable) = match err.status() {
+            Some(status) => (
+                format!("{} answered with {}", service, status),
+                status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            ),
+            None => (format!("Couldn't reach {}", service), true),
+        };
+
+        Self {
+            service,
+            message,
+            retryable,
+        }
+    }
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for UpstreamError {}
+
+impl IntoResponse for UpstreamError {
+    fn into_response(self) -> Response {
+        warn!("{}", self);
+        count("upstream");
+        metrics::counter!("upstream_errors_total", "service" => self.service).increment(1);
+        if self.retryable {
+            retry_later(self.message)
+        } else {
+            (StatusCode::BAD_GATEWAY, self.message).into_response()
+        }
+    }
+}
+
+/// The database failing. Running out of connections or losing them is retryable, the pool
+/// usually has one again soon.
+#[derive(Debug)]
+pub struct StorageError {
+    inner: anyhow::Error,
+    pub retryable: bool,
+}
+
+impl From<sqlx::Error> for StorageError {
+    fn from(err: sqlx::Error) -> Self {
+        let retryable = matches!(
+            err,
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_)
+        );
+
+        Self {
+            inner: err.into(),
+            retryable,
+        }
+    }
+}
+
+/// For stored rows that can't be read back, which no retry fixes.
+impl From<anyhow::Error> for StorageError {
+    fn from(err: anyhow::Error) -> Self {
+        Self {
+            inner: err,
+            retryable: false,
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Storage failed: {}", self.inner)
+    }
+}
+
+impl Error for StorageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.inner.as_ref())
+    }
+}
+
+impl IntoResponse for StorageError {
+    fn into_response(self) -> Response {
+        error!("{}", self);
+        error_reporting::capture_error(&self);
+        count("storage");
+        let message = with_request_id(&self);
+        if self.retryable {
+            retry_later(message)
+        } else {
+            (StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
+        }
+    }
+}
+
+/// Generating a palette or drawing an image failing. Only a string, so requests waiting on
+/// the same palette can all get it.
+#[derive(Debug, Clone)]
+pub struct RenderError(String);
+
+impl From<tokio::task::JoinError> for RenderError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self(format!("Generating the palette failed: {}", err))
+    }
+}
+
+impl From<anyhow::Error> for RenderError {
+    fn from(err: anyhow::Error) -> Self {
+        Self(format!("Generating the palette failed: {:#}", err))
+    }
+}
+
+impl From<png::EncodingError> for RenderError {
+    fn from(err: png::EncodingError) -> Self {
+        Self(format!("Encoding the image failed: {}", err))
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for RenderError {}
+
+impl IntoResponse for RenderError {
+    fn into_response(self) -> Response {
+        error!("{}", self);
+        error_reporting::capture_error(&self);
+        count("render");
+        (StatusCode::INTERNAL_SERVER_ERROR, with_request_id(&self)).into_response()
+    }
+}
+
+/// Counts an error response by what kind of error it was.
+pub fn count(kind: &'static str) {
+    metrics::counter!("errors_total", "kind" => kind).increment(1);
+}
+
+/// The message for errors that are on us, with the request ID to look them up by.
+pub fn with_request_id(err: &dyn fmt::Display) -> String {
+    match request_id::current() {
+        Some(id) => format!("Something went wrong: {} (request ID: {})", err, id),
+        None => format!("Something went wrong: {}", err),
+    }
+}
+
+fn retry_later(message: String) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, RETRY_AFTER_SECS)],
+        message,
+    )
+        .into_response()
+}
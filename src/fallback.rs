@@ -0,0 +1,181 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use utoipa::openapi::path::Operation;
+
+use crate::openapi;
+
+#[derive(Serialize)]
+struct Endpoint {
+    path: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    example: Option<&'static str>,
+}
+
+/// Endpoints that aren't in the OpenAPI spec, but people should know about.
+const UNDOCUMENTED: &[(&str, &str)] = &[
+    ("/getPalette", "Same as /v1/getPalette, for older worlds."),
+    ("/docs", "API documentation."),
+];
+
+/// Examples for the endpoints people look for the most.
+const EXAMPLES: &[(&str, &str)] = &[
+    (
+        "/v1/getPalette",
+        "/v1/getPalette?base_color=59EB5C&theme_type=Dark",
+    ),
+    (
+        "/getPalette",
+        "/getPalette?base_color=59EB5C&theme_type=Light",
+    ),
+    (
+        "/v1/palettes/{id}",
+        "/v1/palettes/4214f2f6-eb97-4fae-8dd8-18fabfddf24f",
+    ),
+    ("/p/{short_code}", "/p/BJnkEB2C"),
+    (
+        "/v1/stats/popular",
+        "/v1/stats/popular?days=7&theme_type=Dark",
+    ),
+];
+
+/// What we list in 404 responses: every documented endpoint but the admin ones, described by
+/// the first sentence of their docs.
+fn endpoints() -> &'static [Endpoint] {
+    static ENDPOINTS: OnceLock<Vec<Endpoint>> = OnceLock::new();
+    ENDPOINTS.get_or_init(|| {
+        let spec = openapi::spec();
+        let documented = spec
+            .paths
+            .paths
+            .iter()
+            .filter(|(path, _)| !path.starts_with("/admin"))
+            .map(|(path, item)| {
+                let description = item
+                    .operations
+                    .values()
+                    .find_map(first_sentence)
+                    .unwrap_or_default();
+                (path.clone(), description)
+            });
+        let undocumented = UNDOCUMENTED
+            .iter()
+            .map(|(path, description)| (path.to_string(), description.to_string()));
+
+        documented
+            .chain(undocumented)
+            .map(|(path, description)| Endpoint {
+                example: EXAMPLES
+                    .iter()
+                    .find(|(example_path, _)| *example_path == path)
+                    .map(|(_, example)| *example),
+                path,
+                description,
+            })
+            .collect()
+    })
+}
+
+/// utoipa makes the first line of the docs the summary, and the rest the description.
+fn first_sentence(operation: &Operation) -> Option<String> {
+    let summary = operation.summary.as_deref()?;
+    let rest = operation.description.as_deref().unwrap_or_default();
+    let paragraph = rest.split("\n\n").next().unwrap_or_default();
+    let docs = format!("{} {}", summary, paragraph.replace('\n', " "));
+
+    let end = docs.find(". ").map_or(docs.len(), |end| end + 1);
+    Some(docs[..end].trim().to_string())
+}
+
+#[derive(Serialize)]
+struct NotFound {
+    error: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    did_you_mean: Option<String>,
+    endpoints: &'static [Endpoint],
+}
+
+/// 404s with a list of endpoints, and a suggestion if the path only differs in capitalization
+/// (people type `getpalette` a lot).
+pub async fn not_found(uri: Uri) -> impl IntoResponse {
+    let path = uri.path();
+    let did_you_mean = endpoints()
+        .iter()
+        .find(|endpoint| {
+            endpoint
+                .path
+                .eq_ignore_ascii_case(path.trim_end_matches('/'))
+        })
+        .map(|endpoint| match uri.query() {
+            Some(query) => format!("{}?{}", endpoint.path, query),
+            None => endpoint.path.clone(),
+        });
+
+    (
+        StatusCode::NOT_FOUND,
+        Json(NotFound {
+            error: "not_found",
+            message: format!("There's nothing at {}", path),
+            did_you_mean,
+            endpoints: endpoints(),
+        }),
+    )
+}
+
+#[derive(Serialize)]
+struct MethodNotAllowed<'a> {
+    error: &'static str,
+    message: String,
+    allowed_methods: Vec<&'a str>,
+}
+
+/// Gives 405s a body saying which methods would have worked.
+///
+/// axum only adds the `Allow` header after a route's layers have run, so this has to wrap
+/// the whole router instead of being one of its layers.
+pub fn explain_method_not_allowed(router: Router) -> Router {
+    Router::new()
+        .fallback_service(router)
+        .layer(middleware::from_fn(method_not_allowed))
+}
+
+async fn method_not_allowed(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+
+    let response = next.run(req).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let (mut parts, _) = response.into_parts();
+    let allow = parts
+        .headers
+        .get(header::ALLOW)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let body = serde_json::to_vec(&MethodNotAllowed {
+        error: "method_not_allowed",
+        message: format!("{} isn't supported on {}", method, path),
+        allowed_methods: allow.split(',').filter(|m| !m.is_empty()).collect(),
+    })
+    .expect("serializing strings can't fail");
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    Response::from_parts(parts, Body::from(body))
+}
@@ -0,0 +1,389 @@
+use std::{fmt::Write, str::FromStr};
+
+use serde::{Deserialize, Serialize, Serializer};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    color_names::{self, Lang},
+    palette::{Hex, Palette, SCHEMA_VERSION},
+};
+
+/// How a palette gets written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// 6 digit hex values with nothing in between, what worlds parse
+    #[default]
+    Raw,
+    /// `{"schema_version": 1, "colors": {"primary": "ffb4a8", ...}}`, in the same order as
+    /// `raw`
+    Json,
+    /// One `Space/ColorName|FFB4A8` line per color, for dynamic variable writers
+    Dynvar,
+    /// One hex value per line, which ProtoFlux can split without any offsets
+    Lines,
+    /// `primary:A2D398 onPrimary:0C390E ...`, for seeing which color is which while debugging
+    Annotated,
+    /// An Android `colors.xml` with `md_theme_dark_*` and `md_theme_light_*` colors
+    #[serde(rename = "android-xml")]
+    AndroidXml,
+}
+
+/// Settings only some formats use.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct FormatOptions {
+    /// Dynamic variable space the `dynvar` lines start with, defaults to `Theme`
+    #[param(example = "Theme")]
+    pub dynvar_space: Option<String>,
+    /// With `lines`, puts each color's name in front of it, like `primary=ffb4a8`
+    #[serde(default)]
+    pub names: bool,
+    /// With `json`, adds `color_names` with the closest named color to each one, like
+    /// `"primary": "Dark Sea Green"`
+    #[serde(default)]
+    pub color_names: bool,
+    /// The language of `color_names`, `en` by default and for languages there are no names in
+    #[serde(default)]
+    pub lang: Lang,
+}
+
+const DEFAULT_DYNVAR_SPACE: &str = "Theme";
+
+impl FormatOptions {
+    pub fn dynvar_space(&self) -> &str {
+        self.dynvar_space.as_deref().unwrap_or(DEFAULT_DYNVAR_SPACE)
+    }
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::Raw,
+        Format::Json,
+        Format::Dynvar,
+        Format::Lines,
+        Format::Annotated,
+        Format::AndroidXml,
+    ];
+
+    /// What goes in the `format` parameter.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Raw => "raw",
+            Format::Json => "json",
+            Format::Dynvar => "dynvar",
+            Format::Lines => "lines",
+            Format::Annotated => "annotated",
+            Format::AndroidXml => "android-xml",
+        }
+    }
+
+    /// The implementation registered under this format's name.
+    pub fn formatter(self) -> &'static dyn OutputFormatter {
+        formatter(self.name()).expect("every format has a formatter")
+    }
+
+    pub fn content_type(self) -> &'static str {
+        self.formatter().content_type()
+    }
+
+    pub fn render(self, palette: &Palette, options: &FormatOptions) -> String {
+        self.formatter().render(palette, options)
+    }
+
+    /// Whether this format has the dark and the light theme in one file, like `android-xml`.
+    /// Those are written with [`Format::render_both`].
+    pub fn both_themes(self) -> bool {
+        self.formatter().both_themes()
+    }
+
+    pub fn render_both(self, dark: &Palette, light: &Palette, options: &FormatOptions) -> String {
+        self.formatter().render_both(dark, light, options)
+    }
+}
+
+/// Writes palettes in one format. A new format is an implementation of this, added to
+/// [`FORMATTERS`] under the name its [`Format`] variant has.
+pub trait OutputFormatter: Sync {
+    fn name(&self) -> &'static str;
+
+    fn content_type(&self) -> &'static str {
+        "text/plain; charset=utf-8"
+    }
+
+    fn render(&self, palette: &Palette, options: &FormatOptions) -> String;
+
+    /// Formats that write both themes into one file say so here, and get both through
+    /// [`OutputFormatter::render_both`].
+    fn both_themes(&self) -> bool {
+        false
+    }
+
+    /// Only called on formats where [`OutputFormatter::both_themes`] is true.
+    fn render_both(&self, dark: &Palette, light: &Palette, options: &FormatOptions) -> String {
+        let _ = light;
+        self.render(dark, options)
+    }
+}
+
+/// Every format there is.
+pub static FORMATTERS: &[&dyn OutputFormatter] =
+    &[&Raw, &Json, &Dynvar, &Lines, &Annotated, &AndroidXml];
+
+/// The formatter with this name, like `json`.
+pub fn formatter(name: &str) -> Option<&'static dyn OutputFormatter> {
+    FORMATTERS
+        .iter()
+        .copied()
+        .find(|formatter| formatter.name() == name)
+}
+
+struct Raw;
+
+impl OutputFormatter for Raw {
+    fn name(&self) -> &'static str {
+        "raw"
+    }
+
+    fn render(&self, palette: &Palette, _: &FormatOptions) -> String {
+        palette.to_wire_string()
+    }
+}
+
+struct Json;
+
+#[derive(Serialize)]
+struct JsonPalette<'a, P> {
+    schema_version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params: Option<&'a P>,
+    colors: &'a Palette,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color_names: Option<ColorNames<'a>>,
+}
+
+/// Each color's name with the name of the named color closest to it, in palette order.
+struct ColorNames<'a>(&'a Palette, Lang);
+
+impl Serialize for ColorNames<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.0
+                .colors
+                .iter()
+                .map(|(name, color)| (name, color_names::nearest(*color, self.1).name)),
+        )
+    }
+}
+
+impl OutputFormatter for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn render(&self, palette: &Palette, options: &FormatOptions) -> String {
+        to_json::<()>(palette, None, options)
+    }
+}
+
+/// `format=json` with what the palette was generated from in `params`, between the schema
+/// version and the colors.
+pub fn json_with_params(
+    palette: &Palette,
+    params: &impl Serialize,
+    options: &FormatOptions,
+) -> String {
+    to_json(palette, Some(params), options)
+}
+
+fn to_json<P: Serialize>(palette: &Palette, params: Option<&P>, options: &FormatOptions) -> String {
+    // `{"schema_version":1,"colors":{` plus `"name":"ffb4a8",` for each color, with names
+    // about this long
+    let mut json = Vec::with_capacity(36 + palette.colors.len() * 40);
+    let palette = JsonPalette {
+        schema_version: SCHEMA_VERSION,
+        params,
+        colors: palette,
+        color_names: options
+            .color_names
+            .then_some(ColorNames(palette, options.lang)),
+    };
+    serde_json::to_writer(&mut json, &palette).expect("palettes always serialize");
+    String::from_utf8(json).expect("serde_json writes UTF-8")
+}
+
+struct Dynvar;
+
+impl OutputFormatter for Dynvar {
+    fn name(&self) -> &'static str {
+        "dynvar"
+    }
+
+    fn render(&self, palette: &Palette, options: &FormatOptions) -> String {
+        let space = options.dynvar_space();
+        let mut lines = String::with_capacity(palette.colors.len() * (space.len() + 32));
+        for (name, color) in &palette.colors {
+            lines.push_str(space);
+            lines.push('/');
+            write_dynvar_name(&mut lines, name);
+            writeln!(lines, "|{:X}", Hex(*color)).expect("writing to a String can't fail");
+        }
+
+        lines
+    }
+}
+
+struct Lines;
+
+impl OutputFormatter for Lines {
+    fn name(&self) -> &'static str {
+        "lines"
+    }
+
+    fn render(&self, palette: &Palette, options: &FormatOptions) -> String {
+        let mut lines = String::with_capacity(palette.colors.len() * 32);
+        for (name, color) in &palette.colors {
+            if options.names {
+                lines.push_str(name);
+                lines.push('=');
+            }
+            writeln!(lines, "{}", Hex(*color)).expect("writing to a String can't fail");
+        }
+
+        lines
+    }
+}
+
+struct Annotated;
+
+impl OutputFormatter for Annotated {
+    fn name(&self) -> &'static str {
+        "annotated"
+    }
+
+    fn render(&self, palette: &Palette, _: &FormatOptions) -> String {
+        let mut annotated = String::with_capacity(palette.colors.len() * 32);
+        for (i, (name, color)) in palette.colors.iter().enumerate() {
+            if i > 0 {
+                annotated.push(' ');
+            }
+            write_camel_case(&mut annotated, name);
+            write!(annotated, ":{:X}", Hex(*color)).expect("writing to a String can't fail");
+        }
+
+        annotated
+    }
+}
+
+/// Has both themes, like the Material Theme Builder's export. With only one theme to go on its
+/// colors are `md_theme_*`.
+struct AndroidXml;
+
+impl OutputFormatter for AndroidXml {
+    fn name(&self) -> &'static str {
+        "android-xml"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/xml"
+    }
+
+    fn render(&self, palette: &Palette, _: &FormatOptions) -> String {
+        write_android_xml(&[("md_theme_", palette)])
+    }
+
+    fn both_themes(&self) -> bool {
+        true
+    }
+
+    fn render_both(&self, dark: &Palette, light: &Palette, _: &FormatOptions) -> String {
+        android_xml(dark, light)
+    }
+}
+
+/// A `colors.xml` with both themes, named like the Material Theme Builder's export:
+/// `md_theme_dark_onPrimary`, `md_theme_light_onPrimary` and so on.
+pub fn android_xml(dark: &Palette, light: &Palette) -> String {
+    write_android_xml(&[("md_theme_dark_", dark), ("md_theme_light_", light)])
+}
+
+fn write_android_xml(themes: &[(&str, &Palette)]) -> String {
+    let colors: usize = themes.iter().map(|(_, palette)| palette.colors.len()).sum();
+    let mut xml = String::with_capacity(64 + colors * 64);
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<resources>\n");
+    for (prefix, palette) in themes {
+        for (name, color) in &palette.colors {
+            xml.push_str("    <color name=\"");
+            xml.push_str(prefix);
+            write_camel_case(&mut xml, name);
+            writeln!(xml, "\">#{:X}</color>", Hex(*color)).expect("writing to a String can't fail");
+        }
+    }
+    xml.push_str("</resources>\n");
+
+    xml
+}
+
+/// Parses what [`Format::name`] returns.
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("unknown format {:?}", s))
+    }
+}
+
+/// `on_primary_container` to `OnPrimaryContainer`, the way DynVars usually get named.
+pub fn dynvar_name(name: &str) -> String {
+    let mut dynvar = String::with_capacity(name.len());
+    write_dynvar_name(&mut dynvar, name);
+    dynvar
+}
+
+/// `on_primary_container` to `onPrimaryContainer`.
+fn write_camel_case(out: &mut String, name: &str) {
+    let start = out.len();
+    write_dynvar_name(out, name);
+    if let Some(first) = out[start..].chars().next() {
+        let lower = first.to_lowercase().to_string();
+        out.replace_range(start..start + first.len_utf8(), &lower);
+    }
+}
+
+fn write_dynvar_name(out: &mut String, name: &str) {
+    for word in name.split('_') {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.extend(first.to_uppercase());
+            out.push_str(chars.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// [`Format::formatter`] panics for a format without one, so every format needs one under
+    /// its own name, spelled the same way serde and `FromStr` spell it.
+    #[test]
+    fn every_format_has_a_formatter() {
+        for format in Format::ALL {
+            let formatter = formatter(format.name());
+            assert!(formatter.is_some(), "{} has no formatter", format.name());
+            assert_eq!(formatter.unwrap().name(), format.name());
+
+            assert_eq!(format.name().parse::<Format>().unwrap(), format);
+            let json = serde_json::to_string(&format).unwrap();
+            assert_eq!(json, format!("\"{}\"", format.name()));
+        }
+        assert_eq!(FORMATTERS.len(), Format::ALL.len());
+    }
+}
@@ -0,0 +1,128 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{http_cache, palette::ThemeType, AppError, AppState};
+
+/// Seeds picked by hand for making good themes, as name and hex value.
+const CURATED_SEEDS: [(&str, &str); 24] = [
+    ("Hero Green", "59EB5C"),
+    ("Sunset", "FF7A59"),
+    ("Lagoon", "2BB3C0"),
+    ("Lavender Fields", "9C88FF"),
+    ("Cherry Blossom", "F49AC2"),
+    ("Forest Floor", "4E7D3A"),
+    ("Desert Sand", "D8A25E"),
+    ("Deep Ocean", "1F4E9A"),
+    ("Mint", "6FE3B4"),
+    ("Ember", "C8381E"),
+    ("Golden Hour", "F2B632"),
+    ("Plum", "7E3F8F"),
+    ("Glacier", "A7D8F0"),
+    ("Moss", "8A9A5B"),
+    ("Coral Reef", "FF6F61"),
+    ("Midnight", "2C2F6B"),
+    ("Citrus", "C6E03A"),
+    ("Rosewood", "9E4A5A"),
+    ("Teal", "008080"),
+    ("Neon Pink", "FF3EA5"),
+    ("Amber", "FFB000"),
+    ("Storm", "5B6C7D"),
+    ("Orchid", "B565D8"),
+    ("Sky", "4FA3FF"),
+];
+/// Seeds in the gallery at a time, so it goes through all of them every three weeks.
+const SEEDS_PER_WEEK: usize = 8;
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GalleryQuery {
+    /// Which theme the links are for, `Dark` by default
+    theme_type: Option<ThemeType>,
+}
+
+/// This week's seeds.
+#[derive(Serialize, ToSchema)]
+pub struct Gallery {
+    seeds: Vec<GallerySeed>,
+    /// When the next seeds come in
+    rotates_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GallerySeed {
+    #[schema(example = "Sunset")]
+    name: &'static str,
+    #[schema(example = "FF7A59")]
+    base_color: &'static str,
+    /// A card drawn with the palette, to show next to the name
+    #[schema(example = "/v1/preview/card.png?base_color=FF7A59&theme_type=Dark")]
+    preview_url: String,
+    #[schema(example = "/v1/getPalette?base_color=FF7A59&theme_type=Dark")]
+    palette_url: String,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/gallery", get(gallery))
+}
+
+/// A few hand-picked seeds to start from, different every week (Monday, UTC), with a preview
+/// image and the palette of each one.
+#[utoipa::path(
+    get,
+    path = "/v1/gallery",
+    params(GalleryQuery),
+    responses(
+        (status = 200, description = "This week's seeds", body = Gallery),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`")
+    )
+)]
+pub async fn gallery(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<GalleryQuery>,
+) -> Result<Response, AppError> {
+    let theme_type = query.theme_type.unwrap_or(ThemeType::Dark);
+    let now = Utc::now();
+    // The epoch was a Thursday, so weeks starting on Monday are 3 days ahead
+    let week = (now.timestamp().div_euclid(DAY_SECS) + 3).div_euclid(7);
+    let rotates_at = DateTime::from_timestamp(((week + 1) * 7 - 3) * DAY_SECS, 0)
+        .expect("next Monday is a valid date");
+
+    let start = week as usize * SEEDS_PER_WEEK;
+    let seeds = (start..start + SEEDS_PER_WEEK)
+        .map(|i| {
+            let (name, base_color) = CURATED_SEEDS[i % CURATED_SEEDS.len()];
+            let query = format!(
+                "base_color={}&theme_type={}",
+                base_color,
+                theme_type.as_str()
+            );
+            GallerySeed {
+                name,
+                base_color,
+                preview_url: format!("/v1/preview/card.png?{}", query),
+                palette_url: format!("/v1/getPalette?{}", query),
+            }
+        })
+        .collect();
+
+    // Nobody should see last week's seeds from a cache
+    let max_age = (rotates_at - now)
+        .num_seconds()
+        .clamp(0, state.config.cache_max_age_secs as i64) as u64;
+    Ok(http_cache::cacheable(
+        &headers,
+        serde_json::to_string(&Gallery { seeds, rotates_at })?,
+        "application/json",
+        http_cache::Caching::public(max_age),
+    ))
+}
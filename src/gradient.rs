@@ -0,0 +1,156 @@
+use std::fmt::Write;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+    auth::MaybeApiKey,
+    cache,
+    errors::InputError,
+    http_cache,
+    palette::Hex,
+    seed_source::{self, SeedQuery},
+    selection::Selection,
+    AppError, AppState,
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GradientQuery {
+    /// The colors to go through, at least two, like `primary,tertiary,primaryContainer`.
+    /// Names can be written like `primary_container` too
+    #[param(example = "primary,tertiary,primaryContainer")]
+    stops: String,
+    /// Which way the gradient goes in degrees, 180 (top to bottom) by default
+    #[param(example = 135.0)]
+    angle: Option<f64>,
+    /// Where each stop is in percent, like `0,40,100`. Spread evenly without
+    #[param(example = "0,40,100")]
+    positions: Option<String>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/gradient.css", get(gradient))
+}
+
+/// A CSS `linear-gradient(...)` through colors of the palette, for web companions and OBS
+/// overlays.
+#[utoipa::path(
+    get,
+    path = "/v1/gradient.css",
+    params(SeedQuery, GradientQuery),
+    responses(
+        (status = 200, description = "The gradient", body = String, content_type = "text/css",
+            example = json!("linear-gradient(135deg, #A2D398 0%, #A0CFD3 40%, #255023 100%)")),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`base_color` is missing or isn't a hex color, a stop isn't a color of the palette, or `positions` doesn't fit the stops", body = String)
+    )
+)]
+pub async fn gradient(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    headers: HeaderMap,
+    Query(seed_query): Query<SeedQuery>,
+    Query(query): Query<GradientQuery>,
+) -> Result<Response, AppError> {
+    let stops = Selection {
+        include_customs: true,
+        only: None,
+        fields: Some(query.stops.clone()),
+    };
+    let positions = match &query.positions {
+        Some(positions) => Some(parse_positions(positions)?),
+        None => None,
+    };
+    let angle = query.angle.unwrap_or(180.0);
+    if !angle.is_finite() {
+        return Err(InputError::new("angle has to be a number of degrees").into());
+    }
+
+    let seed = match seed_source::resolve(
+        &state,
+        key.as_ref(),
+        seed_query.seed,
+        seed_query.base_color.as_deref(),
+    )
+    .await
+    {
+        Ok(seed) => seed,
+        Err(response) => return Ok(response),
+    };
+    let palette = cache::palette(&state, seed.color, seed_query.theme_type).await?;
+    let colors = stops.apply(&palette, &state.custom_colors)?;
+
+    let count = colors.colors.len();
+    if count < 2 {
+        return Err(InputError::new("A gradient needs at least two stops").into());
+    }
+    let positions = match positions {
+        Some(positions) if positions.len() != count => {
+            let message = format!(
+                "There are {} stops but {} positions",
+                count,
+                positions.len()
+            );
+            return Err(InputError::new(message).into());
+        }
+        Some(positions) => positions,
+        None => (0..count)
+            .map(|i| i as f64 * 100.0 / (count - 1) as f64)
+            .collect(),
+    };
+
+    let mut css = format!("linear-gradient({}deg", angle);
+    for ((_, color), position) in colors.colors.iter().zip(positions) {
+        write!(
+            css,
+            ", #{:X} {}%",
+            Hex(*color),
+            (position * 100.0).round() / 100.0
+        )
+        .expect("writing to a String can't fail");
+    }
+    css.push(')');
+
+    let max_age = if seed.deterministic {
+        state.config.cache_max_age_secs
+    } else {
+        0
+    };
+    Ok(http_cache::cacheable(
+        &headers,
+        css,
+        "text/css; charset=utf-8",
+        http_cache::Caching::for_caller(key.as_ref(), max_age),
+    ))
+}
+
+/// Percentages from 0 to 100 that never go back.
+fn parse_positions(positions: &str) -> Result<Vec<f64>, InputError> {
+    let invalid = || InputError::new("positions have to be percentages from 0 to 100, in order");
+
+    let positions = positions
+        .split(',')
+        .map(|position| {
+            position
+                .trim()
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .filter(|position| (0.0..=100.0).contains(position))
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if positions.windows(2).any(|pair| pair[1] < pair[0]) {
+        return Err(invalid());
+    }
+
+    Ok(positions)
+}
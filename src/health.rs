@@ -0,0 +1,98 @@
+use std::{future::Future, time::Duration};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use material_colors::{color::Argb, theme::ThemeBuilder};
+use serde::Serialize;
+use tokio::time;
+
+use crate::AppState;
+
+/// How long the database and Redis get to answer before the instance counts as not ready.
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    checks: Vec<Check>,
+}
+
+/// Liveness and readiness probes. These get merged outside of the load shedding layers,
+/// so a busy instance doesn't get restarted for being busy.
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+}
+
+/// Liveness probe; if this answers, the process is up.
+#[utoipa::path(get, path = "/healthz", responses((status = 200, body = String)))]
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Readiness probe, with the result of each check. The database and Redis are only checked
+/// when they're configured.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Ready to serve requests"),
+        (status = 503, description = "At least one check failed")
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let mut checks = vec![check_theme_generation()];
+    if let Some(storage) = &state.storage {
+        checks.push(ping("database", storage.ping()).await);
+    }
+    if let Some(cache) = &state.cache {
+        checks.push(ping("redis", cache.ping()).await);
+    }
+    let ready = checks.iter().all(|check| check.ok);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(Readiness { ready, checks }))
+}
+
+/// Makes sure we can actually build a theme, which is the whole point of the service.
+fn check_theme_generation() -> Check {
+    let theme = ThemeBuilder::with_source(Argb::new(255, 0x59, 0xEB, 0x5C)).build();
+    let ok = theme.schemes.dark.primary != theme.schemes.light.primary;
+
+    Check {
+        name: "theme_generation",
+        ok,
+        detail: (!ok).then(|| "light and dark schemes came out identical".to_string()),
+    }
+}
+
+/// Waits at most [`PING_TIMEOUT`] for `ping` to answer.
+async fn ping<E: std::fmt::Display>(
+    name: &'static str,
+    ping: impl Future<Output = Result<(), E>>,
+) -> Check {
+    let detail = match time::timeout(PING_TIMEOUT, ping).await {
+        Ok(Ok(())) => None,
+        Ok(Err(err)) => Some(err.to_string()),
+        Err(_) => Some(format!("no answer within {:?}", PING_TIMEOUT)),
+    };
+
+    Check {
+        name,
+        ok: detail.is_none(),
+        detail,
+    }
+}
@@ -0,0 +1,149 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+use crate::auth::ApiKey;
+
+/// Who may keep a response, and for how long.
+#[derive(Debug, Clone, Copy)]
+pub struct Caching {
+    max_age_secs: u64,
+    /// Only the client's own cache, since the response depends on who asked
+    private: bool,
+}
+
+impl Caching {
+    /// Anyone may keep it, shared caches too.
+    pub fn public(max_age_secs: u64) -> Self {
+        Self {
+            max_age_secs,
+            private: false,
+        }
+    }
+
+    /// Public for anonymous requests, but only for the client's own cache with an API key.
+    pub fn for_caller(key: Option<&ApiKey>, max_age_secs: u64) -> Self {
+        Self {
+            max_age_secs,
+            private: key.is_some(),
+        }
+    }
+
+    fn header_value(self) -> HeaderValue {
+        // A max-age of 0 would still let caches keep it around for revalidating
+        if self.max_age_secs == 0 {
+            return HeaderValue::from_static("no-store");
+        }
+
+        let visibility = if self.private { "private" } else { "public" };
+        HeaderValue::from_str(&format!("{}, max-age={}", visibility, self.max_age_secs))
+            .expect("numbers are valid header values")
+    }
+}
+
+/// Weak ETag for a response body, quoted and ready to go in a header. Weak since compression
+/// changes the bytes but not what they mean, and the tag is the same either way.
+pub fn etag_for(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let mut etag = String::with_capacity(36);
+    etag.push_str("W/\"");
+    for byte in &digest[..16] {
+        write!(etag, "{:02x}", byte).expect("writing to a String can't fail");
+    }
+    etag.push('"');
+
+    etag
+}
+
+/// Checks `If-None-Match` against our ETag. Uses weak comparison, as RFC 9110 asks for.
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/"))
+}
+
+/// Wraps a deterministic body with ETag/Cache-Control headers, or answers with a 304 if
+/// the client already has it.
+///
+/// The output only changes when the generation code (or material-colors) changes,
+/// so the max-age can be pretty long.
+pub fn cacheable(
+    request_headers: &HeaderMap,
+    body: impl AsRef<[u8]> + IntoResponse,
+    content_type: &'static str,
+    caching: Caching,
+) -> Response {
+    let etag = etag_for(body.as_ref());
+    let cache_headers = [
+        (
+            header::ETAG,
+            HeaderValue::from_str(&etag).expect("hex ETag is a valid header value"),
+        ),
+        (header::CACHE_CONTROL, caching.header_value()),
+    ];
+
+    if if_none_match(request_headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    (
+        cache_headers,
+        [(header::CONTENT_TYPE, HeaderValue::from_static(content_type))],
+        body,
+    )
+        .into_response()
+}
+
+/// Lets HEAD requests skip compression.
+///
+/// axum answers HEAD with the GET handler's headers and no body, which is what lets clients
+/// cheaply check whether their cached palette is still valid. Compressing that empty body
+/// would replace the real `Content-Length` with 0 though, so HEAD always gets the identity encoding.
+pub async fn skip_compression_for_head(mut req: Request, next: Next) -> Response {
+    if req.method() == Method::HEAD {
+        req.headers_mut().remove(header::ACCEPT_ENCODING);
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uncacheable_responses_are_not_stored() {
+        assert_eq!(Caching::public(0).header_value(), "no-store");
+        assert_eq!(Caching::public(60).header_value(), "public, max-age=60");
+        assert_eq!(
+            Caching {
+                max_age_secs: 60,
+                private: true
+            }
+            .header_value(),
+            "private, max-age=60"
+        );
+    }
+
+    #[test]
+    fn weak_etags_match_either_way() {
+        let etag = etag_for(b"palette");
+        assert!(etag.starts_with("W/\""));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(etag.trim_start_matches("W/")).unwrap(),
+        );
+        assert!(if_none_match(&headers, &etag));
+    }
+}
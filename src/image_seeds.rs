@@ -0,0 +1,224 @@
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use material_colors::color::Argb;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, Semaphore};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{config::ImageSeedConfig, seeds::Pixels, AppError, AppState};
+
+/// Anything bigger gets turned away before decoding, a PNG can have far more pixels than bytes.
+const MAX_SOURCE_PIXELS: u64 = 4096 * 4096;
+/// What the decoder may allocate. It only ever holds a couple of rows.
+const DECODE_LIMIT_BYTES: usize = 8 * 1024 * 1024;
+/// The quantizer keeps its 1.4 MB histogram on the stack, more than the blocking pool's
+/// threads have, so it gets a thread of its own.
+const QUANTIZER_STACK_BYTES: usize = 16 * 1024 * 1024;
+const DEFAULT_SEEDS: i32 = 4;
+const MAX_SEEDS: i32 = 8;
+
+/// One slot per core for the quantizer threads, which each take a 16 MiB stack. Images beyond
+/// that get a 503 instead of piling up threads.
+#[derive(Clone)]
+pub struct Extractions(Arc<Semaphore>);
+
+impl Default for Extractions {
+    fn default() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+        Self(Arc::new(Semaphore::new(cores)))
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SeedQuery {
+    /// How many seeds to suggest, 4 by default and at most 8
+    count: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SeedColors {
+    /// Best first, each one works as a `base_color`. Fewer than asked for if the image
+    /// doesn't have that many distinct colors.
+    #[schema(example = json!(["4285f4", "ea4335"]))]
+    seeds: Vec<String>,
+}
+
+/// Seeds from images, which goes in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/seedColors", post(seed_colors))
+}
+
+/// Suggests seed colors for a PNG (the request body), the way Android picks them from a
+/// wallpaper. Transparent pixels don't count.
+#[utoipa::path(
+    post,
+    path = "/v1/seedColors",
+    params(SeedQuery),
+    request_body(content = Vec<u8>, content_type = "image/png"),
+    responses(
+        (status = 200, description = "The suggested seeds", body = SeedColors),
+        (status = 400, description = "Not a PNG, too many pixels, or nothing opaque in it", body = String),
+        (status = 503, description = "Every core is busy with other images, try again after `Retry-After` seconds", body = String)
+    )
+)]
+pub async fn seed_colors(
+    State(state): State<AppState>,
+    Query(query): Query<SeedQuery>,
+    image: Bytes,
+) -> Result<Response, AppError> {
+    let count = query.count.unwrap_or(DEFAULT_SEEDS).clamp(1, MAX_SEEDS);
+
+    let Ok(permit) = state.seed_extractions.0.clone().try_acquire_owned() else {
+        return Ok((
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "1")],
+            "Too many images right now, try again in a moment",
+        )
+            .into_response());
+    };
+
+    let start = Instant::now();
+    let budget = state.config.image_seeds.clone();
+    let (sender, receiver) = oneshot::channel();
+    std::thread::Builder::new()
+        .name("seed-extraction".to_string())
+        .stack_size(QUANTIZER_STACK_BYTES)
+        .spawn(move || {
+            // Held until the thread is done, even if the client has gone away
+            let _permit = permit;
+            let _ = sender.send(extract_seeds(&image, count, &budget));
+        })?;
+    let seeds = receiver.await?;
+    let seeds = match seeds {
+        Ok(seeds) => seeds,
+        Err(message) => return Ok((StatusCode::BAD_REQUEST, message).into_response()),
+    };
+    metrics::histogram!("seed_extraction_seconds").record(start.elapsed().as_secs_f64());
+
+    Ok(Json(SeedColors {
+        seeds: seeds.iter().map(Argb::to_hex).collect(),
+    })
+    .into_response())
+}
+
+fn extract_seeds(image: &[u8], count: i32, budget: &ImageSeedConfig) -> Result<Vec<Argb>, String> {
+    let pixels = downscaled_pixels(image, budget.max_pixels)?;
+    if pixels.is_empty() {
+        return Err("The image has no opaque pixels".to_string());
+    }
+
+    Ok(Pixels {
+        pixels: &pixels,
+        iterations: budget.quantizer_iterations,
+    }
+    .seeds(count))
+}
+
+/// Where the pixels of each row of an Adam7 pass are, as (x, y, x step, y step).
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Decodes the PNG a row at a time, averaging its opaque pixels into a grid of at most
+/// `max_pixels` cells, so the whole image never has to be in memory.
+fn downscaled_pixels(image: &[u8], max_pixels: u32) -> Result<Vec<Argb>, String> {
+    let not_png = |err| match err {
+        png::DecodingError::LimitsExceeded => "The image is too big".to_string(),
+        _ => "The body has to be a PNG image".to_string(),
+    };
+
+    let mut decoder = png::Decoder::new_with_limits(
+        image,
+        png::Limits {
+            bytes: DECODE_LIMIT_BYTES,
+        },
+    );
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(not_png)?;
+
+    let (width, height, interlaced) = {
+        let info = reader.info();
+        (info.width, info.height, info.interlaced)
+    };
+    if u64::from(width) * u64::from(height) > MAX_SOURCE_PIXELS {
+        return Err(format!(
+            "Images can have at most {} pixels",
+            MAX_SOURCE_PIXELS
+        ));
+    }
+    let channels = reader.output_color_type().0.samples();
+
+    let side = max_pixels.isqrt();
+    let grid_width = width.min(side);
+    let grid_height = height.min(side);
+    // Red, green and blue sums and how many pixels went into them, for each cell
+    let mut cells = vec![[0u64; 4]; (grid_width * grid_height) as usize];
+
+    // The rows of every pass with pixels in it, in the order the decoder returns them
+    let passes = if interlaced {
+        &ADAM7_PASSES[..]
+    } else {
+        &[(0, 0, 1, 1)][..]
+    };
+    let mut rows = passes.iter().flat_map(|&(x0, y0, dx, dy)| {
+        let pass_width = width.saturating_sub(x0).div_ceil(dx);
+        let lines = if pass_width == 0 {
+            0
+        } else {
+            height.saturating_sub(y0).div_ceil(dy)
+        };
+        (0..lines).map(move |line| (x0, y0 + line * dy, dx))
+    });
+
+    while let Some(row) = reader.next_row().map_err(not_png)? {
+        let Some((x0, y, dx)) = rows.next() else {
+            break;
+        };
+        let cell_row = (u64::from(y) * u64::from(grid_height) / u64::from(height)) as u32;
+
+        for (i, pixel) in row.data().chunks_exact(channels).enumerate() {
+            let [red, green, blue, alpha] = match *pixel {
+                [gray] => [gray, gray, gray, 255],
+                [gray, alpha] => [gray, gray, gray, alpha],
+                [red, green, blue] => [red, green, blue, 255],
+                [red, green, blue, alpha] => [red, green, blue, alpha],
+                _ => unreachable!("PNGs have 1 to 4 channels"),
+            };
+            if alpha < 255 {
+                continue;
+            }
+
+            let x = x0 + i as u32 * dx;
+            let cell_column = (u64::from(x) * u64::from(grid_width) / u64::from(width)) as u32;
+            let cell = &mut cells[(cell_row * grid_width + cell_column) as usize];
+            cell[0] += u64::from(red);
+            cell[1] += u64::from(green);
+            cell[2] += u64::from(blue);
+            cell[3] += 1;
+        }
+    }
+
+    Ok(cells
+        .iter()
+        .filter(|cell| cell[3] > 0)
+        .map(|&[red, green, blue, count]| {
+            let average = |sum: u64| (sum / count) as u8;
+            Argb::new(255, average(red), average(green), average(blue))
+        })
+        .collect())
+}
@@ -0,0 +1,17 @@
+use std::str::FromStr;
+
+use material_colors::color::Argb;
+
+/// A hex color like `base_color`, with or without the `#`, as 3 or 6 digits (`#f00`,
+/// `59EB5C`).
+pub fn parse_color(hex: &str) -> anyhow::Result<Argb> {
+    // material-colors skips a `#` anywhere in the string, so `f#f#0000` would be red too
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    anyhow::ensure!(
+        matches!(digits.len(), 3 | 6) && digits.bytes().all(|b| b.is_ascii_hexdigit()),
+        "{:?} isn't 3 or 6 hex digits",
+        hex
+    );
+
+    Ok(Argb::from_str(digits)?)
+}
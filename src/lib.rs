@@ -0,0 +1,28 @@
+//! Material You palettes from a seed color, generated and written out the same way the
+//! service does it. The server binary is an HTTP layer around these modules.
+//!
+//! With the `lib` feature, [`generate_theme`] and [`Theme::to_format`] are the API for other
+//! projects, which follows semver. The modules are what the server and the CLI use, and
+//! can change in any release.
+
+#[doc(hidden)]
+pub mod color_names;
+#[doc(hidden)]
+pub mod formats;
+#[doc(hidden)]
+pub mod input;
+#[doc(hidden)]
+pub mod palette;
+#[doc(hidden)]
+pub mod palette_files;
+#[doc(hidden)]
+pub mod seeds;
+#[cfg(feature = "lib")]
+mod theme;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "lib")]
+pub use theme::{
+    generate_theme, Color, CustomColor, InvalidColor, OutputFormat, Theme, ThemeOptions, ThemeType,
+};
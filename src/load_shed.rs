@@ -0,0 +1,28 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use tower::load_shed::error::Overloaded;
+use tracing::warn;
+
+/// Turns errors from the load shedding stack into responses. Requests beyond
+/// `max_concurrent_requests` get turned away instead of queueing up behind everyone else.
+pub async fn handle_overload(err: BoxError) -> Response {
+    if err.is::<Overloaded>() {
+        warn!("Shedding load, too many requests in flight");
+
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "1")],
+            "Too many requests right now, try again in a moment",
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Unhandled internal error: {}", err),
+    )
+        .into_response()
+}
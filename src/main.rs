@@ -1,136 +1,562 @@
 use axum::{
-    extract::{MatchedPath, Query, Request},
-    http::StatusCode,
+    error_handling::HandleErrorLayer,
+    extract::{DefaultBodyLimit, MatchedPath, Query, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
-use material_colors::{
-    color::Argb,
-    theme::{CustomColor, ThemeBuilder},
+use chrono::Utc;
+use material_colors::color::Argb;
+use sentry::integrations::tower::{NewSentryLayer, SentryHttpLayer};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use serde::Deserialize;
-use std::str::FromStr;
-use tower_http::trace::TraceLayer;
-use tracing::{error, info};
-
-#[derive(Debug, Deserialize)]
-enum ThemeType {
-    Dark,
-    Light,
+use tower::ServiceBuilder;
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    compression::CompressionLayer,
+    limit::RequestBodyLimitLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use resomaterialyou::{color_names, formats, input, palette, palette_files, seeds};
+
+use crate::{
+    auth::MaybeApiKey,
+    cache::{CacheCounters, InFlight, MemoryCache, RedisCache},
+    color_names::Lang,
+    config::{Config, CustomColorConfig},
+    errors::{InputError, RenderError, StorageError, UpstreamError},
+    formats::{Format, FormatOptions},
+    palette::{CustomColorSpec, Hex, Palette, ThemeType},
+    seed_source::SeedKind,
+    selection::{Only, Selection},
+    shutdown::Shutdown,
+    storage::Storage,
+    webhooks::WebhookPayload,
+};
+
+mod admin;
+mod auth;
+mod backup;
+mod batch;
+mod body_limit;
+mod cache;
+mod channels;
+mod config;
+mod contrast;
+mod discord;
+mod downloads;
+mod error_reporting;
+mod errors;
+mod fallback;
+mod gallery;
+mod gradient;
+mod health;
+mod http_cache;
+mod image_seeds;
+mod load_shed;
+mod me;
+mod metadata;
+mod mqtt;
+mod name_color;
+mod openapi;
+mod osc;
+mod pairing;
+mod palettes;
+mod panic;
+mod picker;
+mod preview;
+mod prometheus;
+mod quota;
+mod rate_limit;
+mod render;
+mod request_id;
+mod resonite;
+mod resonite_object;
+mod scheduler;
+mod schema;
+mod seed_source;
+mod selection;
+mod server;
+mod shutdown;
+mod stats;
+mod storage;
+mod suggest_seeds;
+mod telemetry;
+mod timing;
+mod twitch;
+mod usage;
+mod validate;
+mod version;
+mod versioning;
+mod voting;
+mod webhooks;
+#[cfg(test)]
+mod wire_format_tests;
+
+#[derive(Clone)]
+struct AppState {
+    config: Arc<Config>,
+    log_level: telemetry::LogLevelHandle,
+    /// Only there when a database is configured
+    storage: Option<Storage>,
+    cache: Option<RedisCache>,
+    memory_cache: MemoryCache,
+    in_flight: InFlight,
+    cache_counters: CacheCounters,
+    /// `config.custom_colors`, parsed
+    custom_colors: Arc<[CustomColorSpec]>,
+    /// For calling out to other services, like Discord. Gives up on slow ones, so nothing
+    /// waiting on them piles up
+    http: reqwest::Client,
+    /// Palettes posted to Discord lately
+    discord_posts: discord::Posts,
+    channels: channels::Channels,
+    pairings: pairing::Pairings,
+    votes: voting::Votes,
+    /// Threads `/v1/seedColors` may run the quantizer on at once
+    seed_extractions: image_seeds::Extractions,
+    shutdown: Shutdown,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct PaletteQuery {
-    base_color: String,
+    /// Source color as hex, with or without the `#` (3 or 6 digits). Not needed for some
+    /// `seed`s
+    #[param(example = "59EB5C")]
+    base_color: Option<String>,
+    /// Where the seed comes from, `hex` (`base_color`) by default
+    #[serde(default)]
+    seed: SeedKind,
+    theme_type: ThemeType,
+    #[serde(default)]
+    format: Format,
+}
+
+/// The parameters a palette was generated with after everything was resolved, echoed in
+/// `format=json` so two requests that look the same can be compared.
+#[derive(Serialize, ToSchema)]
+pub struct ResolvedParams {
+    seed: SeedKind,
+    /// The seed color as 6 lowercase hex digits, whatever `base_color` looked like
+    #[schema(example = "59eb5c")]
+    seed_color: String,
     theme_type: ThemeType,
+    /// The instance's custom colors, which every palette is generated with
+    custom_colors: Vec<String>,
+    include_customs: bool,
+    only: Option<Only>,
+    /// The colors `fields` picked, with their names spelled like in `colors`
+    #[schema(example = json!(["primary", "on_primary"]))]
+    fields: Option<Vec<String>>,
+    color_names: bool,
+    lang: Lang,
 }
 
-async fn get_palette(pagination: Query<PaletteQuery>) -> Result<String, AppError> {
+impl ResolvedParams {
+    fn new(
+        state: &AppState,
+        query: &PaletteQuery,
+        seed: Argb,
+        selection: &Selection,
+        selected: &Palette,
+        options: &FormatOptions,
+    ) -> Self {
+        Self {
+            seed: query.seed,
+            seed_color: Hex(seed).to_string(),
+            theme_type: query.theme_type,
+            custom_colors: state
+                .custom_colors
+                .iter()
+                .map(|color| color.name.clone())
+                .collect(),
+            include_customs: selection.include_customs,
+            only: selection.only,
+            fields: selection.fields.is_some().then(|| selected.names()),
+            color_names: options.color_names,
+            lang: options.lang,
+        }
+    }
+}
+
+/// Generates a palette from a source color.
+///
+/// The response is every color of the scheme followed by the fixed custom colors
+/// (color, container, on color, on container), as 6 digit hex values with nothing in between.
+/// `format=json` gives the same colors as an object, keyed by name, with the resolved
+/// parameters next to them in `params`, `format=dynvar` one
+/// `Theme/Primary|FFB4A8` line per color, `format=lines` one hex value per line, and
+/// `format=android-xml` an Android `colors.xml` with both the dark and the light theme.
+///
+/// Also available at the unversioned `/getPalette`, which older worlds use. HEAD works too,
+/// and returns just the headers (`ETag`, `Content-Length`) for checking if a cached palette is current.
+#[utoipa::path(
+    get,
+    path = "/v1/getPalette",
+    params(PaletteQuery, FormatOptions, Selection),
+    responses(
+        (status = 200, description = "The concatenated palette", body = String, content_type = "text/plain",
+            headers(
+                ("X-Palette-Colors" = usize, description = "How many colors there are (`format=raw` only)"),
+                ("X-Palette-Schema-Version" = u32, description = "Layout version of the colors, like `/v1/schema`'s (`format=raw` only)"),
+                ("X-Palette-Length" = usize, description = "Characters in the whole string (`format=raw` only)")
+            )
+        ),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`base_color` is missing or isn't a hex color, `fields` has a color that doesn't exist, or the API key has no history for `seed=profile`", body = String),
+        (status = 401, description = "`seed=profile` without an API key", body = String),
+        (status = 500, description = "Generating the palette failed", body = String),
+        (status = 503, description = "The database is busy, try again after `Retry-After` seconds", body = String)
+    )
+)]
+async fn get_palette(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    headers: HeaderMap,
+    pagination: Query<PaletteQuery>,
+    Query(options): Query<FormatOptions>,
+    Query(selection): Query<Selection>,
+) -> Result<Response, AppError> {
     let query: PaletteQuery = pagination.0;
 
     info!(
-        "Generating {:?} theme with color string {:?}",
-        &query.theme_type, &query.base_color
+        "Generating {:?} theme with {:?} seed {:?}",
+        &query.theme_type,
+        &query.seed,
+        query.base_color.as_deref().unwrap_or_default()
     );
 
-    // Define some fixed colors to make people's lives easier
-    let red = CustomColor {
-        value: Argb::from_str("FF7676")?,
-        name: "red".to_string(),
-        blend: true,
-    };
-    let green = CustomColor {
-        value: Argb::from_str("59EB5C")?,
-        name: "green".to_string(),
-        blend: true,
-    };
-    let blue = CustomColor {
-        value: Argb::from_str("0000FF")?,
-        name: "blue".to_string(),
-        blend: true,
+    let parse_start = Instant::now();
+    let seed = match seed_source::resolve(
+        &state,
+        key.as_ref(),
+        query.seed,
+        query.base_color.as_deref(),
+    )
+    .await
+    {
+        Ok(seed) => seed,
+        Err(response) => return Ok(response),
     };
-    let yellow = CustomColor {
-        value: Argb::from_str("F8F770")?,
-        name: "yellow".to_string(),
-        blend: false,
-    };
-    let purple = CustomColor {
-        value: Argb::from_str("BA64F2")?,
-        name: "purple".to_string(),
-        blend: true,
-    };
-    let cyan = CustomColor {
-        value: Argb::from_str("61D1FA")?,
-        name: "cyan".to_string(),
-        blend: true,
+    let source = seed.color;
+    timing::record("parse", parse_start.elapsed());
+
+    let palette_start = Instant::now();
+    let palette = cache::palette(&state, source, query.theme_type).await?;
+    // colors.xml has both themes, whichever one `theme_type` is
+    let other = match (query.format.both_themes(), query.theme_type) {
+        (true, ThemeType::Dark) => Some(cache::palette(&state, source, ThemeType::Light).await?),
+        (true, ThemeType::Light) => Some(cache::palette(&state, source, ThemeType::Dark).await?),
+        (false, _) => None,
     };
-    let orange = CustomColor {
-        value: Argb::from_str("E69E50")?,
-        name: "orange".to_string(),
-        blend: false,
+    timing::record("palette", palette_start.elapsed());
+
+    let selected = selection.apply(&palette, &state.custom_colors)?;
+    let serialize_start = Instant::now();
+    let body = match (query.format, other) {
+        (Format::Json, _) => {
+            let params =
+                ResolvedParams::new(&state, &query, source, &selection, &selected, &options);
+            formats::json_with_params(&selected, &params, &options)
+        }
+        (format, Some(other)) => {
+            let other = selection.apply(&other, &state.custom_colors)?;
+            let (dark, light) = match query.theme_type {
+                ThemeType::Dark => (&selected, &other),
+                ThemeType::Light => (&other, &selected),
+            };
+            format.render_both(dark, light, &options)
+        }
+        (format, None) => format.render(&selected, &options),
     };
+    timing::record("serialize", serialize_start.elapsed());
+    let wire = palette.to_wire_string();
 
-    let custom_colors: Vec<CustomColor> = vec![red, green, blue, yellow, purple, cyan, orange];
-    let theme = ThemeBuilder::with_source(Argb::from_str(&query.base_color)?)
-        .custom_colors(custom_colors)
-        .build();
-
-    let base_theme_string = match query.theme_type {
-        ThemeType::Dark => theme
-            .schemes
-            .dark
-            .into_iter()
-            .map(|x| x.1.to_hex())
-            .collect::<Vec<_>>()
-            .join(""),
-        ThemeType::Light => theme
-            .schemes
-            .light
-            .into_iter()
-            .map(|x| x.1.to_hex())
-            .collect::<Vec<_>>()
-            .join(""),
+    let key_webhook = key.as_ref().and_then(|key| {
+        let config = state.config.api_keys.iter().find(|k| k.name == key.name)?;
+        Some((&key.name, config.discord_webhook.clone()?))
+    });
+    if let Some((key_name, url)) = key_webhook {
+        discord::post_palette(
+            &state,
+            key_name,
+            url,
+            source.to_hex(),
+            query.theme_type,
+            &palette,
+        );
+    }
+
+    if let Some(key) = &key {
+        webhooks::notify(
+            &state,
+            WebhookPayload {
+                event: "palette.generated",
+                api_key: key.name.clone(),
+                base_color: source.to_hex(),
+                theme_type: query.theme_type,
+                palette: wire.clone(),
+                stored_palette_id: None,
+                sent_at: Utc::now(),
+            },
+        );
+    }
+
+    // Nobody else gets the same random seed
+    let max_age = if seed.deterministic {
+        state.config.cache_max_age_secs
+    } else {
+        0
     };
+    let caching = http_cache::Caching::for_caller(key.as_ref(), max_age);
+
+    if let Some(storage) = state.storage.clone() {
+        // Nobody should have to wait on these, or get an error because of them
+        let base_color = source.to_hex();
+        let theme_type = query.theme_type;
+        let history = key.map(|key| (key, wire));
+        let keep = state.config.history_length;
 
-    let custom_colors_string = theme
-        .custom_colors
-        .iter()
-        .map(|x| match query.theme_type {
-            ThemeType::Dark => format!(
-                "{}{}{}{}",
-                x.dark.color.to_hex(),
-                x.dark.color_container.to_hex(),
-                x.dark.on_color.to_hex(),
-                x.dark.on_color_container.to_hex()
-            ),
-            ThemeType::Light => format!(
-                "{}{}{}{}",
-                x.light.color.to_hex(),
-                x.light.color_container.to_hex(),
-                x.light.on_color.to_hex(),
-                x.light.on_color_container.to_hex()
-            ),
-        })
-        .collect::<Vec<_>>()
-        .join("");
-
-    let final_string = format!("{}{}", base_theme_string, custom_colors_string);
-
-    info!("Generated theme: {:?}", final_string);
-
-    Ok(final_string)
+        state.shutdown.spawn_write(async move {
+            if let Err(err) = storage.count_seed(&base_color, theme_type).await {
+                warn!("Couldn't count the seed color: {}", err);
+            }
+
+            if let Some((key, palette)) = history {
+                if let Err(err) = storage
+                    .record_history(&key.name, &base_color, theme_type, &palette, keep)
+                    .await
+                {
+                    warn!("Couldn't record history for {:?}: {}", key.name, err);
+                }
+            }
+        });
+    }
+
+    info!("Generated theme: {:?}", body);
+
+    let raw = query.format == Format::Raw;
+    let names = query.format == Format::Json && options.color_names;
+    let length = body.len();
+    let mut response = http_cache::cacheable(&headers, body, query.format.content_type(), caching);
+    if raw {
+        metadata::add_headers(&mut response, &selected, length);
+    }
+    if names {
+        name_color::add_content_language(&mut response, options.lang);
+    }
+
+    Ok(response)
 }
 
 async fn hello_world() -> &'static str {
     "Hello, world!"
 }
 
+#[cfg(feature = "shuttle")]
 #[shuttle_runtime::main]
-async fn main() -> shuttle_axum::ShuttleAxum {
-    let router = Router::new()
+async fn main(
+    #[shuttle_runtime::Secrets] secrets: shuttle_runtime::SecretStore,
+    #[shuttle_shared_db::Postgres] pool: sqlx::PgPool,
+) -> Result<server::AppService, shuttle_runtime::Error> {
+    let config = Config::load_with_secrets(secrets)?;
+    let storage = Storage::from_pool(pool).await?;
+    let (cache, cache_error) = connect_cache(&config).await;
+
+    let http = config::HttpConfig {
+        shutdown_grace_secs: config.http.shutdown_grace_secs,
+        ..config::HttpConfig::default()
+    };
+    let shutdown = Shutdown::default();
+    let app = app(config, Some(storage), cache, shutdown.clone());
+    warn_without_cache(cache_error);
+    Ok(server::AppService {
+        router: app,
+        http,
+        shutdown,
+    })
+}
+
+#[cfg(not(feature = "shuttle"))]
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let addr = std::net::SocketAddr::new(config.bind_address, config.port);
+    let tls = config.tls.clone();
+    let http = config.http.clone();
+    let storage = match &config.database_url {
+        Some(url) => Some(Storage::connect(url).await?),
+        None => None,
+    };
+    let (cache, cache_error) = connect_cache(&config).await;
+
+    let shutdown = Shutdown::default();
+    let app = app(config, storage, cache, shutdown.clone());
+    warn_without_cache(cache_error);
+    server::serve(app, addr, tls.as_ref(), &http, &shutdown).await?;
+
+    Ok(())
+}
+
+/// The Redis cache, if there's one configured and it answers. Palettes can be generated
+/// without it, so not reaching it doesn't stop the service from starting; the error comes back
+/// to be logged once logging is set up.
+async fn connect_cache(config: &Config) -> (Option<RedisCache>, Option<anyhow::Error>) {
+    let Some(url) = &config.redis_url else {
+        return (None, None);
+    };
+
+    let ttl = Duration::from_secs(config.redis_ttl_secs);
+    match RedisCache::connect(url, ttl, &config.custom_colors).await {
+        Ok(cache) => (Some(cache), None),
+        Err(err) => (None, Some(err)),
+    }
+}
+
+fn warn_without_cache(error: Option<anyhow::Error>) {
+    if let Some(err) = error {
+        warn!("Couldn't connect to Redis, running without it: {:#}", err);
+    }
+}
+
+/// Sets up logging/metrics/error reporting and builds the whole router.
+fn app(
+    config: Config,
+    storage: Option<Storage>,
+    cache: Option<RedisCache>,
+    shutdown: Shutdown,
+) -> Router {
+    error_reporting::init(&config);
+    let log_level = telemetry::init(&config);
+
+    let custom_colors = CustomColorConfig::parse_all(&config.custom_colors)
+        .expect("custom colors get checked when the config loads");
+    let state = AppState {
+        custom_colors: custom_colors.into(),
+        memory_cache: MemoryCache::new(config.memory_cache_bytes),
+        in_flight: InFlight::default(),
+        cache_counters: CacheCounters::default(),
+        config: Arc::new(config),
+        log_level,
+        storage,
+        cache,
+        http: reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(15))
+            .build()
+            .expect("the HTTP client only needs TLS, which is built in"),
+        discord_posts: discord::Posts::default(),
+        channels: channels::Channels::default(),
+        pairings: pairing::Pairings::default(),
+        votes: voting::Votes::default(),
+        seed_extractions: image_seeds::Extractions::default(),
+        shutdown,
+    };
+    let config = &state.config;
+
+    scheduler::start(&state);
+    cache::warm(&state);
+    if let Some(mqtt) = &config.mqtt {
+        mqtt::start(mqtt, &state.channels);
+    }
+    if let Some(osc) = &config.osc {
+        osc::start(osc, &state.channels);
+    }
+    if let Some(twitch) = &config.twitch {
+        twitch::start(&state, twitch);
+    }
+
+    let v1 = Router::new()
         .route("/getPalette", get(get_palette))
+        .merge(validate::router())
+        .merge(preview::router())
+        .merge(name_color::router())
+        .merge(suggest_seeds::router())
+        .merge(gallery::router())
+        .merge(contrast::router())
+        .merge(downloads::router())
+        .merge(gradient::router())
+        .merge(batch::router())
+        .merge(image_seeds::router())
+        .merge(schema::router())
+        .merge(channels::api_router())
+        .merge(pairing::router())
+        .merge(voting::router())
+        .merge(palettes::router())
+        .merge(me::router())
+        .merge(stats::router())
+        .merge(backup::router())
+        .merge(webhooks::router())
+        .merge(resonite::router())
+        .with_state(state.clone());
+
+    let mut router = versioning::router(v1, &config.deprecations)
         .route("/", get(hello_world))
+        .merge(palettes::short_link_router().with_state(state.clone()))
+        .merge(channels::router().with_state(state.clone()))
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(http_cache::skip_compression_for_head))
+        // The layer covers every kind of body, so axum's own (extractor only) limit isn't needed
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(config.max_body_bytes))
+        .layer(middleware::from_fn_with_state(
+            config.max_body_bytes,
+            body_limit::explain_limit,
+        ))
+        // Generating a palette takes milliseconds; anything still running after this is stuck
+        // (or a client trickling its request in) and shouldn't hold the connection open.
+        .layer(TimeoutLayer::new(Duration::from_secs(
+            config.request_timeout_secs,
+        )))
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(load_shed::handle_overload))
+                .load_shed()
+                .concurrency_limit(config.max_concurrent_requests),
+        )
+        .merge(health::router(state.clone()))
+        .merge(version::router())
+        .merge(admin::router(state.clone()))
+        .fallback(fallback::not_found);
+
+    if config.features.docs {
+        router = router.merge(openapi::router());
+    }
+    if config.features.picker {
+        router = router.merge(picker::router());
+    }
+    if config.features.metrics {
+        router = router.merge(prometheus::router(prometheus::install_recorder()));
+    }
+
+    let router = router
+        // Inside the request ID scope so the 500 can include it, and counted like any other 500
+        .layer(CatchPanicLayer::custom(panic::handle_panic))
+        .layer(middleware::from_fn(prometheus::track_requests))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            quota::enforce_quota,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            usage::track_usage,
+        ))
+        .layer(middleware::from_fn(timing::scope))
+        .layer(middleware::from_fn(request_id::scope))
+        .layer(middleware::from_fn(error_reporting::redact_request))
+        .layer(SentryHttpLayer::with_transaction())
+        .layer(NewSentryLayer::<Request>::new_from_top())
         // !!! From https://github.com/tokio-rs/axum/blob/main/examples/error-handling/src/main.rs !!!
         .layer(
             TraceLayer::new_for_http()
@@ -138,7 +564,8 @@ async fn main() -> shuttle_axum::ShuttleAxum {
                 // path is useful for figuring out which handler the request was routed to.
                 .make_span_with(|req: &Request| {
                     let method = req.method();
-                    let uri = req.uri();
+                    // API keys can be in the query, and spans end up in the logs and exports
+                    let uri = telemetry::redacted_uri(req.uri());
 
                     // axum automatically adds this extension.
                     let matched_path = req
@@ -146,25 +573,62 @@ async fn main() -> shuttle_axum::ShuttleAxum {
                         .get::<MatchedPath>()
                         .map(|matched_path| matched_path.as_str());
 
-                    tracing::debug_span!("request", %method, %uri, matched_path)
+                    let request_id = req
+                        .headers()
+                        .get("x-request-id")
+                        .and_then(|value| value.to_str().ok());
+
+                    let span = tracing::info_span!(
+                        "request",
+                        %method,
+                        %uri,
+                        matched_path,
+                        request_id
+                    );
+                    telemetry::set_parent_from_headers(&span, req.headers());
+
+                    span
                 }),
-        );
+        )
+        // Outside of the trace layer so the span can pick up the ID
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
-    Ok(router.into())
+    fallback::explain_method_not_allowed(router)
 }
 
 // !!! From https://github.com/tokio-rs/axum/blob/main/examples/anyhow-error-response/src/main.rs !!!
 // Make our own error that wraps `anyhow::Error`.
 struct AppError(anyhow::Error);
 
-// Tell axum how to convert `AppError` into a response.
+// Tell axum how to convert `AppError` into a response. The error types in `errors` know
+// their own status codes, anything else is a plain 500.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        error!("Error occurred: {}", self.0);
+        let err = match self.0.downcast::<InputError>() {
+            Ok(err) => return err.into_response(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<UpstreamError>() {
+            Ok(err) => return err.into_response(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<StorageError>() {
+            Ok(err) => return err.into_response(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<RenderError>() {
+            Ok(err) => return err.into_response(),
+            Err(err) => err,
+        };
+
+        error!("Error occurred: {}", err);
+        error_reporting::capture(&err);
+        errors::count("internal");
 
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Something went wrong: {}", self.0),
+            errors::with_request_id(&err),
         )
             .into_response()
     }
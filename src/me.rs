@@ -0,0 +1,61 @@
+use axum::{response::IntoResponse, routing::get, Json, Router};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::ApiKey,
+    storage::{HistoryEntry, Storage, StoredPalette},
+    AppError, AppState,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct History {
+    history: Vec<HistoryEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Favorites {
+    favorites: Vec<StoredPalette>,
+}
+
+/// Things belonging to the caller's API key, which go in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/me/history", get(history))
+        .route("/me/favorites", get(favorites))
+}
+
+/// The latest palettes generated with this API key, newest first.
+#[utoipa::path(
+    get,
+    path = "/v1/me/history",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Generated palettes", body = History),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn history(key: ApiKey, storage: Storage) -> Result<impl IntoResponse, AppError> {
+    let history = storage.history(&key.name).await?;
+
+    Ok(Json(History { history }))
+}
+
+/// Stored palettes starred with this API key, last starred first. Made for in-world
+/// "my themes" pickers.
+#[utoipa::path(
+    get,
+    path = "/v1/me/favorites",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Starred palettes", body = Favorites),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn favorites(key: ApiKey, storage: Storage) -> Result<impl IntoResponse, AppError> {
+    let favorites = storage.favorites(&key.name).await?;
+
+    Ok(Json(Favorites { favorites }))
+}
@@ -0,0 +1,19 @@
+use axum::{
+    http::{HeaderName, HeaderValue},
+    response::Response,
+};
+
+use crate::palette::{Palette, SCHEMA_VERSION};
+
+static COLORS: HeaderName = HeaderName::from_static("x-palette-colors");
+static SCHEMA: HeaderName = HeaderName::from_static("x-palette-schema-version");
+static LENGTH: HeaderName = HeaderName::from_static("x-palette-length");
+
+/// Tells parsers that split the raw string by position what to expect before they do: how
+/// many colors there are, the layout version and how many characters the whole string has.
+pub fn add_headers(response: &mut Response, palette: &Palette, length: usize) {
+    let headers = response.headers_mut();
+    headers.insert(COLORS.clone(), HeaderValue::from(palette.colors.len()));
+    headers.insert(SCHEMA.clone(), HeaderValue::from(SCHEMA_VERSION));
+    headers.insert(LENGTH.clone(), HeaderValue::from(length));
+}
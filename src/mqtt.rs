@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+use crate::{channels::Channels, config::MqttConfig};
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// Between connection attempts, so a broker that's down doesn't get hammered.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Publishes that can wait for the connection before publishing starts waiting too.
+const QUEUE_LENGTH: usize = 64;
+
+/// Publishes every theme change (on the configured channels) to the broker, for as long as
+/// the server runs. Changes made while the broker is unreachable are dropped, except for the
+/// last few, which go out once it's back.
+pub fn start(config: &MqttConfig, channels: &Channels) {
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(KEEP_ALIVE);
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.as_deref().unwrap_or_default());
+    }
+
+    let (client, mut event_loop) = AsyncClient::new(options, QUEUE_LENGTH);
+    let host = config.host.clone();
+    tokio::spawn(async move {
+        let mut connected = false;
+        loop {
+            match event_loop.poll().await {
+                Ok(event) => {
+                    if !connected {
+                        info!("Connected to MQTT broker {}", host);
+                        connected = true;
+                    }
+                    debug!("MQTT event: {:?}", event);
+                }
+                Err(err) => {
+                    warn!("MQTT connection to {} failed: {}", host, err);
+                    connected = false;
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    let config = config.clone();
+    let mut updates = channels.updates();
+    tokio::spawn(async move {
+        loop {
+            let (channel, theme) = match updates.recv().await {
+                Ok(update) => update,
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(
+                        "MQTT bridge fell behind and skipped {} theme changes",
+                        missed
+                    );
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            if !config.channels.is_empty() && !config.channels.contains(&channel) {
+                continue;
+            }
+
+            let topic = config.topic.replace("{channel}", &channel);
+            let payload = config.format.render(&theme.palette, &config.format_options);
+            let result = client
+                .publish(&topic, QoS::AtLeastOnce, config.retain, payload)
+                .await;
+
+            let outcome = if result.is_ok() { "ok" } else { "error" };
+            metrics::counter!("mqtt_publishes_total", "result" => outcome).increment(1);
+            if let Err(err) = result {
+                warn!("Couldn't publish to MQTT topic {}: {}", topic, err);
+            }
+        }
+    });
+}
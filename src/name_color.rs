@@ -0,0 +1,194 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::Response,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    color_names::{self, Lang, ReferenceSet},
+    errors::InputError,
+    http_cache, input,
+    palette::Hex,
+    AppError, AppState,
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NameColorQuery {
+    /// Hex color, with or without the `#` (3 or 6 digits)
+    #[param(example = "FF7F50")]
+    color: String,
+    /// The language of the name, `en` by default and for languages there are no names in
+    #[serde(default)]
+    lang: Lang,
+}
+
+/// The closest named color to a color.
+#[derive(Serialize, ToSchema)]
+pub struct ColorName {
+    /// The color asked about, as 6 lowercase hex digits
+    #[schema(example = "ff7f50")]
+    color: String,
+    #[schema(example = "Coral")]
+    name: &'static str,
+    /// The named color's own value
+    #[schema(example = "ff7f50")]
+    named_color: String,
+    /// How different the two look (CIELAB ΔE), under about 2.3 is hard to tell apart
+    #[schema(example = 0.0)]
+    distance: f64,
+}
+
+/// Most matches `/closest` answers with.
+const MAX_MATCHES: usize = 20;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClosestQuery {
+    /// Hex color, with or without the `#` (3 or 6 digits)
+    #[param(example = "FF7F50")]
+    color: String,
+    /// Which colors to look in, `css` by default
+    #[serde(default)]
+    palette: ReferenceSet,
+    /// The language of the names, `en` by default and for languages there are no names in. The
+    /// Pantone-like and Resonite names stay as they are
+    #[serde(default)]
+    lang: Lang,
+    /// How many matches, 5 by default and at most 20
+    #[param(default = 5, maximum = 20)]
+    count: Option<usize>,
+}
+
+/// The colors of a reference set closest to a color.
+#[derive(Serialize, ToSchema)]
+pub struct Closest {
+    #[schema(example = "ff7f50")]
+    color: String,
+    palette: ReferenceSet,
+    lang: Lang,
+    /// Closest first
+    matches: Vec<Match>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Match {
+    #[schema(example = "Living Coral")]
+    name: &'static str,
+    #[schema(example = "ff6f61")]
+    color: String,
+    /// How different it looks from the color asked about (CIELAB ΔE)
+    #[schema(example = 3.52)]
+    distance: f64,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/nameColor", get(name_color))
+        .route("/closest", get(closest))
+}
+
+/// Names a color: the closest of the CSS named colors, like `Coral` or `Slate Blue`, in English,
+/// German (`lang=de`) or Japanese (`lang=ja`). Handy for talking about a theme without pasting
+/// hex codes. `format=json&color_names=true` on
+/// `getPalette` does the same for every color of a palette.
+#[utoipa::path(
+    get,
+    path = "/v1/nameColor",
+    params(NameColorQuery),
+    responses(
+        (status = 200, description = "The name", body = ColorName),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`color` isn't a hex color", body = String)
+    )
+)]
+pub async fn name_color(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<NameColorQuery>,
+) -> Result<Response, AppError> {
+    let color = input::parse_color(&query.color).map_err(|_| InputError::invalid_color("color"))?;
+    let nearest = color_names::nearest(color, query.lang);
+
+    let name = ColorName {
+        color: Hex(color).to_string(),
+        name: nearest.name,
+        named_color: Hex(nearest.value).to_string(),
+        distance: rounded(nearest.distance),
+    };
+    let mut response = http_cache::cacheable(
+        &headers,
+        serde_json::to_string(&name)?,
+        "application/json",
+        http_cache::Caching::public(state.config.cache_max_age_secs),
+    );
+    add_content_language(&mut response, query.lang);
+
+    Ok(response)
+}
+
+/// The colors of a reference set that look the most like a color: the CSS named colors,
+/// Pantone-like print colors or Resonite's UI colors. For finding a printable or on-brand
+/// equivalent of a generated color.
+#[utoipa::path(
+    get,
+    path = "/v1/closest",
+    params(ClosestQuery),
+    responses(
+        (status = 200, description = "The matches", body = Closest),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`color` isn't a hex color, `palette` isn't a reference set, or `count` is 0 or more than 20", body = String)
+    )
+)]
+pub async fn closest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ClosestQuery>,
+) -> Result<Response, AppError> {
+    let color = input::parse_color(&query.color).map_err(|_| InputError::invalid_color("color"))?;
+    let count = query.count.unwrap_or(5);
+    if !(1..=MAX_MATCHES).contains(&count) {
+        let message = format!("count has to be between 1 and {}", MAX_MATCHES);
+        return Err(InputError::new(message).into());
+    }
+
+    let closest = Closest {
+        color: Hex(color).to_string(),
+        palette: query.palette,
+        lang: query.lang,
+        matches: color_names::closest(color, query.palette, query.lang)
+            .take(count)
+            .map(|named| Match {
+                name: named.name,
+                color: Hex(named.value).to_string(),
+                distance: rounded(named.distance),
+            })
+            .collect(),
+    };
+    let mut response = http_cache::cacheable(
+        &headers,
+        serde_json::to_string(&closest)?,
+        "application/json",
+        http_cache::Caching::public(state.config.cache_max_age_secs),
+    );
+    add_content_language(&mut response, query.lang);
+
+    Ok(response)
+}
+
+/// Says which language the names are in, since one there are no names in gets English.
+pub fn add_content_language(response: &mut Response, lang: Lang) {
+    response.headers_mut().insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(lang.as_str()),
+    );
+}
+
+/// Two decimals is plenty for a ΔE.
+fn rounded(distance: f64) -> f64 {
+    (distance * 100.0).round() / 100.0
+}
@@ -0,0 +1,191 @@
+use axum::{response::Html, routing::get, Json, Router};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "ResoMaterialYou",
+        description = "Generates Material You color palettes for Resonite."
+    ),
+    paths(
+        crate::get_palette,
+        crate::validate::validate,
+        crate::preview::card,
+        crate::preview::uix,
+        crate::name_color::name_color,
+        crate::name_color::closest,
+        crate::suggest_seeds::suggest_seeds,
+        crate::gallery::gallery,
+        crate::downloads::ase,
+        crate::downloads::gpl,
+        crate::gradient::gradient,
+        crate::contrast::get_palette_contrast,
+        crate::batch::get_palettes,
+        crate::image_seeds::seed_colors,
+        crate::schema::schema,
+        crate::schema::roles,
+        crate::palettes::create_palette,
+        crate::palettes::get_stored_palette,
+        crate::palettes::search_palettes,
+        crate::palettes::update_palette,
+        crate::palettes::palette_versions,
+        crate::palettes::star,
+        crate::palettes::unstar,
+        crate::palettes::short_link,
+        crate::channels::subscribe,
+        crate::channels::subscribe_events,
+        crate::channels::publish,
+        crate::channels::get_channel,
+        crate::channels::put_channel,
+        crate::channels::claim_channel,
+        crate::channels::wait,
+        crate::voting::propose,
+        crate::voting::get_round,
+        crate::voting::vote,
+        crate::pairing::create_pairing,
+        crate::pairing::pairing_status,
+        crate::pairing::pair,
+        crate::me::history,
+        crate::me::favorites,
+        crate::backup::export,
+        crate::backup::import,
+        crate::stats::popular,
+        crate::webhooks::set_webhook,
+        crate::webhooks::get_webhook,
+        crate::webhooks::delete_webhook,
+        crate::resonite::write_cloud_variable,
+        crate::resonite::object,
+        crate::health::healthz,
+        crate::health::readyz,
+        crate::version::version,
+        crate::admin::get_log_level,
+        crate::admin::set_log_level,
+        crate::admin::usage,
+        crate::admin::cache_stats,
+        crate::admin::flush_cache
+    ),
+    components(schemas(
+        crate::palette::ThemeType,
+        crate::formats::Format,
+        crate::seed_source::SeedKind,
+        crate::selection::Only,
+        crate::formats::FormatOptions,
+        crate::ResolvedParams,
+        crate::validate::Validation,
+        crate::name_color::ColorName,
+        crate::name_color::Closest,
+        crate::name_color::Match,
+        crate::suggest_seeds::Suggestions,
+        crate::suggest_seeds::Suggestion,
+        crate::gallery::Gallery,
+        crate::gallery::GallerySeed,
+        crate::color_names::ReferenceSet,
+        crate::color_names::Lang,
+        crate::contrast::ContrastTable,
+        crate::contrast::Thresholds,
+        crate::contrast::ContrastRow,
+        crate::contrast::ContrastPair,
+        crate::contrast::Level,
+        crate::batch::Batch,
+        crate::batch::BatchItem,
+        crate::image_seeds::SeedColors,
+        crate::schema::Schema,
+        crate::schema::Role,
+        crate::schema::Roles,
+        crate::version::Version,
+        crate::palettes::NewPalette,
+        crate::storage::StoredPalette,
+        crate::storage::PaletteDetails,
+        crate::palettes::PaletteChanges,
+        crate::palettes::SearchResults,
+        crate::palettes::Versions,
+        crate::storage::PaletteVersion,
+        crate::storage::HistoryEntry,
+        crate::me::History,
+        crate::me::Favorites,
+        crate::backup::Export,
+        crate::backup::ExportedPalette,
+        crate::backup::ExportedVersion,
+        crate::backup::ImportResult,
+        crate::backup::SkippedPalette,
+        crate::storage::SeedCount,
+        crate::stats::Popular,
+        crate::channels::NewSeed,
+        crate::channels::Published,
+        crate::channels::ChannelState,
+        crate::channels::ChannelToken,
+        crate::voting::NewProposal,
+        crate::voting::Ballot,
+        crate::voting::VotingRound,
+        crate::voting::ProposalView,
+        crate::pairing::NewPairing,
+        crate::pairing::PairingStatus,
+        crate::pairing::Paired,
+        crate::storage::Webhook,
+        crate::webhooks::NewWebhook,
+        crate::webhooks::WebhookPayload,
+        crate::resonite::CloudVariableWrite,
+        crate::resonite::ResoniteCredentials,
+        crate::resonite::CloudVariableWritten,
+        crate::storage::UsageCount,
+        crate::admin::Usage,
+        crate::cache::CacheStats,
+        crate::cache::MemoryStats,
+        crate::cache::CacheRequests,
+        crate::cache::Flushed
+    )),
+    modifiers(&SecuritySchemes)
+)]
+struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))),
+            );
+        }
+    }
+}
+
+// swagger-ui-dist off a CDN, so we don't have to vendor (or download at build time) the whole UI
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8" />
+    <title>ResoMaterialYou API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js" crossorigin></script>
+    <script>
+        window.onload = () => {
+            window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+        };
+    </script>
+</body>
+</html>
+"##;
+
+/// The whole API, as served at `/openapi.json`.
+pub fn spec() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
+
+/// Serves the spec at `/openapi.json` and Swagger UI at `/docs`.
+pub fn router() -> Router {
+    Router::new()
+        .route("/openapi.json", get(|| async { Json(spec()) }))
+        .route("/docs", get(|| async { Html(SWAGGER_UI) }))
+}
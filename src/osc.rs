@@ -0,0 +1,129 @@
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use tokio::{
+    net::{lookup_host, UdpSocket},
+    sync::broadcast::error::RecvError,
+};
+use tracing::{info, warn};
+
+use crate::{
+    channels::{ChannelTheme, Channels},
+    config::OscConfig,
+};
+
+/// Sends every theme change (on the configured channels) to the OSC receiver, one message per
+/// color with its red, green and blue as floats from 0 to 1.
+pub fn start(config: &OscConfig, channels: &Channels) {
+    let config = config.clone();
+    let mut updates = channels.updates();
+    tokio::spawn(async move {
+        // Only looked up again after something went wrong, in case the receiver moved
+        let mut socket = match connect(&config).await {
+            Ok(socket) => Some(socket),
+            Err(err) => {
+                warn!("Couldn't connect to the OSC receiver: {}", err);
+                None
+            }
+        };
+
+        loop {
+            let (channel, theme) = match updates.recv().await {
+                Ok(update) => update,
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(
+                        "OSC sender fell behind and skipped {} theme changes",
+                        missed
+                    );
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            if !config.channels.is_empty() && !config.channels.contains(&channel) {
+                continue;
+            }
+
+            let connected = match socket.take() {
+                Some(connected) => Ok(connected),
+                None => connect(&config).await,
+            };
+            let result = match connected {
+                Ok(connected) => {
+                    let result = send_theme(&connected, &config, &channel, &theme).await;
+                    if result.is_ok() {
+                        socket = Some(connected);
+                    }
+                    result
+                }
+                Err(err) => Err(err),
+            };
+
+            let outcome = if result.is_ok() { "ok" } else { "error" };
+            metrics::counter!("osc_sends_total", "result" => outcome).increment(1);
+            if let Err(err) = result {
+                warn!("Couldn't send theme of {} over OSC: {}", channel, err);
+            }
+        }
+    });
+}
+
+/// Looks up the receiver and connects a socket to it, so sending doesn't look it up every time.
+async fn connect(config: &OscConfig) -> io::Result<UdpSocket> {
+    let receiver = lookup_host((config.host.as_str(), config.port))
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the host has no addresses"))?;
+    let local: SocketAddr = match receiver {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(receiver).await?;
+    info!(
+        "Sending theme changes to OSC receiver {}:{} ({})",
+        config.host, config.port, receiver
+    );
+
+    Ok(socket)
+}
+
+/// One message per color of the theme.
+async fn send_theme(
+    socket: &UdpSocket,
+    config: &OscConfig,
+    channel: &str,
+    theme: &ChannelTheme,
+) -> io::Result<()> {
+    for (name, color) in &theme.palette.colors {
+        let address = config
+            .address
+            .replace("{channel}", channel)
+            .replace("{color}", name);
+        let rgb = [color.red, color.green, color.blue].map(|c| c as f32 / 255.0);
+        socket.send(&encode_message(&address, &rgb)).await?;
+    }
+
+    Ok(())
+}
+
+/// An OSC message with float arguments. Strings are null terminated and padded to 4 bytes,
+/// numbers are big endian.
+fn encode_message(address: &str, args: &[f32]) -> Vec<u8> {
+    let mut message = Vec::new();
+    push_string(&mut message, address);
+    push_string(&mut message, &format!(",{}", "f".repeat(args.len())));
+    for arg in args {
+        message.extend_from_slice(&arg.to_be_bytes());
+    }
+
+    message
+}
+
+fn push_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(value.as_bytes());
+    // Always at least one null
+    let padding = 4 - value.len() % 4;
+    buffer.extend(std::iter::repeat_n(0, padding));
+}
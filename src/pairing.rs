@@ -0,0 +1,315 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    channels::WriteToken,
+    rate_limit::{self, ClientIp, RateLimiter},
+    AppState,
+};
+
+/// Long enough to walk over to the panel and type it in.
+const PAIRING_TTL: Duration = Duration::from_secs(10 * 60);
+const CODE_LENGTH: usize = 6;
+/// No 0/O or 1/I, which look the same on a lot of in-world fonts. 32 of them, so a random byte
+/// picks one without bias.
+const CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// Codes waiting to be entered, at most.
+const MAX_PENDING: usize = 1_000;
+/// How many codes one client can make a minute.
+const PAIRINGS_PER_MINUTE: u32 = 10;
+/// How many codes one client can try a minute, which keeps anyone from guessing them.
+const ATTEMPTS_PER_MINUTE: u32 = 30;
+
+struct Pending {
+    channel: String,
+    paired: bool,
+    expires_at: DateTime<Utc>,
+}
+
+/// Codes a browser made that are waiting for a world to enter them. Like channels, they only
+/// live in this instance's memory.
+#[derive(Clone)]
+pub struct Pairings {
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+    created: RateLimiter,
+    attempts: RateLimiter,
+}
+
+impl Default for Pairings {
+    fn default() -> Self {
+        let minute = Duration::from_secs(60);
+        Self {
+            pending: Default::default(),
+            created: RateLimiter::new(PAIRINGS_PER_MINUTE, minute),
+            attempts: RateLimiter::new(ATTEMPTS_PER_MINUTE, minute),
+        }
+    }
+}
+
+impl Pairings {
+    /// Whether there's room for another code.
+    fn is_full(&self) -> bool {
+        self.pending.lock().unwrap().len() >= MAX_PENDING
+    }
+
+    /// Makes a new code for the channel, unless there are [`MAX_PENDING`] already.
+    fn create(&self, channel: String) -> Option<(String, DateTime<Utc>)> {
+        let now = Utc::now();
+        let expires_at = now + PAIRING_TTL;
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, pairing| pairing.expires_at > now);
+        if pending.len() >= MAX_PENDING {
+            return None;
+        }
+
+        let code = loop {
+            let code = new_code();
+            if !pending.contains_key(&code) {
+                break code;
+            }
+        };
+        pending.insert(
+            code.clone(),
+            Pending {
+                channel,
+                paired: false,
+                expires_at,
+            },
+        );
+
+        Some((code, expires_at))
+    }
+
+    /// The code's channel, for the first world that enters it.
+    fn pair(&self, code: &str) -> Result<String, StatusCode> {
+        let mut pending = self.pending.lock().unwrap();
+        match pending.get_mut(code) {
+            Some(pairing) if pairing.expires_at > Utc::now() => {
+                if pairing.paired {
+                    return Err(StatusCode::CONFLICT);
+                }
+                pairing.paired = true;
+                Ok(pairing.channel.clone())
+            }
+            _ => Err(StatusCode::NOT_FOUND),
+        }
+    }
+
+    /// How the code is doing, and the channel it's for.
+    fn status(&self, code: &str) -> Option<(PairingStatus, String)> {
+        let pending = self.pending.lock().unwrap();
+        pending
+            .get(code)
+            .filter(|pairing| pairing.expires_at > Utc::now())
+            .map(|pairing| {
+                let status = PairingStatus {
+                    paired: pairing.paired,
+                    expires_at: pairing.expires_at,
+                };
+                (status, pairing.channel.clone())
+            })
+    }
+
+    /// Forgets expired codes, and the rate limit windows that are over.
+    pub fn remove_expired(&self) -> u64 {
+        let now = Utc::now();
+        let mut pending = self.pending.lock().unwrap();
+        let before = pending.len();
+        pending.retain(|_, pairing| pairing.expires_at > now);
+        let codes = (before - pending.len()) as u64;
+        drop(pending);
+
+        codes + self.created.remove_expired() + self.attempts.remove_expired()
+    }
+}
+
+fn new_code() -> String {
+    Uuid::new_v4().as_bytes()[..CODE_LENGTH]
+        .iter()
+        .map(|byte| CODE_ALPHABET[*byte as usize % CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+/// People type these in by hand, so `abc-123` is the same as `ABC123`.
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NewPairing {
+    /// What the world's panel has to enter
+    #[schema(example = "K7QX2M")]
+    code: String,
+    /// The channel the panel will listen to
+    channel: String,
+    /// Changes the channel's theme, see `PUT /v1/channels/{channel}`
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PairingStatus {
+    /// Whether a world entered the code yet
+    paired: bool,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Paired {
+    channel: String,
+    /// Where to listen for the browser's colors
+    #[schema(example = "/ws/theme/pair-6f1c...")]
+    websocket_path: String,
+}
+
+/// Pairing, which goes in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/pairing", post(create_pairing))
+        .route("/pairing/:code", get(pairing_status).post(pair))
+}
+
+fn too_many_pairings() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "There are too many pairing codes right now, try again later",
+    )
+        .into_response()
+}
+
+fn unknown_code() -> Response {
+    (StatusCode::NOT_FOUND, "No such pairing code, or it expired").into_response()
+}
+
+/// Starts pairing a browser with a world. The browser shows the code, gets a new claimed
+/// channel and its token, and keeps the token to itself. Once a world enters the code, every
+/// theme the browser sets on the channel goes to that world.
+#[utoipa::path(
+    post,
+    path = "/v1/pairing",
+    responses(
+        (status = 201, description = "The code to show, and the channel to set themes on", body = NewPairing),
+        (status = 429, description = "Too many codes from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many codes or channels to make another one", body = String)
+    )
+)]
+pub async fn create_pairing(State(state): State<AppState>, ip: ClientIp) -> Response {
+    if let Err(retry_after) = state.pairings.created.check(&ip) {
+        return rate_limit::too_many_requests(
+            retry_after,
+            "Too many pairing codes, try again later",
+        );
+    }
+    // Checked before claiming, so a full instance doesn't leave claimed channels behind
+    if state.pairings.is_full() {
+        return too_many_pairings();
+    }
+
+    // Nobody can guess it, so the world's the only one listening
+    let channel = format!("pair-{}", Uuid::new_v4().simple());
+    let token = match state.channels.claim(&channel) {
+        Ok(token) => token.expect("new channels are never claimed"),
+        Err(err) => return err.into_response(),
+    };
+    let Some((code, expires_at)) = state.pairings.create(channel.clone()) else {
+        return too_many_pairings();
+    };
+
+    (
+        StatusCode::CREATED,
+        Json(NewPairing {
+            code,
+            channel,
+            token,
+            expires_at,
+        }),
+    )
+        .into_response()
+}
+
+/// Whether a world entered the code, for the browser to check while it shows it. Takes the
+/// `token` from `POST /v1/pairing` (in `X-Channel-Token` or `?token=`), so nobody else can find
+/// out which codes are waiting.
+#[utoipa::path(
+    get,
+    path = "/v1/pairing/{code}",
+    params(("code" = String, Path, description = "The code from `POST /v1/pairing`")),
+    responses(
+        (status = 200, description = "The code is still valid", body = PairingStatus),
+        (status = 404, description = "No such code, it expired, or the token is missing or wrong", body = String)
+    )
+)]
+pub async fn pairing_status(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    WriteToken(token): WriteToken,
+) -> Response {
+    match state.pairings.status(&normalize_code(&code)) {
+        // The same answer as for a code that isn't there, so guessing doesn't tell anything
+        Some((status, channel)) if state.channels.may_write(&channel, token.as_deref()) => {
+            Json(status).into_response()
+        }
+        _ => unknown_code(),
+    }
+}
+
+/// Enters a code from the browser in a world, which gets the channel to listen to. Each code
+/// only works once.
+#[utoipa::path(
+    post,
+    path = "/v1/pairing/{code}",
+    params(("code" = String, Path, description = "The code the browser shows, case and dashes don't matter")),
+    responses(
+        (status = 200, description = "Paired, listen to the channel", body = Paired),
+        (status = 404, description = "No such code, or it expired", body = String),
+        (status = 409, description = "Another world entered the code already", body = String),
+        (status = 429, description = "Too many codes tried from this client, see `Retry-After`", body = String)
+    )
+)]
+pub async fn pair(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    ip: ClientIp,
+) -> Response {
+    if let Err(retry_after) = state.pairings.attempts.check(&ip) {
+        return rate_limit::too_many_requests(retry_after, "Too many codes tried, try again later");
+    }
+
+    let code = normalize_code(&code);
+    match state.pairings.pair(&code) {
+        Ok(channel) => {
+            info!("A world entered pairing code {}", code);
+            metrics::counter!("channel_pairings_total").increment(1);
+            Json(Paired {
+                websocket_path: format!("/ws/theme/{}", channel),
+                channel,
+            })
+            .into_response()
+        }
+        Err(StatusCode::CONFLICT) => (
+            StatusCode::CONFLICT,
+            "Another world entered this code already",
+        )
+            .into_response(),
+        Err(_) => unknown_code(),
+    }
+}
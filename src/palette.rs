@@ -0,0 +1,311 @@
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use material_colors::{
+    color::Argb,
+    scheme::Scheme,
+    theme::{CustomColor, CustomColorGroup, Theme, ThemeBuilder},
+};
+use serde::{Deserialize, Serialize, Serializer};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, ToSchema)]
+pub enum ThemeType {
+    Dark,
+    Light,
+}
+
+impl ThemeType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThemeType::Dark => "Dark",
+            ThemeType::Light => "Light",
+        }
+    }
+}
+
+impl FromStr for ThemeType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Dark" => Ok(ThemeType::Dark),
+            "Light" => Ok(ThemeType::Light),
+            _ => anyhow::bail!("unknown theme type {:?}", s),
+        }
+    }
+}
+
+/// Version of the generated palettes: which colors there are, in which order, and the values
+/// they come out as. Within a version the order of the colors never changes, in any format.
+/// Anything changing those, like a material-colors update, has to bump it (the golden
+/// fixtures in `tests/golden` won't match until it does).
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The custom colors every palette has unless the config replaces them, as name, hex value and
+/// whether they blend.
+pub const DEFAULT_CUSTOM_COLORS: [(&str, &str, bool); 7] = [
+    ("red", "FF7676", true),
+    ("green", "59EB5C", true),
+    ("blue", "0000FF", true),
+    ("yellow", "F8F770", false),
+    ("purple", "BA64F2", true),
+    ("cyan", "61D1FA", true),
+    ("orange", "E69E50", false),
+];
+
+/// [`DEFAULT_CUSTOM_COLORS`], parsed.
+pub fn default_custom_colors() -> Vec<CustomColorSpec> {
+    DEFAULT_CUSTOM_COLORS
+        .iter()
+        .map(|&(name, value, blend)| CustomColorSpec {
+            name: name.to_string(),
+            value: Argb::from_str(value).expect("the default custom colors are valid"),
+            blend,
+        })
+        .collect()
+}
+
+/// One of the fixed colors appended after the scheme, parsed once instead of for every palette.
+#[derive(Debug, Clone)]
+pub struct CustomColorSpec {
+    pub name: String,
+    pub value: Argb,
+    /// Whether to shift the color towards the source color
+    pub blend: bool,
+}
+
+/// Every color of a generated theme, in the order the wire format uses: the scheme's colors,
+/// then each custom color's color, container, on color and on container.
+#[derive(Clone)]
+pub struct Palette {
+    pub colors: Vec<(String, Argb)>,
+}
+
+impl Palette {
+    pub fn generate(
+        source: Argb,
+        theme_type: ThemeType,
+        custom_colors: &[CustomColorSpec],
+    ) -> anyhow::Result<Self> {
+        Self::generate_with(source, theme_type, custom_colors, &())
+    }
+
+    /// [`Palette::generate`], with each step run through `hook`.
+    pub fn generate_with(
+        source: Argb,
+        theme_type: ThemeType,
+        custom_colors: &[CustomColorSpec],
+        hook: &impl GenerationHook,
+    ) -> anyhow::Result<Self> {
+        let theme = hook.step("theme_build", || build_theme(source, custom_colors));
+        let scheme = match theme_type {
+            ThemeType::Dark => theme.schemes.dark,
+            ThemeType::Light => theme.schemes.light,
+        };
+
+        let mut palette = Self::from_scheme(scheme);
+        hook.step("custom_colors", || {
+            palette.push_custom_colors(&theme.custom_colors, theme_type)
+        });
+        Ok(palette)
+    }
+
+    /// The dark and the light palette, which come out of the same theme anyway.
+    pub fn generate_both(
+        source: Argb,
+        custom_colors: &[CustomColorSpec],
+    ) -> anyhow::Result<(Self, Self)> {
+        Self::generate_both_with(source, custom_colors, &())
+    }
+
+    /// [`Palette::generate_both`], with each step run through `hook`.
+    pub fn generate_both_with(
+        source: Argb,
+        custom_colors: &[CustomColorSpec],
+        hook: &impl GenerationHook,
+    ) -> anyhow::Result<(Self, Self)> {
+        let theme = hook.step("theme_build", || build_theme(source, custom_colors));
+        let mut dark = Self::from_scheme(theme.schemes.dark);
+        let mut light = Self::from_scheme(theme.schemes.light);
+        hook.step("custom_colors", || {
+            dark.push_custom_colors(&theme.custom_colors, ThemeType::Dark);
+            light.push_custom_colors(&theme.custom_colors, ThemeType::Light);
+        });
+
+        Ok((dark, light))
+    }
+
+    /// Just the scheme's colors, which [`Palette::push_custom_colors`] adds to.
+    pub fn from_scheme(scheme: Scheme) -> Self {
+        Self {
+            colors: scheme.into_iter().collect(),
+        }
+    }
+
+    /// Adds the color, container, on color and on container of each custom color of a theme
+    /// from [`build_theme`].
+    pub fn push_custom_colors(
+        &mut self,
+        custom_colors: &[CustomColorGroup],
+        theme_type: ThemeType,
+    ) {
+        let colors = &mut self.colors;
+        for custom in custom_colors {
+            let name = &custom.color.name;
+            let group = match theme_type {
+                ThemeType::Dark => &custom.dark,
+                ThemeType::Light => &custom.light,
+            };
+
+            let [color, container, on_color, on_container] = custom_color_names(name);
+            colors.push((color, group.color));
+            colors.push((container, group.color_container));
+            colors.push((on_color, group.on_color));
+            colors.push((on_container, group.on_color_container));
+        }
+    }
+
+    /// Reads back a palette written with [`Palette::to_wire_string`].
+    pub fn from_wire_string(names: Vec<String>, wire: &str) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            wire.len() == names.len() * 6,
+            "{} names for {} hex digits",
+            names.len(),
+            wire.len()
+        );
+
+        let colors = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let hex = wire.get(i * 6..i * 6 + 6).unwrap_or_default();
+                Ok((name, Argb::from_str(hex)?))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { colors })
+    }
+
+    /// The format worlds parse: 6 digit hex values with nothing in between.
+    pub fn to_wire_string(&self) -> String {
+        let mut wire = String::with_capacity(self.colors.len() * 6);
+        for (_, color) in &self.colors {
+            write!(wire, "{}", Hex(*color)).expect("writing to a String can't fail");
+        }
+
+        wire
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.colors.iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+/// Gets to wrap each step of generating a palette, which the service uses to time them. `()`
+/// just runs them.
+pub trait GenerationHook {
+    /// Runs `step`, which is `theme_build` or `custom_colors`.
+    fn step<T>(&self, name: &'static str, step: impl FnOnce() -> T) -> T;
+}
+
+impl GenerationHook for () {
+    fn step<T>(&self, _name: &'static str, step: impl FnOnce() -> T) -> T {
+        step()
+    }
+}
+
+/// The names of every palette's colors in order, without generating one: the scheme's, then
+/// four for each custom color.
+pub fn color_names(custom_colors: &[CustomColorSpec]) -> Vec<String> {
+    static SCHEME_NAMES: OnceLock<Vec<String>> = OnceLock::new();
+    let scheme_names = SCHEME_NAMES.get_or_init(|| {
+        let theme = ThemeBuilder::with_source(Argb::new(255, 0, 0, 0)).build();
+        theme
+            .schemes
+            .dark
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    });
+
+    let customs = custom_colors
+        .iter()
+        .flat_map(|custom| custom_color_names(&custom.name));
+    scheme_names.iter().cloned().chain(customs).collect()
+}
+
+fn custom_color_names(name: &str) -> [String; 4] {
+    [
+        name.to_string(),
+        format!("{}_container", name),
+        format!("on_{}", name),
+        format!("on_{}_container", name),
+    ]
+}
+
+/// Everything both palettes of a seed come from.
+pub fn build_theme(source: Argb, custom_colors: &[CustomColorSpec]) -> Theme {
+    // The theme builder wants its own, and they can't be cloned
+    let custom_colors = custom_colors
+        .iter()
+        .map(|color| CustomColor {
+            value: color.value,
+            name: color.name.clone(),
+            blend: color.blend,
+        })
+        .collect();
+
+    ThemeBuilder::with_source(source)
+        .custom_colors(custom_colors)
+        .build()
+}
+
+/// An object of names to hex values, written in palette order. Clients that keep the order
+/// can rely on it like they do on the raw string's.
+impl Serialize for Palette {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.colors
+                .iter()
+                .map(|(name, color)| (name.as_str(), HexValue(*color))),
+        )
+    }
+}
+
+/// A color as 6 hex digits, written straight into the output instead of going through
+/// [`Argb::to_hex`], which allocates a few strings per color. `{:X}` gives uppercase digits.
+#[derive(Clone, Copy)]
+pub struct Hex(pub Argb);
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}{:02x}{:02x}",
+            self.0.red, self.0.green, self.0.blue
+        )
+    }
+}
+
+impl fmt::UpperHex for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02X}{:02X}{:02X}",
+            self.0.red, self.0.green, self.0.blue
+        )
+    }
+}
+
+/// Serializes as a string without building one first.
+struct HexValue(Argb);
+
+impl Serialize for HexValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Hex(self.0))
+    }
+}
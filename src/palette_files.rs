@@ -0,0 +1,134 @@
+//! Palettes as swatch files that image editors load, so textures can use the theme's colors.
+
+use std::fmt::Write;
+
+use crate::palette::Palette;
+
+const ASE_GROUP_START: u16 = 0xC001;
+const ASE_GROUP_END: u16 = 0xC002;
+const ASE_COLOR: u16 = 0x0001;
+/// Neither a global nor a spot color, just a color
+const ASE_NORMAL_COLOR: u16 = 2;
+
+/// A GIMP palette (`.gpl`), which GIMP, Krita and Inkscape read.
+pub fn gpl(palette: &Palette, title: &str) -> String {
+    let mut gpl = String::with_capacity(64 + palette.colors.len() * 40);
+    writeln!(gpl, "GIMP Palette\nName: {}\nColumns: 4\n#", title)
+        .expect("writing to a String can't fail");
+    for (name, color) in &palette.colors {
+        writeln!(
+            gpl,
+            "{:3} {:3} {:3}\t{}",
+            color.red, color.green, color.blue, name
+        )
+        .expect("writing to a String can't fail");
+    }
+
+    gpl
+}
+
+/// An Adobe Swatch Exchange file (`.ase`) for Photoshop and Illustrator, with the colors in a
+/// group named `title`.
+pub fn ase(palette: &Palette, title: &str) -> Vec<u8> {
+    let mut ase = Vec::with_capacity(64 + palette.colors.len() * 80);
+    ase.extend_from_slice(b"ASEF");
+    // Version 1.0
+    ase.extend_from_slice(&1u16.to_be_bytes());
+    ase.extend_from_slice(&0u16.to_be_bytes());
+    let blocks = palette.colors.len() as u32 + 2;
+    ase.extend_from_slice(&blocks.to_be_bytes());
+
+    ase_block(&mut ase, ASE_GROUP_START, &ase_name(title));
+    for (name, color) in &palette.colors {
+        let mut block = ase_name(name);
+        block.extend_from_slice(b"RGB ");
+        for channel in [color.red, color.green, color.blue] {
+            block.extend_from_slice(&(f32::from(channel) / 255.0).to_be_bytes());
+        }
+        block.extend_from_slice(&ASE_NORMAL_COLOR.to_be_bytes());
+        ase_block(&mut ase, ASE_COLOR, &block);
+    }
+    ase_block(&mut ase, ASE_GROUP_END, &[]);
+
+    ase
+}
+
+fn ase_block(ase: &mut Vec<u8>, kind: u16, contents: &[u8]) {
+    ase.extend_from_slice(&kind.to_be_bytes());
+    ase.extend_from_slice(&(contents.len() as u32).to_be_bytes());
+    ase.extend_from_slice(contents);
+}
+
+/// A name as ASE writes them: its length in UTF-16 units, then the units, null terminated.
+fn ase_name(name: &str) -> Vec<u8> {
+    let units: Vec<u16> = name.encode_utf16().chain([0]).collect();
+    let mut bytes = Vec::with_capacity(2 + units.len() * 2);
+    bytes.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    for unit in units {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use material_colors::color::Argb;
+
+    use super::*;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_be_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    fn f32_at(bytes: &[u8], at: usize) -> f32 {
+        f32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn ase_layout() {
+        let palette = Palette {
+            colors: vec![("primary".to_string(), Argb::new(255, 255, 0, 51))],
+        };
+        let ase = ase(&palette, "Theme");
+
+        // Signature, version 1.0, and the group around the one color
+        assert_eq!(&ase[..4], b"ASEF");
+        assert_eq!(u16_at(&ase, 4), 1);
+        assert_eq!(u16_at(&ase, 6), 0);
+        assert_eq!(u32_at(&ase, 8), 3);
+
+        // "Theme" and a null, in UTF-16
+        assert_eq!(u16_at(&ase, 12), ASE_GROUP_START);
+        assert_eq!(u32_at(&ase, 14), 2 + 6 * 2);
+        assert_eq!(u16_at(&ase, 18), 6);
+        let group = 20;
+        let title: Vec<u16> = (0..6).map(|i| u16_at(&ase, group + i * 2)).collect();
+        assert_eq!(String::from_utf16(&title[..5]).unwrap(), "Theme");
+        assert_eq!(title[5], 0);
+
+        // The name, the color model, three floats and the color type
+        let color = group + 12;
+        assert_eq!(u16_at(&ase, color), ASE_COLOR);
+        assert_eq!(u32_at(&ase, color + 2), 2 + 8 * 2 + 4 + 3 * 4 + 2);
+        assert_eq!(u16_at(&ase, color + 6), 8);
+        let name: Vec<u16> = (0..7).map(|i| u16_at(&ase, color + 8 + i * 2)).collect();
+        assert_eq!(String::from_utf16(&name).unwrap(), "primary");
+        assert_eq!(u16_at(&ase, color + 22), 0);
+        let model = color + 24;
+        assert_eq!(&ase[model..model + 4], b"RGB ");
+        assert_eq!(f32_at(&ase, model + 4), 1.0);
+        assert_eq!(f32_at(&ase, model + 8), 0.0);
+        assert_eq!(f32_at(&ase, model + 12), 0.2);
+        assert_eq!(u16_at(&ase, model + 16), ASE_NORMAL_COLOR);
+
+        let end = model + 18;
+        assert_eq!(u16_at(&ase, end), ASE_GROUP_END);
+        assert_eq!(u32_at(&ase, end + 2), 0);
+        assert_eq!(ase.len(), end + 6);
+    }
+}
@@ -0,0 +1,541 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    auth::{ApiKey, MaybeApiKey},
+    cache,
+    formats::{Format, FormatOptions},
+    http_cache, input,
+    palette::ThemeType,
+    storage::{PaletteDetails, PaletteLookup, PaletteVersion, Storage, StoredPalette},
+    webhooks::{self, WebhookPayload},
+    AppError, AppState,
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ShortLinkQuery {
+    /// Defaults to `raw`, which can be pasted straight into a string field
+    #[serde(default)]
+    format: Format,
+    /// Pins the palette to this version, so regenerating it doesn't change what this returns
+    version: Option<i32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VersionQuery {
+    /// Returns this version of the palette instead of the latest one
+    version: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewPalette {
+    /// Same as `base_color` for `/v1/getPalette`
+    #[schema(example = "59EB5C")]
+    base_color: String,
+    theme_type: ThemeType,
+    #[serde(flatten)]
+    details: PaletteDetails,
+    /// Deletes the palette after this many seconds (at most a year). After that it answers
+    /// with 410 Gone, so clients can tell it apart from a wrong ID.
+    #[schema(example = 172800)]
+    ttl_secs: Option<i64>,
+}
+
+/// Only the fields that are there get changed. An empty name or description removes it.
+///
+/// Giving a `base_color` or `theme_type` regenerates the colors, with the current custom colors.
+/// That makes a new version; the earlier ones stay available.
+#[derive(Deserialize, ToSchema)]
+pub struct PaletteChanges {
+    name: Option<String>,
+    description: Option<String>,
+    tags: Option<Vec<String>>,
+    #[schema(example = "59EB5C")]
+    base_color: Option<String>,
+    theme_type: Option<ThemeType>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Only palettes with this tag
+    #[param(example = "halloween")]
+    tag: Option<String>,
+    /// Defaults to 50 (at most 100)
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchResults {
+    palettes: Vec<StoredPalette>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Versions {
+    /// Newest first, the current one included
+    versions: Vec<PaletteVersion>,
+}
+
+const MAX_TTL_SECS: i64 = 365 * 24 * 60 * 60;
+/// `max-age` for short links that aren't pinned to a version, which change when the palette
+/// gets regenerated
+const LATEST_MAX_AGE_SECS: u64 = 60;
+
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_DESCRIPTION_LENGTH: usize = 1000;
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LENGTH: usize = 32;
+
+fn check_length(what: &str, value: &Option<String>, max: usize) -> Result<(), String> {
+    match value {
+        Some(value) if value.chars().count() > max => Err(format!(
+            "The {} can be at most {} characters long",
+            what, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Makes sure nobody stores a novel as a description, and normalizes the tags.
+pub(crate) fn check_details(
+    name: &Option<String>,
+    description: &Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<Option<Vec<String>>, String> {
+    check_length("name", name, MAX_NAME_LENGTH)?;
+    check_length("description", description, MAX_DESCRIPTION_LENGTH)?;
+
+    tags.map(normalize_tags).transpose()
+}
+
+/// Trims and lowercases tags (so searching doesn't depend on how someone typed them), and
+/// drops duplicates.
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(format!(
+                "Tags can be at most {} characters long",
+                MAX_TAG_LENGTH
+            ));
+        }
+        normalized.push(tag);
+    }
+
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("A palette can have at most {} tags", MAX_TAGS));
+    }
+
+    Ok(normalized)
+}
+
+/// Stored palettes, which go in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/palettes", post(create_palette).get(search_palettes))
+        .route(
+            "/palettes/:id",
+            get(get_stored_palette).patch(update_palette),
+        )
+        .route("/palettes/:id/versions", get(palette_versions))
+        .route("/palettes/:id/star", put(star).delete(unstar))
+}
+
+/// Short links to stored palettes. These stay outside of the versioned API, to keep them short.
+pub fn short_link_router() -> Router<AppState> {
+    Router::new().route("/p/:short_code", get(short_link))
+}
+
+/// 404 for palettes that never existed, 410 for ones that expired.
+fn unavailable(lookup: PaletteLookup) -> Response {
+    match lookup {
+        PaletteLookup::Expired(expired_at) => (
+            StatusCode::GONE,
+            format!("This palette expired at {}", expired_at.to_rfc3339()),
+        )
+            .into_response(),
+        _ => (StatusCode::NOT_FOUND, "No palette with that ID").into_response(),
+    }
+}
+
+/// Generates a palette and stores it, so it can be fetched by ID later without ever changing.
+///
+/// With an API key, only that key can change the palette's details later.
+#[utoipa::path(
+    post,
+    path = "/v1/palettes",
+    request_body = NewPalette,
+    responses(
+        (status = 201, description = "The stored palette", body = StoredPalette),
+        (status = 400, description = "The details are too long, or the TTL is out of range", body = String),
+        (status = 401, description = "Unknown API key", body = String),
+        (status = 500, description = "The color couldn't be parsed", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn create_palette(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    storage: Storage,
+    Json(new): Json<NewPalette>,
+) -> Result<Response, AppError> {
+    let mut details = new.details;
+    let trim = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_owned())
+            .filter(|value| !value.is_empty())
+    };
+    details.name = trim(details.name);
+    details.description = trim(details.description);
+    let tags = std::mem::take(&mut details.tags);
+    match check_details(&details.name, &details.description, Some(tags)) {
+        Ok(tags) => details.tags = tags.unwrap_or_default(),
+        Err(message) => return Ok((StatusCode::BAD_REQUEST, message).into_response()),
+    }
+
+    let expires_at = match new.ttl_secs {
+        Some(ttl) if !(1..=MAX_TTL_SECS).contains(&ttl) => {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                format!("ttl_secs has to be between 1 and {}", MAX_TTL_SECS),
+            )
+                .into_response())
+        }
+        Some(ttl) => Some(Utc::now() + chrono::Duration::seconds(ttl)),
+        None => None,
+    };
+
+    let source = input::parse_color(&new.base_color)?;
+    let palette = cache::generate(&state, source, new.theme_type).await?;
+    let owner = key.as_ref().map(|key| key.name.as_str());
+    let stored = storage
+        .insert_palette(
+            &source.to_hex(),
+            new.theme_type,
+            &palette,
+            &details,
+            owner,
+            expires_at,
+        )
+        .await?;
+
+    info!("Stored palette {}", stored.id);
+
+    if let Some(key) = &key {
+        webhooks::notify(
+            &state,
+            WebhookPayload {
+                event: "palette.stored",
+                api_key: key.name.clone(),
+                base_color: stored.base_color.clone(),
+                theme_type: stored.theme_type,
+                palette: stored.palette.clone(),
+                stored_palette_id: Some(stored.id),
+                sent_at: Utc::now(),
+            },
+        );
+    }
+
+    let location = format!("/v1/palettes/{}", stored.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(stored),
+    )
+        .into_response())
+}
+
+/// A palette stored with `POST /v1/palettes`, exactly as it was generated back then (or
+/// when it was last regenerated).
+///
+/// With `version`, just the colors of that version.
+#[utoipa::path(
+    get,
+    path = "/v1/palettes/{id}",
+    params(("id" = Uuid, Path, description = "ID returned when storing the palette"), VersionQuery),
+    responses(
+        (status = 200, description = "The stored palette, or a `PaletteVersion` with `version`", body = StoredPalette),
+        (status = 404, description = "No palette with that ID, or no such version", body = String),
+        (status = 410, description = "The palette expired", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn get_stored_palette(
+    storage: Storage,
+    Path(id): Path<Uuid>,
+    Query(query): Query<VersionQuery>,
+) -> Result<Response, AppError> {
+    let stored = match storage.palette(id).await? {
+        PaletteLookup::Found(stored) => stored,
+        lookup => return Ok(unavailable(lookup)),
+    };
+
+    Ok(match query.version {
+        Some(version) => match storage.palette_version(id, version).await? {
+            Some(version) => Json(version).into_response(),
+            None => no_such_version(version),
+        },
+        None => Json(stored).into_response(),
+    })
+}
+
+/// Every version a stored palette has had, for picking one to pin a world to.
+#[utoipa::path(
+    get,
+    path = "/v1/palettes/{id}/versions",
+    params(("id" = Uuid, Path, description = "ID of the stored palette")),
+    responses(
+        (status = 200, description = "The palette's versions", body = Versions),
+        (status = 404, description = "No palette with that ID", body = String),
+        (status = 410, description = "The palette expired", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn palette_versions(
+    storage: Storage,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    if let lookup @ (PaletteLookup::Expired(_) | PaletteLookup::Missing) =
+        storage.palette(id).await?
+    {
+        return Ok(unavailable(lookup));
+    }
+
+    let versions = storage.palette_versions(id).await?;
+    Ok(Json(Versions { versions }).into_response())
+}
+
+fn no_such_version(version: i32) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        format!("This palette has no version {}", version),
+    )
+        .into_response()
+}
+
+/// Stored palettes, newest first, optionally only the ones with a tag.
+#[utoipa::path(
+    get,
+    path = "/v1/palettes",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Matching palettes", body = SearchResults),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn search_palettes(
+    storage: Storage,
+    Query(query): Query<SearchQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let tag = query.tag.map(|tag| tag.trim().to_lowercase());
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let palettes = storage.search_palettes(tag.as_deref(), limit).await?;
+
+    Ok(Json(SearchResults { palettes }))
+}
+
+/// Changes a stored palette's name, description or tags, or regenerates its colors.
+#[utoipa::path(
+    patch,
+    path = "/v1/palettes/{id}",
+    params(("id" = Uuid, Path, description = "ID of the stored palette")),
+    request_body = PaletteChanges,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The changed palette", body = StoredPalette),
+        (status = 400, description = "The details are too long, or the base color is invalid", body = String),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 403, description = "The palette was stored with a different key, or none", body = String),
+        (status = 404, description = "No palette with that ID", body = String),
+        (status = 410, description = "The palette expired", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn update_palette(
+    State(state): State<AppState>,
+    key: ApiKey,
+    storage: Storage,
+    Path(id): Path<Uuid>,
+    Json(changes): Json<PaletteChanges>,
+) -> Result<Response, AppError> {
+    let stored = match storage.palette(id).await? {
+        PaletteLookup::Found(stored) => stored,
+        lookup => return Ok(unavailable(lookup)),
+    };
+    if stored.owner.as_deref() != Some(key.name.as_str()) {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            "Only the API key that stored this palette can change it",
+        )
+            .into_response());
+    }
+
+    let tags = match check_details(&changes.name, &changes.description, changes.tags) {
+        Ok(tags) => tags,
+        Err(message) => return Ok((StatusCode::BAD_REQUEST, message).into_response()),
+    };
+
+    if changes.base_color.is_some() || changes.theme_type.is_some() {
+        let base_color = changes.base_color.as_deref().unwrap_or(&stored.base_color);
+        let Ok(source) = input::parse_color(base_color) else {
+            return Ok((StatusCode::BAD_REQUEST, "Invalid base_color").into_response());
+        };
+        let theme_type = changes.theme_type.unwrap_or(stored.theme_type);
+        let palette = cache::generate(&state, source, theme_type).await?;
+
+        // No need for a new version if nothing would change
+        let unchanged = source.to_hex() == stored.base_color
+            && theme_type == stored.theme_type
+            && palette.to_wire_string() == stored.palette
+            && palette.names() == stored.color_names;
+        if !unchanged {
+            let regenerated = storage
+                .regenerate(id, &source.to_hex(), theme_type, &palette)
+                .await?;
+            match regenerated {
+                Some(regenerated) => info!(
+                    "Regenerated palette {}, now version {}",
+                    id, regenerated.version
+                ),
+                None => return Ok(unavailable(PaletteLookup::Missing)),
+            }
+        }
+    }
+
+    let updated = storage
+        .update_details(
+            id,
+            changes.name.as_deref().map(str::trim),
+            changes.description.as_deref().map(str::trim),
+            tags.as_deref(),
+        )
+        .await?;
+
+    Ok(match updated {
+        Some(updated) => Json(updated).into_response(),
+        None => unavailable(PaletteLookup::Missing),
+    })
+}
+
+/// Adds a stored palette to the API key's favorites.
+#[utoipa::path(
+    put,
+    path = "/v1/palettes/{id}/star",
+    params(("id" = Uuid, Path, description = "ID of the stored palette")),
+    security(("api_key" = [])),
+    responses(
+        (status = 204, description = "Starred (or it already was)"),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 404, description = "No palette with that ID", body = String),
+        (status = 410, description = "The palette expired", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn star(
+    key: ApiKey,
+    storage: Storage,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    match storage.palette(id).await? {
+        PaletteLookup::Found(_) => {
+            storage.star(&key.name, id).await?;
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        lookup => Ok(unavailable(lookup)),
+    }
+}
+
+/// Removes a stored palette from the API key's favorites.
+#[utoipa::path(
+    delete,
+    path = "/v1/palettes/{id}/star",
+    params(("id" = Uuid, Path, description = "ID of the stored palette")),
+    security(("api_key" = [])),
+    responses(
+        (status = 204, description = "Not starred anymore (or it never was)"),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn unstar(
+    key: ApiKey,
+    storage: Storage,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    storage.unstar(&key.name, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A stored palette by its short code, for sharing it as a tiny URL.
+#[utoipa::path(
+    get,
+    path = "/p/{short_code}",
+    params(
+        ("short_code" = String, Path, description = "`short_code` of a stored palette"),
+        ShortLinkQuery,
+        FormatOptions
+    ),
+    responses(
+        (status = 200, description = "The palette, in the requested format", body = String),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 404, description = "No palette with that code", body = String),
+        (status = 410, description = "The palette expired", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn short_link(
+    State(state): State<AppState>,
+    storage: Storage,
+    headers: HeaderMap,
+    Path(short_code): Path<String>,
+    Query(query): Query<ShortLinkQuery>,
+    Query(options): Query<FormatOptions>,
+) -> Result<Response, AppError> {
+    let stored = match storage.palette_by_short_code(&short_code).await? {
+        PaletteLookup::Found(stored) => stored,
+        PaletteLookup::Missing => {
+            return Ok((StatusCode::NOT_FOUND, "No palette with that code").into_response())
+        }
+        lookup => return Ok(unavailable(lookup)),
+    };
+
+    // Versions never change, so pinned ones can be cached just like generated palettes (but
+    // not for longer than they're around)
+    let (palette, max_age) = match query.version {
+        Some(version) => match storage.palette_version(stored.id, version).await? {
+            Some(version) => (version.to_palette()?, state.config.cache_max_age_secs),
+            None => return Ok(no_such_version(version)),
+        },
+        None => (stored.to_palette()?, LATEST_MAX_AGE_SECS),
+    };
+    let max_age = match stored.expires_at {
+        Some(expires_at) => (expires_at - Utc::now())
+            .num_seconds()
+            .clamp(0, max_age as i64) as u64,
+        None => max_age,
+    };
+    let body = query.format.render(&palette, &options);
+    Ok(http_cache::cacheable(
+        &headers,
+        body,
+        query.format.content_type(),
+        http_cache::Caching::public(max_age),
+    ))
+}
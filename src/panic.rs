@@ -0,0 +1,39 @@
+use std::any::Any;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tracing::error;
+
+use crate::request_id;
+
+#[derive(Serialize)]
+struct PanicResponse {
+    error: &'static str,
+    message: &'static str,
+    request_id: Option<String>,
+}
+
+/// Turns a panicking handler into a 500, instead of the connection just getting dropped
+/// (which in-world clients can't tell apart from the network being down).
+///
+/// Sentry's panic hook has already reported it by the time this runs.
+pub fn handle_panic(err: Box<dyn Any + Send + 'static>) -> Response {
+    let details = err
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| err.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic payload");
+    error!("Handler panicked: {}", details);
+
+    let body = PanicResponse {
+        error: "internal_error",
+        message: "Something went wrong while handling the request",
+        request_id: request_id::current(),
+    };
+
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response()
+}
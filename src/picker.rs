@@ -0,0 +1,154 @@
+use axum::{
+    extract::Path,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use crate::channels;
+
+// Everything it needs is inline, so it works on instances without internet access too. It finds
+// the channel in its own URL, and a `?token=` for claimed channels.
+const PICKER_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+    <title>ResoMaterialYou picker</title>
+    <style>
+        body { font-family: system-ui, sans-serif; margin: 0; padding: 24px; display: flex; flex-wrap: wrap; gap: 32px; background: var(--background, #1a1c18); color: var(--on_background, #e2e3dc); transition: background .2s, color .2s; }
+        main { display: flex; flex-direction: column; gap: 16px; width: 280px; }
+        #wheel { width: 280px; height: 280px; border-radius: 50%; cursor: crosshair; touch-action: none; position: relative;
+            background: radial-gradient(circle, #fff, transparent 70%), conic-gradient(from 90deg, #f00, #ff0, #0f0, #0ff, #00f, #f0f, #f00); }
+        #marker { position: absolute; width: 14px; height: 14px; margin: -9px; border: 2px solid #fff; border-radius: 50%; box-shadow: 0 0 2px #000; pointer-events: none; }
+        label { display: flex; justify-content: space-between; align-items: center; gap: 8px; }
+        #status { font-size: 0.9em; opacity: 0.8; }
+        #preview { display: grid; grid-template-columns: repeat(auto-fill, minmax(150px, 1fr)); gap: 8px; flex: 1; min-width: 280px; align-content: start; }
+        .swatch { padding: 16px 8px; border-radius: 8px; font-size: 0.8em; }
+    </style>
+</head>
+<body>
+    <main>
+        <h1 id="title">Picker</h1>
+        <div id="wheel"><div id="marker"></div></div>
+        <label>Color <input id="color" type="color" value="#59eb5c" /></label>
+        <label>Brightness <input id="value" type="range" min="10" max="100" value="100" /></label>
+        <label>Theme
+            <select id="theme_type"><option>Dark</option><option>Light</option></select>
+        </label>
+        <div id="status">Connecting...</div>
+    </main>
+    <section id="preview"></section>
+    <script>
+        const channel = decodeURIComponent(location.pathname.split("/").pop());
+        const token = new URLSearchParams(location.search).get("token");
+        const $ = (id) => document.getElementById(id);
+        $("title").textContent = channel;
+
+        let hue = 0, saturation = 0, value = 1;
+
+        function hsvToHex(h, s, v) {
+            const f = (n) => {
+                const k = (n + h / 60) % 6;
+                return Math.round(255 * (v - v * s * Math.max(0, Math.min(k, 4 - k, 1))));
+            };
+            return "#" + [f(5), f(3), f(1)].map((c) => c.toString(16).padStart(2, "0")).join("");
+        }
+
+        function hexToHsv(hex) {
+            const [r, g, b] = [1, 3, 5].map((i) => parseInt(hex.slice(i, i + 2), 16) / 255);
+            const max = Math.max(r, g, b), d = max - Math.min(r, g, b);
+            let h = 0;
+            if (d) h = max === r ? ((g - b) / d) % 6 : max === g ? (b - r) / d + 2 : (r - g) / d + 4;
+            return [(h * 60 + 360) % 360, max ? d / max : 0, max];
+        }
+
+        function moveMarker() {
+            const radius = $("wheel").clientWidth / 2, angle = hue * Math.PI / 180;
+            $("marker").style.left = radius + Math.cos(angle) * saturation * radius + "px";
+            $("marker").style.top = radius - Math.sin(angle) * saturation * radius + "px";
+        }
+
+        let pending;
+        function changed() {
+            $("color").value = hsvToHex(hue, saturation, value);
+            moveMarker();
+            // Dragging over the wheel would otherwise send a theme for every pixel
+            clearTimeout(pending);
+            pending = setTimeout(push, 150);
+        }
+
+        async function push() {
+            const headers = { "Content-Type": "application/json" };
+            if (token) headers["X-Channel-Token"] = token;
+            const response = await fetch("/v1/channels/" + encodeURIComponent(channel), {
+                method: "PUT",
+                headers,
+                body: JSON.stringify({ base_color: $("color").value.slice(1), theme_type: $("theme_type").value }),
+            });
+            if (!response.ok) $("status").textContent = await response.text();
+        }
+
+        function pickFromWheel(event) {
+            const box = $("wheel").getBoundingClientRect(), radius = box.width / 2;
+            const x = event.clientX - box.left - radius, y = radius - (event.clientY - box.top);
+            hue = (Math.atan2(y, x) * 180 / Math.PI + 360) % 360;
+            saturation = Math.min(1, Math.hypot(x, y) / radius);
+            changed();
+        }
+
+        $("wheel").addEventListener("pointerdown", (event) => {
+            $("wheel").setPointerCapture(event.pointerId);
+            pickFromWheel(event);
+        });
+        $("wheel").addEventListener("pointermove", (event) => {
+            if ($("wheel").hasPointerCapture(event.pointerId)) pickFromWheel(event);
+        });
+        $("value").addEventListener("input", () => { value = $("value").value / 100; changed(); });
+        $("color").addEventListener("input", () => {
+            [hue, saturation, value] = hexToHsv($("color").value);
+            $("value").value = Math.round(value * 100);
+            changed();
+        });
+        $("theme_type").addEventListener("change", changed);
+
+        // The preview shows what the channel actually has, so it's the same as what worlds see
+        const events = new EventSource("/sse/theme/" + encodeURIComponent(channel) + "?format=json");
+        events.addEventListener("theme", (event) => {
+            const colors = JSON.parse(event.data).colors;
+            $("preview").replaceChildren(...Object.entries(colors).map(([name, hex]) => {
+                document.body.style.setProperty("--" + name, "#" + hex);
+                const swatch = document.createElement("div");
+                swatch.className = "swatch";
+                swatch.style.background = "#" + hex;
+                swatch.style.color = Math.max(...[0, 2, 4].map((i) => parseInt(hex.slice(i, i + 2), 16))) > 160 ? "#000" : "#fff";
+                swatch.textContent = name + " " + hex;
+                return swatch;
+            }));
+            $("status").textContent = "Revision " + event.lastEventId;
+        });
+        events.onerror = () => { $("status").textContent = "Reconnecting..."; };
+        events.onopen = () => { $("status").textContent = "Connected"; };
+
+        [hue, saturation, value] = hexToHsv($("color").value);
+        moveMarker();
+    </script>
+</body>
+</html>
+"##;
+
+/// Serves the color picker at `/picker/{channel}`, outside of the versioned API like the
+/// channels it drives.
+pub fn router() -> Router {
+    Router::new().route("/picker/:channel", get(picker))
+}
+
+/// A page with a color wheel that sets the channel's theme, and shows the channel's palette
+/// as it changes.
+async fn picker(Path(channel): Path<String>) -> Response {
+    if !channels::valid_channel(&channel) {
+        return channels::invalid_channel();
+    }
+
+    Html(PICKER_PAGE).into_response()
+}
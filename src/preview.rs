@@ -0,0 +1,103 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
+    routing::get,
+    Router,
+};
+
+use crate::{
+    auth::{ApiKey, MaybeApiKey},
+    cache, http_cache,
+    palette::Palette,
+    render::{self, Canvas},
+    seed_source::{self, SeedQuery},
+    AppError, AppState,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/preview/card.png", get(card))
+        .route("/preview/uix.png", get(uix))
+}
+
+/// Draws a Material card with the palette: text on a surface, a filled primary button and a
+/// tonal one. Shows how readable a theme is at a glance, which swatches don't.
+#[utoipa::path(
+    get,
+    path = "/v1/preview/card.png",
+    params(SeedQuery),
+    responses(
+        (status = 200, description = "The card", body = Vec<u8>, content_type = "image/png"),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`base_color` is missing or isn't a hex color", body = String),
+        (status = 500, description = "Drawing the card failed", body = String)
+    )
+)]
+pub async fn card(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    headers: HeaderMap,
+    Query(query): Query<SeedQuery>,
+) -> Result<Response, AppError> {
+    preview(&state, key, &headers, &query, render::card).await
+}
+
+/// Draws a Resonite UIX settings panel with the palette: a header, a slider, a checkbox, a text
+/// field and a button in each of the custom (hero) colors, to see what a theme looks like
+/// in-headset before applying it.
+#[utoipa::path(
+    get,
+    path = "/v1/preview/uix.png",
+    params(SeedQuery),
+    responses(
+        (status = 200, description = "The panel", body = Vec<u8>, content_type = "image/png"),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`base_color` is missing or isn't a hex color", body = String),
+        (status = 500, description = "Drawing the panel failed", body = String)
+    )
+)]
+pub async fn uix(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    headers: HeaderMap,
+    Query(query): Query<SeedQuery>,
+) -> Result<Response, AppError> {
+    let heroes: Vec<&str> = state
+        .custom_colors
+        .iter()
+        .map(|color| color.name.as_str())
+        .collect();
+    preview(&state, key, &headers, &query, |palette| {
+        render::uix_panel(palette, &heroes)
+    })
+    .await
+}
+
+/// The PNG of whatever `draw` draws with the palette asked for.
+async fn preview(
+    state: &AppState,
+    key: Option<ApiKey>,
+    headers: &HeaderMap,
+    query: &SeedQuery,
+    draw: impl FnOnce(&Palette) -> Canvas,
+) -> Result<Response, AppError> {
+    let seed =
+        match seed_source::resolve(state, key.as_ref(), query.seed, query.base_color.as_deref())
+            .await
+        {
+            Ok(seed) => seed,
+            Err(response) => return Ok(response),
+        };
+
+    let palette = cache::palette(state, seed.color, query.theme_type).await?;
+    let png = draw(&palette).to_png()?;
+
+    let max_age = if seed.deterministic {
+        state.config.cache_max_age_secs
+    } else {
+        0
+    };
+    let caching = http_cache::Caching::for_caller(key.as_ref(), max_age);
+    Ok(http_cache::cacheable(headers, png, "image/png", caching))
+}
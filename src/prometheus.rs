@@ -0,0 +1,55 @@
+use std::{future::ready, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+    routing::get,
+    Router,
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+// Palette generation is quick, so the buckets lean towards the low end.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Installs the global metrics recorder. Only call this once!
+pub fn install_recorder() -> PrometheusHandle {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), LATENCY_BUCKETS)
+        .expect("latency buckets aren't empty")
+        .install_recorder()
+        .expect("failed to install the Prometheus recorder")
+}
+
+/// Serves the scrape endpoint at `/metrics`.
+pub fn router(handle: PrometheusHandle) -> Router {
+    Router::new().route("/metrics", get(move || ready(handle.render())))
+}
+
+/// Counts requests and records their latency per route.
+// !!! Based on https://github.com/tokio-rs/axum/blob/main/examples/prometheus-metrics/src/main.rs !!!
+pub async fn track_requests(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    // Raw paths of unmatched requests would blow up the label cardinality, so lump them together
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched_path| matched_path.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_owned());
+    let method = req.method().to_string();
+
+    let response = next.run(req).await;
+
+    let labels = [
+        ("method", method),
+        ("path", path),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!("http_requests_total", &labels).increment(1);
+    metrics::histogram!("http_request_duration_seconds", &labels)
+        .record(start.elapsed().as_secs_f64());
+
+    response
+}
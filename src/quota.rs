@@ -0,0 +1,93 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Days, Utc};
+use tracing::warn;
+
+use crate::{auth::MaybeApiKey, AppState};
+
+static LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+static REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+static RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Seconds until the quotas reset, at midnight UTC.
+fn secs_until_reset() -> i64 {
+    let now = Utc::now();
+    let midnight = now
+        .date_naive()
+        .checked_add_days(Days::new(1))
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|midnight| midnight.and_utc());
+
+    midnight.map_or(0, |midnight| (midnight - now).num_seconds())
+}
+
+fn quota_headers(headers: &mut HeaderMap, limit: i64, used: i64, reset: i64) {
+    headers.insert(LIMIT.clone(), HeaderValue::from(limit));
+    headers.insert(REMAINING.clone(), HeaderValue::from((limit - used).max(0)));
+    headers.insert(RESET.clone(), HeaderValue::from(reset));
+}
+
+/// Counts requests made with an API key, and turns them away with a 429 once the key's
+/// `daily_quota` (or `default_daily_quota`) is used up for the day.
+///
+/// Requests without a key aren't limited here, and neither is anything when there's no database
+/// to count in.
+pub async fn enforce_quota(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(storage) = state.storage.clone() else {
+        return next.run(req).await;
+    };
+
+    let (mut parts, body) = req.into_parts();
+    // Wrong keys get rejected by whatever needs the key
+    let key = match MaybeApiKey::from_request_parts(&mut parts, &state).await {
+        Ok(MaybeApiKey(Some(key))) => key,
+        _ => return next.run(Request::from_parts(parts, body)).await,
+    };
+    let req = Request::from_parts(parts, body);
+
+    let limit = state
+        .config
+        .api_keys
+        .iter()
+        .find(|configured| configured.name == key.name)
+        .and_then(|configured| configured.daily_quota)
+        .or(state.config.default_daily_quota);
+    let Some(limit) = limit else {
+        return next.run(req).await;
+    };
+
+    let used = match storage.count_key_request(&key.name).await {
+        Ok(used) => used,
+        Err(err) => {
+            // Better to let a few requests too many through than to fail all of them
+            warn!("Couldn't count the request for {:?}: {}", key.name, err);
+            return next.run(req).await;
+        }
+    };
+    let reset = secs_until_reset();
+
+    let mut response = if used > limit {
+        metrics::counter!("quota_exceeded_total").increment(1);
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "The daily quota of {} requests for this API key is used up, it resets in {} seconds",
+                limit, reset
+            ),
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(axum::http::header::RETRY_AFTER, HeaderValue::from(reset));
+        response
+    } else {
+        next.run(req).await
+    };
+
+    quota_headers(response.headers_mut(), limit, used, reset);
+    response
+}
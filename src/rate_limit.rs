@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::AppState;
+
+/// Who made a request: the peer's address, or the last hop of `X-Forwarded-For` with
+/// `trust_forwarded_for` on. `None` when neither is there, like in tests.
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if state.config.trust_forwarded_for {
+            // The proxy in front appends who it got the request from
+            let forwarded = parts
+                .headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .last()
+                .and_then(|ip| ip.trim().parse().ok());
+            if forwarded.is_some() {
+                return Ok(Self(forwarded));
+            }
+        }
+
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        Ok(Self(peer))
+    }
+}
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// At most `limit` of something per client in every `period`, counted in fixed windows.
+/// IPv6 clients count per /64, since that's what one of them usually gets.
+#[derive(Clone)]
+pub struct RateLimiter {
+    windows: Arc<Mutex<HashMap<IpAddr, Window>>>,
+    limit: u32,
+    period: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, period: Duration) -> Self {
+        Self {
+            windows: Default::default(),
+            limit,
+            period,
+        }
+    }
+
+    /// Counts one for the client, or says how long until it may try again. Clients nobody
+    /// knows the address of aren't limited.
+    pub fn check(&self, ClientIp(ip): &ClientIp) -> Result<(), Duration> {
+        let Some(ip) = ip else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(bucket(*ip)).or_insert(Window {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= self.period {
+            window.started = now;
+            window.count = 0;
+        }
+        if window.count >= self.limit {
+            return Err(self.period - now.duration_since(window.started));
+        }
+
+        window.count += 1;
+        Ok(())
+    }
+
+    /// Forgets windows that are over, which the next [`RateLimiter::check`] would reset anyway.
+    pub fn remove_expired(&self) -> u64 {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        let before = windows.len();
+        windows.retain(|_, window| now.duration_since(window.started) < self.period);
+
+        (before - windows.len()) as u64
+    }
+}
+
+fn bucket(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => {
+                let [a, b, c, d, ..] = ip.segments();
+                IpAddr::V6(Ipv6Addr::new(a, b, c, d, 0, 0, 0, 0))
+            }
+        },
+        ip => ip,
+    }
+}
+
+/// A 429 saying when to come back.
+pub fn too_many_requests(retry_after: Duration, message: &'static str) -> Response {
+    // Rounded up, so coming back right on time works
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, secs.to_string())],
+        message,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(ip: &str) -> ClientIp {
+        ClientIp(Some(ip.parse().unwrap()))
+    }
+
+    #[test]
+    fn clients_get_their_own_limit() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+
+        assert!(limiter.check(&client("192.0.2.1")).is_ok());
+        assert!(limiter.check(&client("192.0.2.1")).is_ok());
+        let retry_after = limiter.check(&client("192.0.2.1")).unwrap_err();
+        assert!(retry_after <= Duration::from_secs(60));
+        assert!(limiter.check(&client("192.0.2.2")).is_ok());
+        assert!(limiter.check(&ClientIp(None)).is_ok());
+    }
+
+    #[test]
+    fn ipv6_clients_count_per_64() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+
+        assert!(limiter.check(&client("2001:db8::1")).is_ok());
+        assert!(limiter.check(&client("2001:db8::2")).is_err());
+        assert!(limiter.check(&client("2001:db8:0:1::1")).is_ok());
+    }
+
+    #[test]
+    fn windows_start_over() {
+        let limiter = RateLimiter::new(1, Duration::ZERO);
+
+        assert!(limiter.check(&client("192.0.2.1")).is_ok());
+        assert!(limiter.check(&client("192.0.2.1")).is_ok());
+        assert_eq!(limiter.remove_expired(), 1);
+    }
+}
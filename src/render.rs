@@ -0,0 +1,268 @@
+use material_colors::color::Argb;
+
+use crate::{errors::RenderError, palette::Palette};
+
+const SWATCH_SIZE: u32 = 40;
+const SWATCH_GAP: u32 = 4;
+const SWATCHES_PER_ROW: u32 = 10;
+const CARD_WIDTH: u32 = 320;
+const CARD_MARGIN: u32 = 20;
+const PANEL_WIDTH: u32 = 400;
+const PANEL_PADDING: u32 = 16;
+const HERO_GAP: u32 = 6;
+const HERO_HEIGHT: u32 = 36;
+/// More hero buttons than this wrap onto another row.
+const HEROES_PER_ROW: u32 = 8;
+
+/// An RGB image to draw rectangles on.
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Argb) -> Self {
+        let pixels =
+            [background.red, background.green, background.blue].repeat((width * height) as usize);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Fills a rectangle, cutting off whatever is outside the canvas.
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Argb) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                let i = ((row * self.width + column) * 3) as usize;
+                self.pixels[i..i + 3].copy_from_slice(&[color.red, color.green, color.blue]);
+            }
+        }
+    }
+
+    /// Fills a rectangle with its corners rounded off, like Material's cards and buttons.
+    pub fn fill_rounded_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        radius: u32,
+        color: Argb,
+    ) {
+        let radius = f64::from(radius.min(width / 2).min(height / 2));
+        let (right, bottom) = (f64::from(width) - radius, f64::from(height) - radius);
+        for row in 0..height {
+            for column in 0..width {
+                // How far the pixel's center is outside the rectangle without the corners
+                let (cx, cy) = (f64::from(column) + 0.5, f64::from(row) + 0.5);
+                let dx = (radius - cx).max(cx - right).max(0.0);
+                let dy = (radius - cy).max(cy - bottom).max(0.0);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.fill_rect(x + column, y + row, 1, 1, color);
+                }
+            }
+        }
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, RenderError> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+
+        Ok(png)
+    }
+}
+
+/// Every color of the palette as a square, in palette order, on the palette's background.
+pub fn swatches(palette: &Palette) -> Canvas {
+    let background = named(palette, "background");
+    let rows = (palette.colors.len() as u32).div_ceil(SWATCHES_PER_ROW);
+    let step = SWATCH_SIZE + SWATCH_GAP;
+
+    let mut canvas = Canvas::new(
+        SWATCHES_PER_ROW * step + SWATCH_GAP,
+        rows * step + SWATCH_GAP,
+        background,
+    );
+    for (i, (_, color)) in palette.colors.iter().enumerate() {
+        let i = i as u32;
+        canvas.fill_rect(
+            SWATCH_GAP + i % SWATCHES_PER_ROW * step,
+            SWATCH_GAP + i / SWATCHES_PER_ROW * step,
+            SWATCH_SIZE,
+            SWATCH_SIZE,
+            *color,
+        );
+    }
+
+    canvas
+}
+
+/// A made-up Material card in the palette's colors: a title and two lines of text (as bars,
+/// there's no font), a filled button and a tonal one, on the palette's background.
+pub fn card(palette: &Palette) -> Canvas {
+    let color = |name| named(palette, name);
+
+    let mut canvas = Canvas::new(CARD_WIDTH + 2 * CARD_MARGIN, 220, color("background"));
+    canvas.fill_rounded_rect(
+        CARD_MARGIN,
+        CARD_MARGIN,
+        CARD_WIDTH,
+        180,
+        12,
+        color("surface_container"),
+    );
+
+    let left = CARD_MARGIN + 20;
+    canvas.fill_rounded_rect(left, 44, 180, 14, 4, color("on_surface"));
+    canvas.fill_rounded_rect(left, 72, 260, 8, 4, color("on_surface_variant"));
+    canvas.fill_rounded_rect(left, 88, 200, 8, 4, color("on_surface_variant"));
+
+    canvas.fill_rounded_rect(left, 144, 120, 36, 18, color("primary"));
+    canvas.fill_rounded_rect(left + 24, 158, 72, 8, 4, color("on_primary"));
+    canvas.fill_rounded_rect(left + 132, 144, 120, 36, 18, color("secondary_container"));
+    canvas.fill_rounded_rect(left + 156, 158, 72, 8, 4, color("on_secondary_container"));
+
+    canvas
+}
+
+/// A made-up Resonite UIX settings panel in the palette's colors: a header, rows of labels with
+/// a slider, a checkbox and a text field, and a row of buttons in the hero colors. `heroes` are
+/// the custom colors to make buttons of, like `red`. The panel gets taller for each row of them.
+pub fn uix_panel(palette: &Palette, heroes: &[&str]) -> Canvas {
+    let color = |name: &str| named(palette, name);
+    let inner = PANEL_WIDTH - 2 * PANEL_PADDING;
+    let rows = (heroes.len() as u32).div_ceil(HEROES_PER_ROW).max(1);
+    let action = 200 + rows * HERO_HEIGHT + (rows - 1) * HERO_GAP + 16;
+
+    let mut canvas = Canvas::new(PANEL_WIDTH, action + 48, color("background"));
+    canvas.fill_rect(0, 0, PANEL_WIDTH, 44, color("surface_container_highest"));
+    canvas.fill_rounded_rect(PANEL_PADDING, 16, 140, 12, 3, color("on_surface"));
+    canvas.fill_rect(0, 44, PANEL_WIDTH, 2, color("primary"));
+
+    let control = PANEL_PADDING + 150;
+    let control_width = inner - 160;
+    for (i, y) in [64, 104, 144].into_iter().enumerate() {
+        canvas.fill_rect(
+            PANEL_PADDING,
+            y - 4,
+            inner,
+            32,
+            color("surface_container_low"),
+        );
+        canvas.fill_rounded_rect(
+            PANEL_PADDING + 10,
+            y + 8,
+            100,
+            8,
+            3,
+            color("on_surface_variant"),
+        );
+        match i {
+            // Slider, filled to the handle
+            0 => {
+                canvas.fill_rect(control, y + 10, control_width, 4, color("surface_variant"));
+                canvas.fill_rect(control, y + 10, control_width * 3 / 5, 4, color("primary"));
+                canvas.fill_rounded_rect(
+                    control + control_width * 3 / 5 - 8,
+                    y + 4,
+                    16,
+                    16,
+                    8,
+                    color("primary"),
+                );
+            }
+            // Checkbox that is on
+            1 => {
+                canvas.fill_rounded_rect(control, y + 2, 20, 20, 3, color("primary"));
+                canvas.fill_rect(control + 5, y + 10, 10, 4, color("on_primary"));
+            }
+            // Text field with some text in it
+            _ => {
+                canvas.fill_rect(control, y, control_width, 24, color("outline"));
+                canvas.fill_rect(
+                    control + 1,
+                    y + 1,
+                    control_width - 2,
+                    22,
+                    color("surface_container"),
+                );
+                canvas.fill_rounded_rect(control + 8, y + 8, 80, 8, 3, color("on_surface"));
+            }
+        }
+    }
+
+    for (row, heroes) in heroes.chunks(HEROES_PER_ROW as usize).enumerate() {
+        let count = heroes.len() as u32;
+        let width = (inner - (count - 1) * HERO_GAP) / count;
+        let y = 200 + row as u32 * (HERO_HEIGHT + HERO_GAP);
+        for (i, hero) in heroes.iter().enumerate() {
+            let x = PANEL_PADDING + i as u32 * (width + HERO_GAP);
+            canvas.fill_rounded_rect(
+                x,
+                y,
+                width,
+                HERO_HEIGHT,
+                4,
+                color(&format!("{}_container", hero)),
+            );
+            canvas.fill_rounded_rect(
+                x + width / 4,
+                y + 14,
+                width / 2,
+                8,
+                3,
+                color(&format!("on_{}_container", hero)),
+            );
+        }
+    }
+
+    // Primary action, like "Apply"
+    canvas.fill_rounded_rect(PANEL_PADDING, action, inner, 32, 4, color("primary"));
+    canvas.fill_rounded_rect(
+        PANEL_WIDTH / 2 - 40,
+        action + 12,
+        80,
+        8,
+        3,
+        color("on_primary"),
+    );
+
+    canvas
+}
+
+/// A color of the palette by name, black if it doesn't have it.
+fn named(palette: &Palette, name: &str) -> Argb {
+    palette
+        .colors
+        .iter()
+        .find(|(color_name, _)| color_name == name)
+        .map_or(Argb::new(255, 0, 0, 0), |(_, color)| *color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hero_buttons_wrap_onto_more_rows() {
+        let palette = Palette { colors: Vec::new() };
+        let heroes = vec!["red"; 100];
+
+        let one_row = uix_panel(&palette, &heroes[..HEROES_PER_ROW as usize]);
+        let many_rows = uix_panel(&palette, &heroes);
+
+        assert_eq!(one_row.height, 300);
+        assert_eq!(uix_panel(&palette, &[]).height, 300);
+        assert_eq!(many_rows.height, 300 + 12 * (HERO_HEIGHT + HERO_GAP));
+    }
+}
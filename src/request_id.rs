@@ -0,0 +1,28 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Makes the request's ID (set by `SetRequestIdLayer`) available to everything handling it,
+/// so error responses can include it without every handler passing it around.
+pub async fn scope(req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+
+    sentry::configure_scope(|scope| scope.set_tag("request_id", &id));
+
+    REQUEST_ID.scope(id, next.run(req)).await
+}
+
+/// The ID of the request currently being handled, if there is one.
+pub fn current() -> Option<String> {
+    REQUEST_ID
+        .try_with(|id| id.clone())
+        .ok()
+        .filter(|id| !id.is_empty())
+}
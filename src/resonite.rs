@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth::ApiKey,
+    cache,
+    config::{default_resonite_api_url, ResoniteConfig},
+    errors::UpstreamError,
+    formats::{Format, FormatOptions},
+    input,
+    palette::ThemeType,
+    resonite_object, AppError, AppState,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A Resonite user and their token, from logging in.
+#[derive(Deserialize, ToSchema)]
+pub struct ResoniteCredentials {
+    #[schema(example = "U-someone")]
+    user_id: String,
+    token: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CloudVariableWrite {
+    #[schema(example = "59EB5C")]
+    base_color: String,
+    theme_type: ThemeType,
+    /// What gets written, defaults to `raw`
+    #[serde(default)]
+    format: Format,
+    #[serde(flatten)]
+    options: FormatOptions,
+    /// `U-...` or `G-...`, defaults to the user writing it
+    owner_id: Option<String>,
+    /// The variable's path, as it was defined
+    #[schema(example = "theme.palette")]
+    path: String,
+    /// Write as this user instead of the instance's Resonite account
+    credentials: Option<ResoniteCredentials>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CloudVariableWritten {
+    owner_id: String,
+    path: String,
+    value: String,
+}
+
+/// What Resonite's API takes for a cloud variable.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CloudVariable<'a> {
+    owner_id: &'a str,
+    path: &'a str,
+    value: &'a str,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ObjectQuery {
+    #[param(example = "59EB5C")]
+    base_color: String,
+    theme_type: ThemeType,
+}
+
+/// Resonite integrations, which go in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/resonite/cloudvars", post(write_cloud_variable))
+        .route("/resonite/object", get(object))
+}
+
+/// IDs and paths go into the URL as they are.
+fn url_safe(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn bad_request(message: &str) -> Response {
+    (StatusCode::BAD_REQUEST, message.to_owned()).into_response()
+}
+
+/// Generates a palette and writes it into a Resonite cloud variable, so worlds can read it
+/// like any other variable instead of calling us.
+#[utoipa::path(
+    post,
+    path = "/v1/resonite/cloudvars",
+    request_body = CloudVariableWrite,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The variable got written", body = CloudVariableWritten),
+        (status = 400, description = "Invalid color, owner or path", body = String),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 502, description = "Resonite didn't take it", body = String),
+        (status = 503, description = "Resonite is down or didn't answer in time, try again after `Retry-After` seconds", body = String),
+        (status = 503, description = "No credentials given, and this instance has no Resonite account", body = String)
+    )
+)]
+pub async fn write_cloud_variable(
+    State(state): State<AppState>,
+    key: ApiKey,
+    Json(write): Json<CloudVariableWrite>,
+) -> Result<Response, AppError> {
+    let configured = state.config.resonite.as_ref();
+    let api_url = configured.map_or_else(default_resonite_api_url, |resonite| {
+        resonite.api_url.clone()
+    });
+    let (user_id, token) = match (&write.credentials, configured) {
+        (Some(credentials), _) => (&credentials.user_id, &credentials.token),
+        (None, Some(ResoniteConfig { user_id, token, .. })) => (user_id, token),
+        (None, None) => {
+            return Ok((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "This instance has no Resonite account, send credentials",
+            )
+                .into_response())
+        }
+    };
+
+    let owner_id = write.owner_id.as_deref().unwrap_or(user_id);
+    let owner_kind = match owner_id.get(..2) {
+        Some("U-") => "users",
+        Some("G-") => "groups",
+        _ => return Ok(bad_request("owner_id has to start with U- or G-")),
+    };
+    if !url_safe(owner_id) {
+        return Ok(bad_request("Invalid owner_id"));
+    }
+    if !url_safe(&write.path) {
+        return Ok(bad_request(
+            "The path can only have letters, digits, '.', '_' and '-'",
+        ));
+    }
+    let Ok(source) = input::parse_color(&write.base_color) else {
+        return Ok(bad_request("Invalid base_color"));
+    };
+
+    let palette = cache::palette(&state, source, write.theme_type).await?;
+    let value = write.format.render(&palette, &write.options);
+
+    let url = format!(
+        "{}/{}/{}/vars/{}",
+        api_url.trim_end_matches('/'),
+        owner_kind,
+        owner_id,
+        write.path
+    );
+    let result = state
+        .http
+        .put(&url)
+        .timeout(TIMEOUT)
+        .header(
+            reqwest::header::AUTHORIZATION,
+            format!("res {}:{}", user_id, token),
+        )
+        .json(&CloudVariable {
+            owner_id,
+            path: &write.path,
+            value: &value,
+        })
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    metrics::counter!("resonite_cloud_variable_writes_total", "result" => outcome).increment(1);
+    if let Err(err) = result {
+        warn!("Couldn't write cloud variable {}: {}", write.path, err);
+        return Err(UpstreamError::from_reqwest("Resonite", &err).into());
+    }
+
+    info!(
+        "{} wrote cloud variable {} of {}",
+        key.name, write.path, owner_id
+    );
+    Ok(Json(CloudVariableWritten {
+        owner_id: owner_id.to_owned(),
+        path: write.path,
+        value,
+    })
+    .into_response())
+}
+
+/// Downloads the palette as a Resonite object: a slot with a dynamic variable space (named
+/// after `dynvar_space`), and a child slot per color with a `colorX` dynamic variable. It
+/// follows the layout of objects Resonite saves, for creators who'd rather drop a theme
+/// into their world than parse a string.
+#[utoipa::path(
+    get,
+    path = "/v1/resonite/object",
+    params(ObjectQuery, FormatOptions),
+    responses(
+        (status = 200, description = "The object, as a download", body = Object, content_type = "application/json"),
+        (status = 400, description = "Invalid color", body = String)
+    )
+)]
+pub async fn object(
+    State(state): State<AppState>,
+    Query(query): Query<ObjectQuery>,
+    Query(options): Query<FormatOptions>,
+) -> Result<Response, AppError> {
+    let Ok(source) = input::parse_color(&query.base_color) else {
+        return Ok(bad_request("Invalid base_color"));
+    };
+
+    let palette = cache::palette(&state, source, query.theme_type).await?;
+    let object = resonite_object::build(&palette, options.dynvar_space());
+
+    let disposition = format!(
+        "attachment; filename=\"theme-{}-{}.json\"",
+        source.to_hex(),
+        query.theme_type.as_str().to_lowercase()
+    );
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(object)).into_response())
+}
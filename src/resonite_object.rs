@@ -0,0 +1,106 @@
+use serde_json::{json, Map, Value};
+
+use crate::{formats, palette::Palette};
+
+const COMPONENT_TYPES: &[&str] = &[
+    "[FrooxEngine]FrooxEngine.DynamicVariableSpace",
+    "[FrooxEngine]FrooxEngine.DynamicValueVariable<colorX>",
+];
+const SPACE_TYPE: usize = 0;
+const VARIABLE_TYPE: usize = 1;
+
+/// Hands out the IDs every slot, component and field needs. Resonite gives everything new
+/// ones when it loads the object, they only have to be unique within it.
+struct Ids(usize);
+
+impl Ids {
+    fn next(&mut self) -> String {
+        self.0 += 1;
+        format!("RMY-{}", self.0)
+    }
+
+    fn field(&mut self, data: Value) -> Value {
+        json!({"ID": self.next(), "Data": data})
+    }
+
+    fn component(&mut self, type_index: usize, fields: Map<String, Value>) -> Value {
+        let mut data = Map::new();
+        data.insert("ID".to_string(), self.next().into());
+        data.insert("persistent-ID".to_string(), self.next().into());
+        data.insert("UpdateOrder".to_string(), self.field(0.into()));
+        data.insert("Enabled".to_string(), self.field(true.into()));
+        data.extend(fields);
+
+        json!({"Type": type_index, "Data": data})
+    }
+
+    fn slot(
+        &mut self,
+        name: &str,
+        parent: Option<&str>,
+        components: Vec<Value>,
+        children: Vec<Value>,
+    ) -> Value {
+        json!({
+            "ID": self.next(),
+            "Components": self.field(components.into()),
+            "Name": self.field(name.into()),
+            "Tag": self.field(Value::Null),
+            "Active": self.field(true.into()),
+            "Persistent-ID": self.next(),
+            "Position": self.field(json!([0.0, 0.0, 0.0])),
+            "Rotation": self.field(json!([0.0, 0.0, 0.0, 1.0])),
+            "Scale": self.field(json!([1.0, 1.0, 1.0])),
+            "OrderOffset": self.field(0.into()),
+            "ParentReference": parent,
+            "Children": children,
+        })
+    }
+}
+
+/// The palette as a Resonite object, in the layout of its saved objects: a slot with a dynamic
+/// variable space, and a child slot per color with a `colorX` variable like `Theme/Primary`.
+pub fn build(palette: &Palette, space: &str) -> Value {
+    let mut ids = Ids(0);
+    let root_id = "RMY-root";
+
+    let children = palette
+        .colors
+        .iter()
+        .map(|(name, color)| {
+            let [r, g, b] = [color.red, color.green, color.blue].map(|c| c as f64 / 255.0);
+            let mut fields = Map::new();
+            fields.insert(
+                "VariableName".to_string(),
+                ids.field(format!("{}/{}", space, formats::dynvar_name(name)).into()),
+            );
+            fields.insert(
+                "Value".to_string(),
+                ids.field(json!({"r": r, "g": g, "b": b, "a": 1.0, "profile": "sRGB"})),
+            );
+            let variable = ids.component(VARIABLE_TYPE, fields);
+
+            ids.slot(name, Some(root_id), vec![variable], Vec::new())
+        })
+        .collect();
+
+    let mut fields = Map::new();
+    fields.insert("SpaceName".to_string(), ids.field(space.into()));
+    fields.insert("OnlyDirectBinding".to_string(), ids.field(false.into()));
+    let variable_space = ids.component(SPACE_TYPE, fields);
+
+    let mut root = ids.slot(
+        &format!("{} (Material You)", space),
+        None,
+        vec![variable_space],
+        children,
+    );
+    root["ID"] = root_id.into();
+
+    json!({
+        "Types": COMPONENT_TYPES,
+        "TypeVersions": {},
+        "Object": root,
+        "Assets": [],
+    })
+}
@@ -0,0 +1,105 @@
+use std::{future::Future, time::Duration};
+
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use crate::AppState;
+
+const EXPIRED_PALETTES_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const STALE_CACHE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const IDLE_CHANNELS_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const RATE_LIMITS_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const EXPIRED_PAIRINGS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Theme channels nobody listens to are forgotten once they haven't changed for this long.
+const CHANNEL_MAX_IDLE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Daily counters older than this get merged into one row per month.
+const ROLLUP_AFTER_DAYS: i32 = 90;
+
+/// Starts the housekeeping tasks, which run for as long as the server does. Each one only
+/// gets started if what it cleans up is configured.
+pub fn start(state: &AppState) {
+    let channels = state.channels.clone();
+    every("idle_channels", IDLE_CHANNELS_INTERVAL, move || {
+        let channels = channels.clone();
+        async move { Ok(channels.remove_idle(CHANNEL_MAX_IDLE)) }
+    });
+    let channels = state.channels.clone();
+    every("channel_rate_limits", RATE_LIMITS_INTERVAL, move || {
+        let channels = channels.clone();
+        async move { Ok(channels.rate_limits().remove_expired()) }
+    });
+    let votes = state.votes.clone();
+    every("proposal_rate_limits", RATE_LIMITS_INTERVAL, move || {
+        let votes = votes.clone();
+        async move { Ok(votes.rate_limits().remove_expired()) }
+    });
+    let discord_posts = state.discord_posts.clone();
+    every("recent_discord_posts", RATE_LIMITS_INTERVAL, move || {
+        let discord_posts = discord_posts.clone();
+        async move { Ok(discord_posts.remove_expired()) }
+    });
+    let pairings = state.pairings.clone();
+    every("expired_pairings", EXPIRED_PAIRINGS_INTERVAL, move || {
+        let pairings = pairings.clone();
+        async move { Ok(pairings.remove_expired()) }
+    });
+
+    if let Some(storage) = state.storage.clone() {
+        let expired_storage = storage.clone();
+        every("expired_palettes", EXPIRED_PALETTES_INTERVAL, move || {
+            let storage = expired_storage.clone();
+            async move { Ok(storage.delete_expired().await?) }
+        });
+        every("counter_rollups", ROLLUP_INTERVAL, move || {
+            let storage = storage.clone();
+            async move { Ok(storage.roll_up_counters(ROLLUP_AFTER_DAYS).await?) }
+        });
+    }
+
+    if let Some(cache) = state.cache.clone() {
+        every("stale_cache_entries", STALE_CACHE_INTERVAL, move || {
+            let cache = cache.clone();
+            async move { cache.delete_stale().await }
+        });
+    }
+}
+
+/// Runs `task` every `interval`, starting right away. Tasks return how many things they
+/// cleaned up, which ends up in the logs and metrics.
+fn every<F, Fut>(name: &'static str, interval: Duration, task: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<u64>> + Send,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        // A slow run shouldn't be followed by a burst of catching up
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let start = Instant::now();
+            let result = task().await;
+            metrics::histogram!("scheduled_task_duration_seconds", "task" => name)
+                .record(start.elapsed().as_secs_f64());
+
+            let outcome = if result.is_ok() { "ok" } else { "error" };
+            metrics::counter!("scheduled_task_runs_total", "task" => name, "result" => outcome)
+                .increment(1);
+
+            match result {
+                Ok(0) => debug!("{} had nothing to do", name),
+                Ok(items) => {
+                    metrics::counter!("scheduled_task_items_total", "task" => name)
+                        .increment(items);
+                    info!("{} cleaned up {} items", name, items);
+                }
+                Err(err) => warn!("{} failed: {}", name, err),
+            }
+        }
+    });
+}
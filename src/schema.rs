@@ -0,0 +1,152 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
+    routing::get,
+    Router,
+};
+use material_colors::color::Argb;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    cache,
+    errors::{InputError, RenderError},
+    http_cache,
+    palette::{ThemeType, SCHEMA_VERSION},
+    AppError, AppState,
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SchemaQuery {
+    /// Layout version, defaults to the current one (1)
+    version: Option<u32>,
+}
+
+/// Where every color is in the `raw` format.
+#[derive(Serialize, ToSchema)]
+pub struct Schema {
+    version: u32,
+    /// Characters in the whole string
+    length: usize,
+    roles: Vec<Role>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Role {
+    #[schema(example = "primary_container")]
+    name: String,
+    /// Characters before this color's hex value
+    #[schema(example = 12)]
+    offset: usize,
+    length: usize,
+}
+
+/// The names of every color, in order.
+#[derive(Serialize, ToSchema)]
+pub struct Roles {
+    version: u32,
+    #[schema(example = json!(["primary", "on_primary", "primary_container"]))]
+    roles: Vec<String>,
+}
+
+/// The palette layout, which goes in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/schema", get(schema))
+        .route("/roles", get(roles))
+}
+
+/// The layout version asked for, if there is one.
+fn version(query: &SchemaQuery) -> Result<u32, InputError> {
+    let version = query.version.unwrap_or(SCHEMA_VERSION);
+    if version != SCHEMA_VERSION {
+        let message = format!("There's only version {} of the layout", SCHEMA_VERSION);
+        return Err(InputError::new(message));
+    }
+
+    Ok(version)
+}
+
+/// The color names of this instance's palettes, custom colors included.
+async fn role_names(state: &AppState) -> Result<Vec<String>, RenderError> {
+    // The names and their order don't depend on the colors
+    let palette = cache::generate(state, Argb::new(255, 0, 0, 0), ThemeType::Dark).await?;
+    Ok(palette.names())
+}
+
+/// Lists the colors of the `raw` format in order, with the offset each one starts at, so
+/// parsers don't have to hardcode them. Custom colors come after the scheme, depending on
+/// this instance's config.
+#[utoipa::path(
+    get,
+    path = "/v1/schema",
+    params(SchemaQuery),
+    responses(
+        (status = 200, description = "The layout", body = Schema),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "No such layout version", body = String)
+    )
+)]
+pub async fn schema(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SchemaQuery>,
+) -> Result<Response, AppError> {
+    let version = version(&query)?;
+
+    let roles: Vec<Role> = role_names(&state)
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| Role {
+            name,
+            offset: i * 6,
+            length: 6,
+        })
+        .collect();
+    let schema = Schema {
+        version,
+        length: roles.len() * 6,
+        roles,
+    };
+
+    Ok(http_cache::cacheable(
+        &headers,
+        serde_json::to_string(&schema)?,
+        "application/json",
+        http_cache::Caching::public(state.config.cache_max_age_secs),
+    ))
+}
+
+/// Just the color names of [`schema`], in order, for generating parsers or UIs from.
+#[utoipa::path(
+    get,
+    path = "/v1/roles",
+    params(SchemaQuery),
+    responses(
+        (status = 200, description = "The names", body = Roles),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "No such layout version", body = String)
+    )
+)]
+pub async fn roles(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SchemaQuery>,
+) -> Result<Response, AppError> {
+    let version = version(&query)?;
+
+    let roles = Roles {
+        version,
+        roles: role_names(&state).await?,
+    };
+
+    Ok(http_cache::cacheable(
+        &headers,
+        serde_json::to_string(&roles)?,
+        "application/json",
+        http_cache::Caching::public(state.config.cache_max_age_secs),
+    ))
+}
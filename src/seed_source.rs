@@ -0,0 +1,114 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use material_colors::color::Argb;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth::ApiKey,
+    errors::InputError,
+    palette::ThemeType,
+    seeds::{Hex, Random, SeedSource, TextHash},
+    storage::HistoryEntry,
+    AppError, AppState,
+};
+
+/// Where `getPalette` gets its seed color from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SeedKind {
+    /// `base_color` is the seed
+    #[default]
+    Hex,
+    /// `base_color` is any text, like a username, which always gets the same seed
+    Text,
+    /// A different seed every time, `base_color` isn't needed
+    Random,
+    /// The seed the API key generated a palette with last, `base_color` isn't needed
+    Profile,
+}
+
+/// The parameters of endpoints that only need a palette, like the previews.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SeedQuery {
+    /// Source color as hex, like `getPalette`'s
+    #[param(example = "59EB5C")]
+    pub base_color: Option<String>,
+    #[serde(default)]
+    pub seed: SeedKind,
+    pub theme_type: ThemeType,
+}
+
+/// The last seed in an API key's history.
+pub struct Profile(pub Vec<HistoryEntry>);
+
+impl SeedSource for Profile {
+    fn seed(&self) -> anyhow::Result<Argb> {
+        let latest = self
+            .0
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("This API key hasn't generated any palettes yet"))?;
+        Hex(&latest.base_color).seed()
+    }
+
+    fn deterministic(&self) -> bool {
+        false
+    }
+}
+
+pub struct Seed {
+    pub color: Argb,
+    /// Whether the response can be cached like any other palette
+    pub deterministic: bool,
+}
+
+/// The seed a request asks for, or the response saying why there isn't one.
+pub async fn resolve(
+    state: &AppState,
+    key: Option<&ApiKey>,
+    kind: SeedKind,
+    base_color: Option<&str>,
+) -> Result<Seed, Response> {
+    let error = |status, message: &str| (status, message.to_string()).into_response();
+    let missing = || InputError::new("base_color is missing").into_response();
+
+    let source: Box<dyn SeedSource + '_> = match kind {
+        SeedKind::Hex => Box::new(Hex(base_color.ok_or_else(missing)?)),
+        SeedKind::Text => Box::new(TextHash(base_color.ok_or_else(missing)?)),
+        SeedKind::Random => Box::new(Random),
+        SeedKind::Profile => {
+            let key = key
+                .ok_or_else(|| error(StatusCode::UNAUTHORIZED, "seed=profile needs an API key"))?;
+            let storage = state.storage.as_ref().ok_or_else(|| {
+                error(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Palette storage isn't set up on this instance",
+                )
+            })?;
+            let history = storage
+                .history(&key.name)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            if history.is_empty() {
+                return Err(
+                    InputError::new("This API key hasn't generated any palettes yet")
+                        .into_response(),
+                );
+            }
+            Box::new(Profile(history))
+        }
+    };
+
+    let color = source.seed().map_err(|err| match kind {
+        // The only way these fail is a bad color
+        SeedKind::Hex => InputError::invalid_color("base_color").into_response(),
+        _ => AppError::from(err).into_response(),
+    })?;
+    Ok(Seed {
+        color,
+        deterministic: source.deterministic(),
+    })
+}
@@ -0,0 +1,111 @@
+use material_colors::{
+    color::Argb,
+    hct::Hct,
+    quantize::{Quantizer, QuantizerWsmeans, QuantizerWu},
+    score::Score,
+};
+
+use crate::input;
+
+/// Chroma and tone of the seeds made up from a hue, colorful enough for a lively palette.
+const MADE_UP_CHROMA: f64 = 48.0;
+const MADE_UP_TONE: f64 = 60.0;
+/// Clusters the quantizer starts with, which scoring narrows down to the seeds.
+const QUANTIZE_COLORS: usize = 128;
+
+/// Something a palette's seed color comes from. Each kind of input is its own implementation,
+/// so a new one doesn't mean another branch in every handler.
+pub trait SeedSource {
+    fn seed(&self) -> anyhow::Result<Argb>;
+
+    /// Whether the same input always gives the same seed, so responses can be cached.
+    fn deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// A hex color like `base_color`, taken as it is.
+pub struct Hex<'a>(pub &'a str);
+
+impl SeedSource for Hex<'_> {
+    fn seed(&self) -> anyhow::Result<Argb> {
+        input::parse_color(self.0)
+    }
+}
+
+/// A color picked by hashing some text, like a username, so it always gets the same theme.
+/// The hash is written out here instead of using std's, which may change between releases.
+pub struct TextHash<'a>(pub &'a str);
+
+impl SeedSource for TextHash<'_> {
+    fn seed(&self) -> anyhow::Result<Argb> {
+        // 64 bit FNV-1a
+        let hash = self.0.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        Ok(from_hue((hash % 360) as f64))
+    }
+}
+
+/// A different color every time.
+pub struct Random;
+
+impl SeedSource for Random {
+    fn seed(&self) -> anyhow::Result<Argb> {
+        let mut bytes = [0; 2];
+        getrandom::getrandom(&mut bytes)?;
+        Ok(from_hue(f64::from(u16::from_le_bytes(bytes) % 360)))
+    }
+
+    fn deterministic(&self) -> bool {
+        false
+    }
+}
+
+/// The colors of an image, ranked the way Android picks seeds from a wallpaper.
+///
+/// The quantizer keeps a 1.4 MB histogram on the stack, so this needs a thread with a big one.
+pub struct Pixels<'a> {
+    pub pixels: &'a [Argb],
+    /// How long the quantizer refines its clusters, more finds colors a bit better
+    pub iterations: i32,
+}
+
+impl Pixels<'_> {
+    /// Up to `count` seeds, best first. Fewer if the image doesn't have that many distinct
+    /// colors, none if it has no pixels.
+    pub fn seeds(&self, count: i32) -> Vec<Argb> {
+        if self.pixels.is_empty() {
+            return Vec::new();
+        }
+
+        // What `QuantizerCelebi` does, with our number of iterations
+        let starting_clusters: Vec<Argb> = QuantizerWu::default()
+            .quantize(self.pixels, QUANTIZE_COLORS, None)
+            .color_to_count
+            .into_keys()
+            .collect();
+        let quantized = QuantizerWsmeans::quantize(
+            self.pixels,
+            QUANTIZE_COLORS,
+            Some(&starting_clusters),
+            None,
+            Some(self.iterations),
+            None,
+        );
+        Score::score(&quantized.color_to_count, Some(count), None, None)
+    }
+}
+
+impl SeedSource for Pixels<'_> {
+    fn seed(&self) -> anyhow::Result<Argb> {
+        self.seeds(1)
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("The image has no opaque pixels"))
+    }
+}
+
+fn from_hue(hue: f64) -> Argb {
+    Hct::from(hue, MADE_UP_CHROMA, MADE_UP_TONE).into()
+}
@@ -0,0 +1,100 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    errors::InputError,
+    palette::{CustomColorSpec, Palette},
+};
+
+/// Which colors of the palette a response has, all of them by default.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Selection {
+    /// `false` leaves out the custom colors at the end, so only the scheme's are left
+    #[serde(default = "include_customs")]
+    #[param(default = true)]
+    pub include_customs: bool,
+    /// `customs` keeps only the custom colors
+    pub only: Option<Only>,
+    /// Only these colors, in this order, like `primary,onPrimary,surface,onSurface`. Names can
+    /// be written like `on_primary` too
+    #[param(example = "primary,onPrimary,surface,onSurface")]
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Only {
+    /// The custom colors, for worlds that keep the scheme fixed but change their accents
+    Customs,
+}
+
+fn include_customs() -> bool {
+    true
+}
+
+impl Selection {
+    /// The colors asked for, or the 400 for a name that isn't in the palette. Every palette
+    /// ends with `custom_colors`, four colors each.
+    pub fn apply<'a>(
+        &self,
+        palette: &'a Palette,
+        custom_colors: &[CustomColorSpec],
+    ) -> Result<Cow<'a, Palette>, InputError> {
+        let scheme_colors = palette.colors.len() - custom_colors.len() * 4;
+        let colors = match (self.only, self.include_customs) {
+            (None, true) => None,
+            (None, false) => Some(&palette.colors[..scheme_colors]),
+            (Some(Only::Customs), true) => Some(&palette.colors[scheme_colors..]),
+            (Some(Only::Customs), false) => {
+                return Err(InputError::new(
+                    "only=customs and include_customs=false leave no colors",
+                ))
+            }
+        };
+        let palette = match colors {
+            Some(colors) => Cow::Owned(Palette {
+                colors: colors.to_vec(),
+            }),
+            None => Cow::Borrowed(palette),
+        };
+
+        let Some(fields) = &self.fields else {
+            return Ok(palette);
+        };
+
+        let colors = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                palette
+                    .colors
+                    .iter()
+                    .find(|(name, _)| same_name(name, field))
+                    .cloned()
+                    .ok_or_else(|| InputError::new(format!("There's no color {:?}", field)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if colors.is_empty() {
+            return Err(InputError::new("fields has to name at least one color"));
+        }
+
+        Ok(Cow::Owned(Palette { colors }))
+    }
+}
+
+/// `on_primary` is `onPrimary`, which is how most clients spell them.
+fn same_name(name: &str, field: &str) -> bool {
+    let mut name = name.chars().filter(|&c| c != '_');
+    let mut field = field.chars().filter(|&c| c != '_');
+    loop {
+        match (name.next(), field.next()) {
+            (Some(a), Some(b)) if a.eq_ignore_ascii_case(&b) => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
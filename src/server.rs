@@ -0,0 +1,134 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use hyper_util::{
+    rt::{TokioExecutor, TokioTimer},
+    server::conn::auto::Builder,
+};
+use tokio::signal;
+use tracing::info;
+
+use crate::{
+    config::{HttpConfig, TlsConfig},
+    error_reporting,
+    shutdown::Shutdown,
+    telemetry,
+};
+
+/// Like `shuttle_axum::AxumService`, but lets in-flight requests finish when we get told to stop.
+#[cfg(feature = "shuttle")]
+pub struct AppService {
+    pub router: Router,
+    /// The defaults, but for how long shutting down may take
+    pub http: HttpConfig,
+    pub shutdown: Shutdown,
+}
+
+#[cfg(feature = "shuttle")]
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for AppService {
+    async fn bind(mut self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        // Shuttle terminates TLS for us, and its proxy is what clients keep connections open to
+        serve(self.router, addr, None, &self.http, &self.shutdown)
+            .await
+            .map_err(shuttle_runtime::CustomError::new)?;
+
+        Ok(())
+    }
+}
+
+/// Serves the router (over HTTPS if `tls` is given) until we get a shutdown signal, then stops
+/// accepting connections, closes the theme streams, gives in-flight requests and background
+/// writes `shutdown_grace_secs` to finish and flushes anything we'd otherwise lose.
+///
+/// HTTP/2 works either way, over TLS through ALPN and in cleartext for clients that start
+/// with it (h2c with prior knowledge).
+pub async fn serve(
+    router: Router,
+    addr: SocketAddr,
+    tls: Option<&TlsConfig>,
+    http: &HttpConfig,
+    shutdown: &Shutdown,
+) -> std::io::Result<()> {
+    let grace = Duration::from_secs(http.shutdown_grace_secs);
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            // Streams would keep their connections open for as long as the clients like
+            shutdown.start();
+            handle.graceful_shutdown(Some(grace));
+        }
+    });
+
+    // The peer's address is what rate limits go by
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+    match tls {
+        Some(tls) => {
+            let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await?;
+            let mut server = axum_server::bind_rustls(addr, rustls_config).handle(handle);
+            configure(server.http_builder(), http);
+
+            info!("Listening on {} (HTTPS)", addr);
+            server.serve(service).await?;
+        }
+        None => {
+            let mut server = axum_server::bind(addr).handle(handle);
+            configure(server.http_builder(), http);
+
+            info!("Listening on {}", addr);
+            server.serve(service).await?;
+        }
+    }
+
+    shutdown.writes_done(grace).await;
+    info!("Flushing before exiting");
+    error_reporting::flush();
+    telemetry::shutdown();
+
+    Ok(())
+}
+
+fn configure(builder: &mut Builder<TokioExecutor>, http: &HttpConfig) {
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(http.keep_alive)
+        .header_read_timeout(Duration::from_secs(http.header_read_timeout_secs));
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(http.http2_keep_alive_interval_secs.map(Duration::from_secs))
+        .keep_alive_timeout(Duration::from_secs(http.http2_keep_alive_timeout_secs))
+        .max_concurrent_streams(http.http2_max_concurrent_streams);
+}
+
+// !!! From https://github.com/tokio-rs/axum/blob/main/examples/graceful-shutdown/src/main.rs !!!
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("Shutdown signal received, finishing in-flight requests");
+}
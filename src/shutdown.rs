@@ -0,0 +1,93 @@
+use std::{future::Future, time::Duration};
+
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::warn;
+
+/// The server stopping. Streams that would stay open forever end once it starts, and writes
+/// that shouldn't get lost get waited for before anything is flushed.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    stopping: CancellationToken,
+    writes: TaskTracker,
+}
+
+impl Shutdown {
+    /// Tells every stream to end.
+    pub fn start(&self) {
+        self.stopping.cancel();
+    }
+
+    /// Done once [`Shutdown::start`] is called.
+    pub async fn stopping(&self) {
+        self.stopping.cancelled().await
+    }
+
+    /// `stream`, until the server starts stopping.
+    pub fn until_stopping<S: Stream>(&self, stream: S) -> impl Stream<Item = S::Item> {
+        let stopping = self.stopping.clone();
+        let stopped = tokio_stream::once(()).then(move |()| stopping.clone().cancelled_owned());
+        stream
+            .map(Some)
+            .merge(stopped.map(|()| None))
+            .map_while(|item| item)
+    }
+
+    /// Runs a write in the background, which the server waits for before exiting.
+    pub fn spawn_write(&self, write: impl Future<Output = ()> + Send + 'static) {
+        self.writes.spawn(write);
+    }
+
+    /// Waits up to `grace` for the writes still going.
+    pub async fn writes_done(&self, grace: Duration) {
+        self.writes.close();
+        if tokio::time::timeout(grace, self.writes.wait())
+            .await
+            .is_err()
+        {
+            warn!(
+                "Gave up on {} writes that didn't finish in time",
+                self.writes.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn streams_end_once_stopping() {
+        let shutdown = Shutdown::default();
+        let mut stream = Box::pin(
+            shutdown.until_stopping(tokio_stream::iter([1, 2]).chain(tokio_stream::pending())),
+        );
+
+        assert_eq!(stream.next().await, Some(1));
+        assert_eq!(stream.next().await, Some(2));
+        shutdown.start();
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn writes_get_waited_for() {
+        let shutdown = Shutdown::default();
+        let written = Arc::new(AtomicBool::new(false));
+        shutdown.spawn_write({
+            let written = written.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                written.store(true, Ordering::SeqCst);
+            }
+        });
+
+        shutdown.writes_done(Duration::from_secs(5)).await;
+        assert!(written.load(Ordering::SeqCst));
+    }
+}
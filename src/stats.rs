@@ -0,0 +1,52 @@
+use axum::{extract::Query, response::IntoResponse, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    palette::ThemeType,
+    storage::{SeedCount, Storage},
+    AppError, AppState,
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PopularQuery {
+    /// How far back to count, defaults to 30 (at most 365)
+    days: Option<i32>,
+    /// Only one theme type
+    theme_type: Option<ThemeType>,
+    /// Defaults to 20 (at most 100)
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Popular {
+    days: i32,
+    popular: Vec<SeedCount>,
+}
+
+/// Aggregate statistics, which go in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/stats/popular", get(popular))
+}
+
+/// The most requested seed colors. Only colors and theme types are counted, not who asked.
+#[utoipa::path(
+    get,
+    path = "/v1/stats/popular",
+    params(PopularQuery),
+    responses(
+        (status = 200, description = "Seed colors by request count", body = Popular),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn popular(
+    storage: Storage,
+    Query(query): Query<PopularQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let days = query.days.unwrap_or(30).clamp(1, 365);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let popular = storage.popular_seeds(days, query.theme_type, limit).await?;
+
+    Ok(Json(Popular { days, popular }))
+}
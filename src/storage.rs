@@ -0,0 +1,924 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts, http::StatusCode};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgPoolOptions, Encode, FromRow, PgPool, Postgres, Type};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    errors::StorageError,
+    palette::{Palette, ThemeType},
+    AppState,
+};
+
+const PALETTE_COLUMNS: &str = "id, short_code, base_color, theme_type, palette, color_names, \
+                               name, description, tags, owner, expires_at, version, \
+                               regenerated_at, created_at";
+
+/// Expired palettes stay in the table until the next cleanup, this keeps them out until then.
+const NOT_EXPIRED: &str = "(expires_at IS NULL OR expires_at > now())";
+
+/// Palettes [`Storage::owned_palettes`] reads ahead of whoever is sending them out.
+const OWNED_PALETTES_BUFFER: usize = 64;
+
+/// Characters for short codes, leaving out ones that are easy to mix up when typed over.
+const SHORT_CODE_ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ";
+const SHORT_CODE_LENGTH: usize = 8;
+
+/// A palette as it was generated when it got stored. It only changes when its owner
+/// regenerates it (even if the defaults or material-colors change), and every earlier
+/// version stays available.
+#[derive(Serialize, ToSchema)]
+pub struct StoredPalette {
+    pub id: Uuid,
+    /// For the short link, `/p/{short_code}`
+    pub short_code: String,
+    pub base_color: String,
+    pub theme_type: ThemeType,
+    /// Same format as `/v1/getPalette`
+    pub palette: String,
+    #[serde(skip)]
+    pub color_names: Vec<String>,
+    #[serde(flatten)]
+    pub details: PaletteDetails,
+    #[serde(skip)]
+    pub owner: Option<String>,
+    /// After this, the palette is gone for good
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Starts at 1, and goes up every time the palette gets regenerated
+    pub version: i32,
+    pub regenerated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What looking for a stored palette can come up with.
+pub enum PaletteLookup {
+    Found(Box<StoredPalette>),
+    /// It existed, but expired at this time
+    Expired(DateTime<Utc>),
+    Missing,
+}
+
+/// What people can describe their stored palettes with.
+#[derive(Default, Deserialize, Serialize, ToSchema)]
+pub struct PaletteDetails {
+    #[schema(example = "Spooky")]
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Lowercase, for searching with `GET /v1/palettes?tag=`
+    #[serde(default)]
+    #[schema(example = json!(["halloween"]))]
+    pub tags: Vec<String>,
+}
+
+/// One version of a stored palette, which can be used instead of the latest one.
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct PaletteVersion {
+    pub version: i32,
+    pub base_color: String,
+    /// `Dark` or `Light`
+    pub theme_type: String,
+    /// Same format as `/v1/getPalette`
+    pub palette: String,
+    #[serde(skip)]
+    pub color_names: Vec<String>,
+    /// When this version was generated
+    pub created_at: DateTime<Utc>,
+}
+
+/// Colors of palettes stored before their names were kept get called `color_N`.
+fn read_palette(names: &[String], wire: &str) -> anyhow::Result<Palette> {
+    let names = if names.is_empty() {
+        (0..wire.len() / 6)
+            .map(|i| format!("color_{}", i))
+            .collect()
+    } else {
+        names.to_vec()
+    };
+
+    Palette::from_wire_string(names, wire)
+}
+
+impl StoredPalette {
+    /// The stored colors, for writing them out in any format.
+    pub fn to_palette(&self) -> anyhow::Result<Palette> {
+        read_palette(&self.color_names, &self.palette)
+    }
+}
+
+impl PaletteVersion {
+    pub fn to_palette(&self) -> anyhow::Result<Palette> {
+        read_palette(&self.color_names, &self.palette)
+    }
+}
+
+/// A palette someone generated with their API key.
+#[derive(Serialize, ToSchema)]
+pub struct HistoryEntry {
+    pub base_color: String,
+    pub theme_type: ThemeType,
+    pub palette: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct HistoryRow {
+    base_color: String,
+    theme_type: String,
+    palette: String,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<HistoryRow> for HistoryEntry {
+    type Error = StorageError;
+
+    fn try_from(row: HistoryRow) -> Result<Self, StorageError> {
+        Ok(Self {
+            base_color: row.base_color,
+            theme_type: row.theme_type.parse()?,
+            palette: row.palette,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// How often a seed color got requested.
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct SeedCount {
+    pub base_color: String,
+    /// `Dark` or `Light`
+    pub theme_type: String,
+    pub count: i64,
+}
+
+/// Requests in one day, or to one endpoint, or in one format.
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct UsageCount {
+    /// The day, endpoint or format
+    pub key: String,
+    pub requests: i64,
+    pub client_errors: i64,
+    pub server_errors: i64,
+}
+
+/// Where to send events about an API key's palettes.
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct Webhook {
+    pub url: String,
+    /// For checking the `X-Webhook-Signature` of payloads
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct PaletteRow {
+    id: Uuid,
+    short_code: String,
+    base_color: String,
+    theme_type: String,
+    palette: String,
+    color_names: Vec<String>,
+    name: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    owner: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    version: i32,
+    regenerated_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<PaletteRow> for StoredPalette {
+    type Error = StorageError;
+
+    fn try_from(row: PaletteRow) -> Result<Self, StorageError> {
+        Ok(Self {
+            id: row.id,
+            short_code: row.short_code,
+            base_color: row.base_color,
+            theme_type: row.theme_type.parse()?,
+            palette: row.palette,
+            color_names: row.color_names,
+            details: PaletteDetails {
+                name: row.name,
+                description: row.description,
+                tags: row.tags,
+            },
+            owner: row.owner,
+            expires_at: row.expires_at,
+            version: row.version,
+            regenerated_at: row.regenerated_at,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// The versions a palette had before its current one, oldest first.
+async fn earlier_versions(pool: &PgPool, id: Uuid) -> Result<Vec<PaletteVersion>, StorageError> {
+    let versions = sqlx::query_as(
+        "SELECT version, base_color, theme_type, palette, color_names, created_at
+         FROM palette_versions WHERE palette_id = $1 ORDER BY version",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(versions)
+}
+
+/// Postgres, for everything that has to outlive a request.
+#[derive(Clone)]
+pub struct Storage {
+    pool: PgPool,
+}
+
+impl Storage {
+    /// Connects to `database_url` (standalone only, Shuttle hands us a pool).
+    #[cfg_attr(feature = "shuttle", allow(dead_code))]
+    pub async fn connect(database_url: &str) -> anyhow::Result<Self> {
+        let pool = PgPoolOptions::new().connect(database_url).await?;
+
+        Self::from_pool(pool).await
+    }
+
+    /// Brings the schema up to date before anything uses the pool.
+    pub async fn from_pool(pool: PgPool) -> anyhow::Result<Self> {
+        sqlx::migrate!().run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+
+    pub async fn insert_palette(
+        &self,
+        base_color: &str,
+        theme_type: ThemeType,
+        palette: &Palette,
+        details: &PaletteDetails,
+        owner: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<StoredPalette, StorageError> {
+        let query = format!(
+            "INSERT INTO palettes (id, short_code, base_color, theme_type, palette, color_names,
+                 name, description, tags, owner, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING {}",
+            PALETTE_COLUMNS
+        );
+
+        // With 55^8 possible codes a collision is very unlikely, but not impossible
+        let mut attempts = 0;
+        loop {
+            let result = sqlx::query_as::<_, PaletteRow>(&query)
+                .bind(Uuid::new_v4())
+                .bind(new_short_code())
+                .bind(base_color)
+                .bind(theme_type.as_str())
+                .bind(palette.to_wire_string())
+                .bind(palette.names())
+                .bind(&details.name)
+                .bind(&details.description)
+                .bind(&details.tags)
+                .bind(owner)
+                .bind(expires_at)
+                .fetch_one(&self.pool)
+                .await;
+
+            attempts += 1;
+            match result {
+                Err(sqlx::Error::Database(err)) if err.is_unique_violation() && attempts < 3 => {
+                    continue
+                }
+                result => return result?.try_into(),
+            }
+        }
+    }
+
+    /// Stores palettes as they are, IDs, short codes and earlier versions included, all at
+    /// once. Says for each one whether it got stored, which it doesn't when its ID or short
+    /// code is taken.
+    pub async fn restore_palettes(
+        &self,
+        palettes: &[(StoredPalette, Vec<PaletteVersion>)],
+    ) -> Result<Vec<bool>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let mut stored = Vec::with_capacity(palettes.len());
+        for (palette, versions) in palettes {
+            let result = sqlx::query(
+                "INSERT INTO palettes (id, short_code, base_color, theme_type, palette, color_names,
+                     name, description, tags, owner, expires_at, version, regenerated_at,
+                     created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind(palette.id)
+            .bind(&palette.short_code)
+            .bind(&palette.base_color)
+            .bind(palette.theme_type.as_str())
+            .bind(&palette.palette)
+            .bind(&palette.color_names)
+            .bind(&palette.details.name)
+            .bind(&palette.details.description)
+            .bind(&palette.details.tags)
+            .bind(&palette.owner)
+            .bind(palette.expires_at)
+            .bind(palette.version)
+            .bind(palette.regenerated_at)
+            .bind(palette.created_at)
+            .execute(&mut *tx)
+            .await?;
+
+            let inserted = result.rows_affected() == 1;
+            if inserted {
+                for version in versions {
+                    sqlx::query(
+                        "INSERT INTO palette_versions
+                             (palette_id, version, base_color, theme_type, palette, color_names,
+                             created_at)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                    )
+                    .bind(palette.id)
+                    .bind(version.version)
+                    .bind(&version.base_color)
+                    .bind(&version.theme_type)
+                    .bind(&version.palette)
+                    .bind(&version.color_names)
+                    .bind(version.created_at)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+            stored.push(inserted);
+        }
+        tx.commit().await?;
+
+        Ok(stored)
+    }
+
+    pub async fn palette(&self, id: Uuid) -> Result<PaletteLookup, StorageError> {
+        self.lookup("id", id).await
+    }
+
+    pub async fn palette_by_short_code(
+        &self,
+        short_code: &str,
+    ) -> Result<PaletteLookup, StorageError> {
+        self.lookup("short_code", short_code).await
+    }
+
+    /// Finds a palette by `column` (`id` or `short_code`), whether it's still around or not.
+    async fn lookup<T>(&self, column: &str, value: T) -> Result<PaletteLookup, StorageError>
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Send + Clone,
+    {
+        let query = format!(
+            "SELECT {} FROM palettes WHERE {} = $1",
+            PALETTE_COLUMNS, column
+        );
+        let row: Option<PaletteRow> = sqlx::query_as(&query)
+            .bind(value.clone())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if let Some(row) = row {
+            let stored: StoredPalette = row.try_into()?;
+            return Ok(match stored.expires_at {
+                Some(expires_at) if expires_at <= Utc::now() => PaletteLookup::Expired(expires_at),
+                _ => PaletteLookup::Found(Box::new(stored)),
+            });
+        }
+
+        let query = format!(
+            "SELECT expired_at FROM expired_palettes WHERE {} = $1",
+            column
+        );
+        let expired_at: Option<DateTime<Utc>> = sqlx::query_scalar(&query)
+            .bind(value)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(expired_at.map_or(PaletteLookup::Missing, PaletteLookup::Expired))
+    }
+
+    /// Whether the database answers, for the readiness probe.
+    pub async fn ping(&self) -> Result<(), StorageError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Deletes expired palettes, leaving just enough behind to tell they expired.
+    pub async fn delete_expired(&self) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            "WITH expired AS (
+                 DELETE FROM palettes WHERE expires_at <= now()
+                 RETURNING id, short_code, expires_at
+             )
+             INSERT INTO expired_palettes (id, short_code, expired_at) SELECT * FROM expired",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Replaces the colors of a palette that hasn't expired, keeping the ones it had as a
+    /// version of their own.
+    pub async fn regenerate(
+        &self,
+        id: Uuid,
+        base_color: &str,
+        theme_type: ThemeType,
+        palette: &Palette,
+    ) -> Result<Option<StoredPalette>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        // Locks the row, so two regenerations can't both save the same version
+        let query = format!(
+            "INSERT INTO palette_versions
+                 (palette_id, version, base_color, theme_type, palette, color_names, created_at)
+             SELECT id, version, base_color, theme_type, palette, color_names,
+                 COALESCE(regenerated_at, created_at)
+             FROM palettes WHERE id = $1 AND {} FOR UPDATE",
+            NOT_EXPIRED
+        );
+        sqlx::query(&query).bind(id).execute(&mut *tx).await?;
+
+        let query = format!(
+            "UPDATE palettes SET
+                 version = version + 1,
+                 base_color = $2,
+                 theme_type = $3,
+                 palette = $4,
+                 color_names = $5,
+                 regenerated_at = now()
+             WHERE id = $1 AND {} RETURNING {}",
+            NOT_EXPIRED, PALETTE_COLUMNS
+        );
+        let row: Option<PaletteRow> = sqlx::query_as(&query)
+            .bind(id)
+            .bind(base_color)
+            .bind(theme_type.as_str())
+            .bind(palette.to_wire_string())
+            .bind(palette.names())
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        row.map(TryInto::try_into).transpose()
+    }
+
+    /// Every version of a palette, the current one included, newest first.
+    pub async fn palette_versions(&self, id: Uuid) -> Result<Vec<PaletteVersion>, StorageError> {
+        let versions = sqlx::query_as(
+            "SELECT version, base_color, theme_type, palette, color_names,
+                 COALESCE(regenerated_at, created_at) AS created_at
+             FROM palettes WHERE id = $1
+             UNION ALL
+             SELECT version, base_color, theme_type, palette, color_names, created_at
+             FROM palette_versions WHERE palette_id = $1
+             ORDER BY version DESC",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(versions)
+    }
+
+    /// One version of a palette, which can also be the current one.
+    pub async fn palette_version(
+        &self,
+        id: Uuid,
+        version: i32,
+    ) -> Result<Option<PaletteVersion>, StorageError> {
+        let version = sqlx::query_as(
+            "SELECT version, base_color, theme_type, palette, color_names,
+                 COALESCE(regenerated_at, created_at) AS created_at
+             FROM palettes WHERE id = $1 AND version = $2
+             UNION ALL
+             SELECT version, base_color, theme_type, palette, color_names, created_at
+             FROM palette_versions WHERE palette_id = $1 AND version = $2",
+        )
+        .bind(id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(version)
+    }
+
+    /// Merges daily seed and usage counts older than `days` into the first day of their month,
+    /// and forgets per-key counts from before yesterday, which quotas don't need anymore.
+    /// Returns how many daily rows got merged or deleted.
+    pub async fn roll_up_counters(&self, days: i32) -> Result<u64, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let seeds: i64 = sqlx::query_scalar(
+            "WITH old AS (
+                 DELETE FROM seed_counts
+                 WHERE day < CURRENT_DATE - $1 AND day <> date_trunc('month', day)::DATE
+                 RETURNING day, base_color, theme_type, count
+             ),
+             merged AS (
+                 INSERT INTO seed_counts (day, base_color, theme_type, count)
+                 SELECT date_trunc('month', day)::DATE, base_color, theme_type, SUM(count)
+                 FROM old GROUP BY 1, 2, 3
+                 ON CONFLICT (day, base_color, theme_type)
+                 DO UPDATE SET count = seed_counts.count + EXCLUDED.count
+             )
+             SELECT COUNT(*) FROM old",
+        )
+        .bind(days)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let usage: i64 = sqlx::query_scalar(
+            "WITH old AS (
+                 DELETE FROM usage_counts
+                 WHERE day < CURRENT_DATE - $1 AND day <> date_trunc('month', day)::DATE
+                 RETURNING day, endpoint, format, requests, client_errors, server_errors
+             ),
+             merged AS (
+                 INSERT INTO usage_counts
+                     (day, endpoint, format, requests, client_errors, server_errors)
+                 SELECT date_trunc('month', day)::DATE, endpoint, format,
+                     SUM(requests), SUM(client_errors), SUM(server_errors)
+                 FROM old GROUP BY 1, 2, 3
+                 ON CONFLICT (day, endpoint, format) DO UPDATE SET
+                     requests = usage_counts.requests + EXCLUDED.requests,
+                     client_errors = usage_counts.client_errors + EXCLUDED.client_errors,
+                     server_errors = usage_counts.server_errors + EXCLUDED.server_errors
+             )
+             SELECT COUNT(*) FROM old",
+        )
+        .bind(days)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let keys =
+            sqlx::query("DELETE FROM key_usage WHERE day < (now() AT TIME ZONE 'UTC')::DATE - 1")
+                .execute(&mut *tx)
+                .await?;
+
+        tx.commit().await?;
+
+        Ok(seeds as u64 + usage as u64 + keys.rows_affected())
+    }
+
+    /// Changes the details that are given, and clears the name/description when they're empty.
+    pub async fn update_details(
+        &self,
+        id: Uuid,
+        name: Option<&str>,
+        description: Option<&str>,
+        tags: Option<&[String]>,
+    ) -> Result<Option<StoredPalette>, StorageError> {
+        let query = format!(
+            "UPDATE palettes SET
+                 name = NULLIF(COALESCE($2, name), ''),
+                 description = NULLIF(COALESCE($3, description), ''),
+                 tags = COALESCE($4, tags)
+             WHERE id = $1 RETURNING {}",
+            PALETTE_COLUMNS
+        );
+        let row: Option<PaletteRow> = sqlx::query_as(&query)
+            .bind(id)
+            .bind(name)
+            .bind(description)
+            .bind(tags)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(TryInto::try_into).transpose()
+    }
+
+    /// The newest stored palettes, only ones with `tag` if it's given.
+    pub async fn search_palettes(
+        &self,
+        tag: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<StoredPalette>, StorageError> {
+        let query = format!(
+            "SELECT {} FROM palettes WHERE ($1::TEXT IS NULL OR tags @> ARRAY[$1]) AND {}
+             ORDER BY created_at DESC LIMIT $2",
+            PALETTE_COLUMNS, NOT_EXPIRED
+        );
+        let rows: Vec<PaletteRow> = sqlx::query_as(&query)
+            .bind(tag)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(TryInto::try_into).collect()
+    }
+
+    pub async fn star(&self, api_key: &str, palette_id: Uuid) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO favorites (api_key, palette_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(api_key)
+        .bind(palette_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unstar(&self, api_key: &str, palette_id: Uuid) -> Result<(), StorageError> {
+        sqlx::query("DELETE FROM favorites WHERE api_key = $1 AND palette_id = $2")
+            .bind(api_key)
+            .bind(palette_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Palettes starred by `api_key`, last starred first.
+    pub async fn favorites(&self, api_key: &str) -> Result<Vec<StoredPalette>, StorageError> {
+        let query = format!(
+            "SELECT {} FROM palettes JOIN (
+                 SELECT palette_id, created_at AS starred_at FROM favorites WHERE api_key = $1
+             ) AS starred ON starred.palette_id = palettes.id
+             WHERE {}
+             ORDER BY starred.starred_at DESC",
+            PALETTE_COLUMNS, NOT_EXPIRED
+        );
+        let rows: Vec<PaletteRow> = sqlx::query_as(&query)
+            .bind(api_key)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(TryInto::try_into).collect()
+    }
+
+    /// Palettes stored with `api_key` that haven't expired, oldest first, each with its earlier
+    /// versions (oldest first too). They're sent one at a time as they come out of the
+    /// database, so a big library never has to fit in memory. Nothing comes after an error.
+    pub fn owned_palettes(
+        &self,
+        api_key: &str,
+    ) -> mpsc::Receiver<Result<(StoredPalette, Vec<PaletteVersion>), StorageError>> {
+        let (sender, receiver) = mpsc::channel(OWNED_PALETTES_BUFFER);
+        let pool = self.pool.clone();
+        let api_key = api_key.to_owned();
+
+        tokio::spawn(async move {
+            let query = format!(
+                "SELECT {} FROM palettes WHERE owner = $1 AND {} ORDER BY created_at",
+                PALETTE_COLUMNS, NOT_EXPIRED
+            );
+            let mut rows = sqlx::query_as::<_, PaletteRow>(&query)
+                .bind(&api_key)
+                .fetch(&pool);
+            while let Some(row) = rows.next().await {
+                let palette = match row.map_err(Into::into).and_then(StoredPalette::try_from) {
+                    Ok(palette) => earlier_versions(&pool, palette.id)
+                        .await
+                        .map(|versions| (palette, versions)),
+                    Err(err) => Err(err),
+                };
+                let failed = palette.is_err();
+                if sender.send(palette).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        receiver
+    }
+
+    /// Remembers a generated palette for `api_key`, forgetting all but the `keep` latest ones.
+    pub async fn record_history(
+        &self,
+        api_key: &str,
+        base_color: &str,
+        theme_type: ThemeType,
+        palette: &str,
+        keep: i64,
+    ) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO palette_history (api_key, base_color, theme_type, palette)
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(api_key)
+        .bind(base_color)
+        .bind(theme_type.as_str())
+        .bind(palette)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "DELETE FROM palette_history WHERE api_key = $1 AND id NOT IN (
+                 SELECT id FROM palette_history WHERE api_key = $1 ORDER BY id DESC LIMIT $2
+             )",
+        )
+        .bind(api_key)
+        .bind(keep)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Newest first.
+    pub async fn history(&self, api_key: &str) -> Result<Vec<HistoryEntry>, StorageError> {
+        let rows: Vec<HistoryRow> = sqlx::query_as(
+            "SELECT base_color, theme_type, palette, created_at FROM palette_history
+             WHERE api_key = $1 ORDER BY id DESC",
+        )
+        .bind(api_key)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(TryInto::try_into).collect()
+    }
+
+    /// Counts one more request for this seed color today.
+    pub async fn count_seed(
+        &self,
+        base_color: &str,
+        theme_type: ThemeType,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO seed_counts (base_color, theme_type) VALUES ($1, $2)
+             ON CONFLICT (day, base_color, theme_type) DO UPDATE SET count = seed_counts.count + 1",
+        )
+        .bind(base_color)
+        .bind(theme_type.as_str())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The most requested seed colors over the last `days` days, most popular first.
+    pub async fn popular_seeds(
+        &self,
+        days: i32,
+        theme_type: Option<ThemeType>,
+        limit: i64,
+    ) -> Result<Vec<SeedCount>, StorageError> {
+        let counts = sqlx::query_as(
+            "SELECT base_color, theme_type, SUM(count)::BIGINT AS count FROM seed_counts
+             WHERE day > CURRENT_DATE - $1 AND ($2::TEXT IS NULL OR theme_type = $2)
+             GROUP BY base_color, theme_type
+             ORDER BY count DESC, base_color
+             LIMIT $3",
+        )
+        .bind(days)
+        .bind(theme_type.map(ThemeType::as_str))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Sets the webhook for `api_key`. `secret` is only used if it didn't have one before.
+    pub async fn set_webhook(
+        &self,
+        api_key: &str,
+        url: &str,
+        secret: &str,
+    ) -> Result<Webhook, StorageError> {
+        let webhook = sqlx::query_as(
+            "INSERT INTO webhooks (api_key, url, secret) VALUES ($1, $2, $3)
+             ON CONFLICT (api_key) DO UPDATE SET url = $2
+             RETURNING url, secret, created_at",
+        )
+        .bind(api_key)
+        .bind(url)
+        .bind(secret)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn webhook(&self, api_key: &str) -> Result<Option<Webhook>, StorageError> {
+        let webhook =
+            sqlx::query_as("SELECT url, secret, created_at FROM webhooks WHERE api_key = $1")
+                .bind(api_key)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn delete_webhook(&self, api_key: &str) -> Result<(), StorageError> {
+        sqlx::query("DELETE FROM webhooks WHERE api_key = $1")
+            .bind(api_key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Counts a request made with `api_key` today (UTC), returning how many there were so far.
+    pub async fn count_key_request(&self, api_key: &str) -> Result<i64, StorageError> {
+        let requests = sqlx::query_scalar(
+            "INSERT INTO key_usage (api_key) VALUES ($1)
+             ON CONFLICT (day, api_key) DO UPDATE SET requests = key_usage.requests + 1
+             RETURNING requests",
+        )
+        .bind(api_key)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(requests)
+    }
+
+    pub async fn count_usage(
+        &self,
+        endpoint: &str,
+        format: &str,
+        client_error: bool,
+        server_error: bool,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO usage_counts (endpoint, format, requests, client_errors, server_errors)
+             VALUES ($1, $2, 1, $3, $4)
+             ON CONFLICT (day, endpoint, format) DO UPDATE SET
+                 requests = usage_counts.requests + 1,
+                 client_errors = usage_counts.client_errors + $3,
+                 server_errors = usage_counts.server_errors + $4",
+        )
+        .bind(endpoint)
+        .bind(format)
+        .bind(i64::from(client_error))
+        .bind(i64::from(server_error))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Usage over the last `days` days, summed up by day (newest first), endpoint or format
+    /// (busiest first).
+    pub async fn usage(
+        &self,
+        days: i32,
+        group: UsageGroup,
+    ) -> Result<Vec<UsageCount>, StorageError> {
+        let (key, order) = match group {
+            UsageGroup::Day => ("day::TEXT", "key DESC"),
+            UsageGroup::Endpoint => ("endpoint", "requests DESC, key"),
+            UsageGroup::Format => ("format", "requests DESC, key"),
+        };
+        let query = format!(
+            "SELECT {} AS key, SUM(requests)::BIGINT AS requests,
+                 SUM(client_errors)::BIGINT AS client_errors,
+                 SUM(server_errors)::BIGINT AS server_errors
+             FROM usage_counts WHERE day > CURRENT_DATE - $1
+             GROUP BY 1 ORDER BY {}",
+            key, order
+        );
+
+        let counts = sqlx::query_as(&query)
+            .bind(days)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(counts)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum UsageGroup {
+    Day,
+    Endpoint,
+    Format,
+}
+
+fn new_short_code() -> String {
+    // UUIDv4s are random enough, and saves pulling in `rand` for this
+    let mut bits = Uuid::new_v4().as_u128();
+    let base = SHORT_CODE_ALPHABET.len() as u128;
+
+    (0..SHORT_CODE_LENGTH)
+        .map(|_| {
+            let c = SHORT_CODE_ALPHABET[(bits % base) as usize] as char;
+            bits /= base;
+            c
+        })
+        .collect()
+}
+
+/// Handlers that need the database can just take a `Storage`, and get a 503 without one.
+#[async_trait]
+impl FromRequestParts<AppState> for Storage {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(_: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        state.storage.clone().ok_or((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Palette storage isn't set up on this instance",
+        ))
+    }
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
+    routing::get,
+    Router,
+};
+use material_colors::{color::Argb, hct::Hct};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    errors::{InputError, RenderError},
+    http_cache, input,
+    palette::{self, Hex},
+    AppError, AppState,
+};
+
+/// How far the suggestions turn the hue, both ways.
+const HUE_SHIFTS: [f64; 5] = [0.0, -15.0, 15.0, -30.0, 30.0];
+/// Chroma the suggestions try on top of the seed's own. Core palettes give the primary colors
+/// at least 48, so that's where a seed stops being "too gray".
+const CHROMAS: [f64; 2] = [48.0, 72.0];
+const VIBRANT_CHROMA: f64 = 48.0;
+const SUGGESTIONS: usize = 5;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestQuery {
+    /// The seed to start from, as hex with or without the `#` (3 or 6 digits)
+    #[param(example = "8A9A8C")]
+    color: String,
+}
+
+/// Seeds near another one, the best first.
+#[derive(Serialize, ToSchema)]
+pub struct Suggestions {
+    #[schema(example = "8a9a8c")]
+    color: String,
+    /// How good the seed asked about is, on the same scale as the suggestions'
+    #[schema(example = 0.31)]
+    score: f64,
+    suggestions: Vec<Suggestion>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Suggestion {
+    #[schema(example = "6ba271")]
+    color: String,
+    /// Degrees the hue was turned by
+    #[schema(example = -15.0)]
+    hue_shift: f64,
+    #[schema(example = 48.0)]
+    chroma: f64,
+    /// From 0 to 1: half how colorful the seed is (a gray one's hue is close to random, so a
+    /// small change can give a different theme), half how colorful the scheme's primary
+    /// colors come out
+    #[schema(example = 0.93)]
+    score: f64,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/suggestSeeds", get(suggest_seeds))
+}
+
+/// Suggests seeds close to a color that make livelier, more predictable themes: the hue turned
+/// a bit and the chroma turned up, ranked by how colorful the seed and its scheme are. For
+/// nudging users away from grayish seeds, whose themes change a lot with small tweaks.
+#[utoipa::path(
+    get,
+    path = "/v1/suggestSeeds",
+    params(SuggestQuery),
+    responses(
+        (status = 200, description = "The suggestions", body = Suggestions),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 400, description = "`color` isn't a hex color", body = String),
+        (status = 500, description = "Generating the schemes failed", body = String)
+    )
+)]
+pub async fn suggest_seeds(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SuggestQuery>,
+) -> Result<Response, AppError> {
+    let color = input::parse_color(&query.color).map_err(|_| InputError::invalid_color("color"))?;
+
+    // A theme for every candidate adds up to a few milliseconds
+    let suggestions = tokio::task::spawn_blocking(move || suggest(color))
+        .await
+        .map_err(RenderError::from)?;
+
+    Ok(http_cache::cacheable(
+        &headers,
+        serde_json::to_string(&suggestions)?,
+        "application/json",
+        http_cache::Caching::public(state.config.cache_max_age_secs),
+    ))
+}
+
+fn suggest(color: Argb) -> Suggestions {
+    let seed = Hct::new(color);
+    let chromas = std::iter::once(seed.get_chroma()).chain(CHROMAS);
+
+    let mut candidates: Vec<Suggestion> = Vec::new();
+    for chroma in chromas {
+        for hue_shift in HUE_SHIFTS {
+            let hue = (seed.get_hue() + hue_shift).rem_euclid(360.0);
+            let candidate: Argb = Hct::from(hue, chroma, seed.get_tone()).into();
+            // Out of gamut chroma gets clamped, which can land on a color that's already there
+            if candidate == color
+                || candidates
+                    .iter()
+                    .any(|c| c.color == Hex(candidate).to_string())
+            {
+                continue;
+            }
+
+            candidates.push(Suggestion {
+                color: Hex(candidate).to_string(),
+                hue_shift,
+                chroma: rounded(Hct::new(candidate).get_chroma()),
+                score: rounded(score(candidate)),
+            });
+        }
+    }
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates.truncate(SUGGESTIONS);
+
+    Suggestions {
+        color: Hex(color).to_string(),
+        score: rounded(score(color)),
+        suggestions: candidates,
+    }
+}
+
+fn score(seed: Argb) -> f64 {
+    let colorful = |chroma: f64| (chroma / VIBRANT_CHROMA).min(1.0);
+
+    let schemes = palette::build_theme(seed, &[]).schemes;
+    let primaries = [
+        schemes.dark.primary,
+        schemes.dark.primary_container,
+        schemes.light.primary,
+        schemes.light.primary_container,
+    ];
+    let scheme_chroma = primaries
+        .iter()
+        .map(|&color| Hct::new(color).get_chroma())
+        .sum::<f64>()
+        / primaries.len() as f64;
+
+    (colorful(Hct::new(seed).get_chroma()) + colorful(scheme_chroma)) / 2.0
+}
+
+fn rounded(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,161 @@
+use axum::http::{HeaderMap, HeaderName, Uri};
+use opentelemetry::{global, propagation::Extractor, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace, Resource};
+use sentry::integrations::tracing::EventFilter;
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer, Registry,
+};
+
+use crate::config::{Config, LogFormat};
+
+/// Lets the log filter be swapped out while running.
+pub type LogLevelHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Sets up logging, plus OTLP trace export when `otel_exporter_otlp_endpoint` is set.
+///
+/// This replaces Shuttle's default subscriber, and keeps the same defaults for the logs.
+/// Setting `log_format` to `json` switches the logs to JSON lines for log shippers.
+pub fn init(config: &Config) -> LogLevelHandle {
+    let otlp_endpoint = config.otel_exporter_otlp_endpoint.clone();
+
+    let otel_layer = otlp_endpoint.as_deref().map(|endpoint| {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        // This also registers the provider globally
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(trace::config().with_resource(Resource::new([KeyValue::new(
+                "service.name",
+                env!("CARGO_PKG_NAME"),
+            )])))
+            .install_batch(runtime::Tokio)
+            .expect("failed to set up the OTLP exporter");
+
+        // Shuttle's logs only go down to info, but traces are more useful with a bit more detail
+        let filter = EnvFilter::try_new(&config.otel_traces_filter)
+            .expect("otel_traces_filter isn't a valid filter");
+
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(filter)
+    });
+
+    let (log_filter, log_level_handle) = reload::Layer::new(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,shuttle=trace")),
+    );
+
+    let fmt_layer = if config.log_format == LogFormat::Json {
+        // Log shippers want timestamps and the request span's fields on every line
+        tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed()
+    } else {
+        tracing_subscriber::fmt::layer().without_time().boxed()
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(log_filter))
+        .with(otel_layer)
+        // Errors get reported to Sentry explicitly (with more context), so logs only become breadcrumbs
+        .with(sentry::integrations::tracing::layer().event_filter(
+            |metadata| match *metadata.level() {
+                Level::ERROR | Level::WARN | Level::INFO => EventFilter::Breadcrumb,
+                _ => EventFilter::Ignore,
+            },
+        ))
+        .init();
+
+    if let Some(endpoint) = otlp_endpoint {
+        tracing::info!("Exporting traces to {}", endpoint);
+    }
+
+    log_level_handle
+}
+
+/// Exports any spans that are still buffered.
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Query parameters and headers that can have credentials in them, which logs and traces
+/// shouldn't.
+const SECRET_PARAMS: [&str; 2] = ["api_key", "token"];
+const SECRET_HEADERS: [&str; 4] = ["authorization", "cookie", "x-api-key", "x-channel-token"];
+
+/// The request's path and query, with the values of credentials like `api_key` blanked out.
+pub fn redacted_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.path().to_owned();
+    };
+
+    let query: Vec<_> = query
+        .split('&')
+        .map(|pair| {
+            let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+            // Compared decoded, like extractors see them
+            let secret = form_urlencoded::parse(name.as_bytes())
+                .next()
+                .is_some_and(|(name, _)| SECRET_PARAMS.contains(&name.as_ref()));
+            if secret {
+                format!("{}=[redacted]", name)
+            } else {
+                pair.to_owned()
+            }
+        })
+        .collect();
+    format!("{}?{}", uri.path(), query.join("&"))
+}
+
+/// Whether a header can have credentials in it.
+pub fn is_secret_header(name: &HeaderName) -> bool {
+    SECRET_HEADERS.contains(&name.as_str())
+}
+
+/// Continues the trace from a caller's `traceparent` header, if they sent one.
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let context =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    span.set_parent(context);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credentials_are_left_out_of_uris() {
+        let redacted = |uri: &str| redacted_uri(&uri.parse().unwrap());
+
+        assert_eq!(redacted("/v1/getPalette"), "/v1/getPalette");
+        assert_eq!(
+            redacted("/v1/getPalette?base_color=59EB5C&api_key=hunter2&fields=primary,surface"),
+            "/v1/getPalette?base_color=59EB5C&api_key=[redacted]&fields=primary,surface"
+        );
+        assert_eq!(
+            redacted("/picker/mine?api%5Fkey=hunter2&token"),
+            "/picker/mine?api%5Fkey=[redacted]&token=[redacted]"
+        );
+    }
+}
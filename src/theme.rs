@@ -0,0 +1,199 @@
+use std::{error, fmt, str::FromStr};
+
+use material_colors::color::Argb;
+
+use crate::{
+    formats::{Format, FormatOptions},
+    input,
+    palette::{self, CustomColorSpec, Palette},
+};
+
+pub use crate::palette::ThemeType;
+
+/// An sRGB color, shown as 6 lowercase hex digits like the `raw` format writes them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue }
+    }
+}
+
+/// Parses hex like `base_color`: with or without the `#`, as 3 or 6 digits.
+impl FromStr for Color {
+    type Err = InvalidColor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let argb = input::parse_color(s).map_err(|_| InvalidColor(s.to_string()))?;
+        Ok(Self::new(argb.red, argb.green, argb.blue))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+/// Kept out of the public API, so updating material-colors doesn't break anyone.
+fn argb(color: Color) -> Argb {
+    Argb::new(255, color.red, color.green, color.blue)
+}
+
+/// A string that isn't a hex color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidColor(String);
+
+impl fmt::Display for InvalidColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} isn't a hex color", self.0)
+    }
+}
+
+impl error::Error for InvalidColor {}
+
+/// One of the fixed colors appended after the scheme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomColor {
+    pub name: String,
+    pub value: Color,
+    /// Whether to shift the color towards the source color
+    pub blend: bool,
+}
+
+/// What [`generate_theme`] makes a theme from.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ThemeOptions {
+    pub base_color: Color,
+    pub theme_type: ThemeType,
+    /// The service's built-in ones unless changed
+    pub custom_colors: Vec<CustomColor>,
+}
+
+impl ThemeOptions {
+    pub fn new(base_color: Color, theme_type: ThemeType) -> Self {
+        let custom_colors = palette::default_custom_colors()
+            .into_iter()
+            .map(|color| CustomColor {
+                name: color.name,
+                value: Color::new(color.value.red, color.value.green, color.value.blue),
+                blend: color.blend,
+            })
+            .collect();
+
+        Self {
+            base_color,
+            theme_type,
+            custom_colors,
+        }
+    }
+
+    /// Replaces the custom colors, which changes how many colors themes have.
+    pub fn custom_colors(mut self, custom_colors: impl IntoIterator<Item = CustomColor>) -> Self {
+        self.custom_colors = custom_colors.into_iter().collect();
+        self
+    }
+}
+
+/// How [`Theme::to_format`] writes a theme, the same as the `format` parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OutputFormat {
+    /// 6 digit hex values with nothing in between, what worlds parse
+    Raw,
+    /// `{"schema_version": 1, "colors": {"primary": "ffb4a8", ...}}`
+    Json,
+    /// One `Space/ColorName|FFB4A8` line per color
+    Dynvar { space: String },
+    /// One hex value per line, with `names` like `primary=ffb4a8`
+    Lines { names: bool },
+    /// `primary:FFB4A8 onPrimary:...` on one line
+    Annotated,
+}
+
+/// A generated theme's colors, in the order the service returns them: the scheme's, then the
+/// color, container, on color and on container of each custom color.
+#[derive(Clone)]
+pub struct Theme {
+    palette: Palette,
+}
+
+impl Theme {
+    pub fn colors(&self) -> impl Iterator<Item = (&str, Color)> {
+        self.palette
+            .colors
+            .iter()
+            .map(|(name, argb)| (name.as_str(), Color::new(argb.red, argb.green, argb.blue)))
+    }
+
+    /// A color by its name, like `on_primary_container` or `pink_container`.
+    pub fn color(&self, name: &str) -> Option<Color> {
+        self.colors()
+            .find(|&(color_name, _)| color_name == name)
+            .map(|(_, color)| color)
+    }
+
+    /// The theme exactly like `GET /v1/getPalette` returns it in that format.
+    pub fn to_format(&self, format: &OutputFormat) -> String {
+        let (format, options) = match format {
+            OutputFormat::Raw => (Format::Raw, FormatOptions::default()),
+            OutputFormat::Json => (Format::Json, FormatOptions::default()),
+            OutputFormat::Annotated => (Format::Annotated, FormatOptions::default()),
+            OutputFormat::Dynvar { space } => (
+                Format::Dynvar,
+                FormatOptions {
+                    dynvar_space: Some(space.clone()),
+                    ..Default::default()
+                },
+            ),
+            OutputFormat::Lines { names } => (
+                Format::Lines,
+                FormatOptions {
+                    names: *names,
+                    ..Default::default()
+                },
+            ),
+        };
+
+        format.render(&self.palette, &options)
+    }
+}
+
+impl fmt::Debug for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.colors()).finish()
+    }
+}
+
+/// Generates a theme the same way the service does.
+///
+/// ```
+/// use resomaterialyou::{generate_theme, OutputFormat, ThemeOptions, ThemeType};
+///
+/// let options = ThemeOptions::new("59EB5C".parse()?, ThemeType::Dark);
+/// let theme = generate_theme(&options);
+/// assert_eq!(theme.color("primary").unwrap().to_string(), "a2d398");
+/// assert!(theme.to_format(&OutputFormat::Json).starts_with(r#"{"schema_version":1,"colors":{"primary":"#));
+/// # Ok::<(), resomaterialyou::InvalidColor>(())
+/// ```
+pub fn generate_theme(options: &ThemeOptions) -> Theme {
+    let custom_colors: Vec<CustomColorSpec> = options
+        .custom_colors
+        .iter()
+        .map(|color| CustomColorSpec {
+            name: color.name.clone(),
+            value: argb(color.value),
+            blend: color.blend,
+        })
+        .collect();
+    let palette = Palette::generate(argb(options.base_color), options.theme_type, &custom_colors)
+        .expect("generating a palette can't fail");
+
+    Theme { palette }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use material_colors::color::Argb;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tracing::{debug, info, warn};
+
+use crate::{cache, config::TwitchConfig, input, palette::ThemeType, AppState};
+
+/// Between connection attempts, so Twitch doesn't get hammered while it's down.
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+const COMMAND: &str = "!theme";
+
+/// Who changed the theme when, so chat can't flip it every second.
+#[derive(Default)]
+struct Cooldowns {
+    last_change: Option<Instant>,
+    by_user: HashMap<String, Instant>,
+}
+
+impl Cooldowns {
+    fn allows(&self, config: &TwitchConfig, user: &str, now: Instant) -> bool {
+        let waited = |since: Option<&Instant>, secs| {
+            since.is_none_or(|since| now - *since >= Duration::from_secs(secs))
+        };
+
+        waited(self.last_change.as_ref(), config.cooldown_secs)
+            && waited(self.by_user.get(user), config.user_cooldown_secs)
+    }
+
+    fn record(&mut self, config: &TwitchConfig, user: &str, now: Instant) {
+        self.last_change = Some(now);
+        self.by_user.insert(user.to_owned(), now);
+
+        let cooldown = Duration::from_secs(config.user_cooldown_secs);
+        self.by_user.retain(|_, since| now - *since < cooldown);
+    }
+}
+
+/// Joins the streamer's chat and turns `!theme <color> [light|dark]` messages into theme
+/// changes on the configured channel, for as long as the server runs.
+pub fn start(state: &AppState, config: &TwitchConfig) {
+    let state = state.clone();
+    let config = config.clone();
+    tokio::spawn(async move {
+        let mut cooldowns = Cooldowns::default();
+        loop {
+            if let Err(err) = listen(&state, &config, &mut cooldowns).await {
+                warn!("Lost Twitch chat of {}: {}", config.twitch_channel, err);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn listen(
+    state: &AppState,
+    config: &TwitchConfig,
+    cooldowns: &mut Cooldowns,
+) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(&config.server).await?;
+    let (reader, mut writer) = stream.into_split();
+
+    // Without a token, Twitch lets anyone read chat as justinfan<number>
+    let login = match (&config.nick, &config.oauth_token) {
+        (Some(nick), Some(token)) => format!("PASS oauth:{}\r\nNICK {}\r\n", token, nick),
+        _ => "NICK justinfan31337\r\n".to_string(),
+    };
+    let join = format!("JOIN #{}\r\n", config.twitch_channel.to_lowercase());
+    writer.write_all(login.as_bytes()).await?;
+    writer.write_all(join.as_bytes()).await?;
+    info!("Listening to Twitch chat of {}", config.twitch_channel);
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(server) = line.strip_prefix("PING ") {
+            writer
+                .write_all(format!("PONG {}\r\n", server).as_bytes())
+                .await?;
+            continue;
+        }
+
+        let Some((user, message)) = chat_message(&line) else {
+            continue;
+        };
+        let Some(arguments) = message.strip_prefix(COMMAND) else {
+            continue;
+        };
+        let Some((source, theme_type)) = parse_arguments(arguments, config.theme_type) else {
+            debug!("Ignoring {:?} from {}", message, user);
+            continue;
+        };
+
+        let now = Instant::now();
+        if !cooldowns.allows(config, user, now) {
+            metrics::counter!("twitch_theme_commands_total", "result" => "cooldown").increment(1);
+            continue;
+        }
+        cooldowns.record(config, user, now);
+
+        let palette = match cache::palette(state, source, theme_type).await {
+            Ok(palette) => palette,
+            Err(err) => {
+                warn!("Couldn't generate {}'s theme: {}", user, err);
+                continue;
+            }
+        };
+        let published =
+            state
+                .channels
+                .publish(&config.channel, source.to_hex(), theme_type, palette);
+        if published.is_err() {
+            warn!("Couldn't change {}: too many channels", config.channel);
+            continue;
+        }
+        metrics::counter!("twitch_theme_commands_total", "result" => "ok").increment(1);
+        info!(
+            "{} changed {} to {} ({})",
+            user,
+            config.channel,
+            source.to_hex(),
+            theme_type.as_str()
+        );
+    }
+
+    anyhow::bail!("Twitch closed the connection")
+}
+
+/// The sender and text of a `:user!user@user.tmi.twitch.tv PRIVMSG #channel :text` line.
+fn chat_message(line: &str) -> Option<(&str, &str)> {
+    let line = line.strip_prefix(':')?;
+    let (prefix, rest) = line.split_once(' ')?;
+    let (user, _) = prefix.split_once('!')?;
+    let rest = rest.strip_prefix("PRIVMSG ")?;
+    let (_, text) = rest.split_once(" :")?;
+
+    Some((user, text.trim()))
+}
+
+/// ` #ff0000 light` and the like, with the theme type being optional.
+fn parse_arguments(arguments: &str, default: ThemeType) -> Option<(Argb, ThemeType)> {
+    // `!themes` isn't `!theme`
+    if !arguments.is_empty() && !arguments.starts_with(' ') {
+        return None;
+    }
+
+    let mut words = arguments.split_whitespace();
+    let source = input::parse_color(words.next()?).ok()?;
+    let theme_type = match words.next().map(str::to_lowercase).as_deref() {
+        None => default,
+        Some("dark") => ThemeType::Dark,
+        Some("light") => ThemeType::Light,
+        Some(_) => return None,
+    };
+
+    Some((source, theme_type))
+}
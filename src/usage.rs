@@ -0,0 +1,56 @@
+use axum::{
+    extract::{MatchedPath, Query, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{formats::Format, AppState};
+
+#[derive(Deserialize)]
+struct FormatParam {
+    format: Option<Format>,
+}
+
+/// Counts requests per day, endpoint and format in the database, for `/admin/usage`.
+///
+/// Unlike `/metrics`, these survive restarts and don't need anything else running to be useful.
+pub async fn track_usage(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(storage) = state.storage.clone() else {
+        return next.run(req).await;
+    };
+
+    // Same as for metrics, raw unmatched paths could be anything
+    let endpoint = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched_path| matched_path.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_owned());
+    let format = match Query::<FormatParam>::try_from_uri(req.uri()) {
+        Ok(Query(FormatParam {
+            format: Some(format),
+        })) => format.name(),
+        Ok(Query(FormatParam { format: None })) => "default",
+        Err(_) => "invalid",
+    };
+
+    let response = next.run(req).await;
+    let status = response.status();
+
+    state.shutdown.spawn_write(async move {
+        let result = storage
+            .count_usage(
+                &endpoint,
+                format,
+                status.is_client_error(),
+                status.is_server_error(),
+            )
+            .await;
+        if let Err(err) = result {
+            warn!("Couldn't count usage: {}", err);
+        }
+    });
+
+    response
+}
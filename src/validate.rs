@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use material_colors::color::Argb;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::MaybeApiKey,
+    formats::{Format, FormatOptions},
+    palette::{self, Palette},
+    seed_source,
+    selection::Selection,
+    AppError, AppState, PaletteQuery, ResolvedParams,
+};
+
+/// What `getPalette` would answer with for the same parameters.
+#[derive(Serialize, ToSchema)]
+pub struct Validation {
+    params: ResolvedParams,
+    format: Format,
+    /// The colors the response would have, in order
+    #[schema(example = json!(["primary", "on_primary", "surface", "on_surface"]))]
+    colors: Vec<String>,
+    /// Characters in the `raw` string
+    #[schema(example = 24)]
+    raw_length: usize,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/validate", get(validate))
+}
+
+/// Checks the parameters of `getPalette` without generating the palette, for tools that want
+/// to tell users about a mistake while they type.
+///
+/// Answers with the same errors `getPalette` would, or with the resolved parameters and the
+/// colors the response would have. A `seed=random` gets a different seed than the real request
+/// would.
+#[utoipa::path(
+    get,
+    path = "/v1/validate",
+    params(PaletteQuery, FormatOptions, Selection),
+    responses(
+        (status = 200, description = "The parameters are fine", body = Validation),
+        (status = 400, description = "`base_color` is missing or isn't a hex color, `fields` has a color that doesn't exist, or the API key has no history for `seed=profile`", body = String),
+        (status = 401, description = "`seed=profile` without an API key", body = String),
+        (status = 503, description = "The database is busy, try again after `Retry-After` seconds", body = String)
+    )
+)]
+pub async fn validate(
+    State(state): State<AppState>,
+    MaybeApiKey(key): MaybeApiKey,
+    Query(query): Query<PaletteQuery>,
+    Query(options): Query<FormatOptions>,
+    Query(selection): Query<Selection>,
+) -> Result<Response, AppError> {
+    let seed = match seed_source::resolve(
+        &state,
+        key.as_ref(),
+        query.seed,
+        query.base_color.as_deref(),
+    )
+    .await
+    {
+        Ok(seed) => seed,
+        Err(response) => return Ok(response),
+    };
+
+    // The names and their order don't depend on the colors, so they can all be black
+    let names = Palette {
+        colors: palette::color_names(&state.custom_colors)
+            .into_iter()
+            .map(|name| (name, Argb::new(255, 0, 0, 0)))
+            .collect(),
+    };
+    let selected = selection.apply(&names, &state.custom_colors)?;
+    let colors = selected.names();
+
+    Ok(Json(Validation {
+        params: ResolvedParams::new(&state, &query, seed.color, &selection, &selected, &options),
+        format: query.format,
+        raw_length: colors.len() * 6,
+        colors,
+    })
+    .into_response())
+}
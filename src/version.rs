@@ -0,0 +1,47 @@
+use axum::{routing::get, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::palette::SCHEMA_VERSION;
+
+/// What this instance is running, for telling apart output from different builds.
+#[derive(Serialize, ToSchema)]
+pub struct Version {
+    #[schema(example = "0.1.0")]
+    version: &'static str,
+    /// `unknown` if it was built without git
+    git_commit: &'static str,
+    built_at: Option<DateTime<Utc>>,
+    /// The version of the library generating the palettes
+    #[schema(example = "0.3.2")]
+    material_colors: &'static str,
+    /// The layout of the palettes, like `/v1/schema`'s `version`
+    schema_version: u32,
+}
+
+pub fn router() -> Router {
+    Router::new().route("/version", get(version))
+}
+
+/// The crate version, commit and build time of this instance, and the material-colors version
+/// it generates palettes with.
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses((status = 200, description = "The build info", body = Version))
+)]
+pub async fn version() -> Json<Version> {
+    let built_at = env!("BUILD_TIMESTAMP")
+        .parse()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+
+    Json(Version {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("BUILD_GIT_COMMIT"),
+        built_at,
+        material_colors: env!("BUILD_MATERIAL_COLORS_VERSION"),
+        schema_version: SCHEMA_VERSION,
+    })
+}
@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::any,
+    Router,
+};
+use chrono::{DateTime, Utc};
+
+use crate::config::DeprecationConfig;
+
+static API_VERSION: HeaderName = HeaderName::from_static("api-version");
+static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+static SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// Mounts the v1 API under `/v1`, and keeps it available at the old unversioned paths too.
+///
+/// Our deprecation policy, as far as clients can see it:
+/// - every versioned response says which version it came from in `API-Version`
+/// - the unversioned aliases point at their versioned replacement with a `successor-version` link
+/// - once it's configured, they also say since when they're deprecated (`Deprecation`, RFC 9745)
+///   and when they go away (`Sunset`, RFC 8594)
+/// - `/v2` is reserved for the next API and answers with 501 until it exists
+pub fn router(v1: Router, deprecations: &DeprecationConfig) -> Router {
+    let unversioned = Deprecation {
+        since: deprecations
+            .unversioned_since
+            .map(|since| format!("@{}", since.timestamp())),
+        sunset: deprecations.unversioned_sunset.map(http_date),
+    };
+
+    Router::new()
+        .nest("/v1", v1.clone().layer(middleware::from_fn(tag_v1)))
+        .merge(v1.layer(middleware::from_fn_with_state(unversioned, tag_unversioned)))
+        .route("/v2", any(v2_reserved))
+        .route("/v2/*rest", any(v2_reserved))
+}
+
+async fn tag_v1(req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    response
+        .headers_mut()
+        .insert(API_VERSION.clone(), HeaderValue::from_static("1"));
+
+    response
+}
+
+/// The deprecation headers of a part of the API, already formatted.
+#[derive(Clone)]
+struct Deprecation {
+    since: Option<String>,
+    sunset: Option<String>,
+}
+
+async fn tag_unversioned(
+    State(deprecation): State<Deprecation>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let successor = format!("</v1{}>; rel=\"successor-version\"", req.uri().path());
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert(API_VERSION.clone(), HeaderValue::from_static("1"));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert(header::LINK, link);
+    }
+    for (name, value) in [
+        (&DEPRECATION, &deprecation.since),
+        (&SUNSET, &deprecation.sunset),
+    ] {
+        if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name.clone(), value);
+        }
+    }
+
+    response
+}
+
+async fn v2_reserved() -> impl IntoResponse {
+    (
+        StatusCode::NOT_IMPLEMENTED,
+        "v2 of the API doesn't exist yet, use /v1",
+    )
+}
+
+/// The date format HTTP headers use, like `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
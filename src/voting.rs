@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use material_colors::color::Argb;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    cache,
+    channels::{self, NewSeed, WriteToken, MAX_CHANNELS},
+    input,
+    palette::ThemeType,
+    rate_limit::{self, ClientIp, RateLimiter},
+    AppState,
+};
+
+const DEFAULT_VOTING_SECS: u64 = 120;
+const MAX_VOTING_SECS: u64 = 60 * 60;
+const MAX_PROPOSALS: usize = 32;
+const MAX_VOTER_LENGTH: usize = 64;
+/// Keeps a flood of made up voters from using up memory.
+const MAX_VOTERS: usize = 10_000;
+/// How many proposals one client can make a minute. Every round waits for its deadline in a
+/// task of its own, so they shouldn't pile up.
+const PROPOSALS_PER_MINUTE: u32 = 20;
+
+struct Proposal {
+    source: Argb,
+    theme_type: ThemeType,
+}
+
+struct Round {
+    deadline: DateTime<Utc>,
+    proposals: Vec<Proposal>,
+    /// Voter to the index of the proposal they voted for, so changing your mind moves your vote
+    votes: HashMap<String, usize>,
+}
+
+impl Round {
+    fn tally(&self) -> Vec<usize> {
+        let mut tally = vec![0; self.proposals.len()];
+        for proposal in self.votes.values() {
+            tally[*proposal] += 1;
+        }
+
+        tally
+    }
+
+    /// The proposal with the most votes, the earliest of them on a tie.
+    fn winner(&self) -> &Proposal {
+        let tally = self.tally();
+        let best = tally
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, votes)| (**votes, std::cmp::Reverse(*i)))
+            .map_or(0, |(i, _)| i);
+
+        &self.proposals[best]
+    }
+
+    fn view(&self) -> VotingRound {
+        let tally = self.tally();
+        VotingRound {
+            deadline: self.deadline,
+            proposals: self
+                .proposals
+                .iter()
+                .zip(tally)
+                .enumerate()
+                .map(|(id, (proposal, votes))| ProposalView {
+                    id,
+                    base_color: proposal.source.to_hex(),
+                    theme_type: proposal.theme_type,
+                    votes,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Open voting rounds by channel, one per channel at most. Like channels, they only live in
+/// this instance's memory.
+#[derive(Clone)]
+pub struct Votes {
+    rounds: Arc<Mutex<HashMap<String, Round>>>,
+    proposals: RateLimiter,
+}
+
+impl Default for Votes {
+    fn default() -> Self {
+        Self {
+            rounds: Default::default(),
+            proposals: RateLimiter::new(PROPOSALS_PER_MINUTE, Duration::from_secs(60)),
+        }
+    }
+}
+
+impl Votes {
+    /// How many proposals clients made lately.
+    pub fn rate_limits(&self) -> &RateLimiter {
+        &self.proposals
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewProposal {
+    #[serde(flatten)]
+    seed: NewSeed,
+    /// How long voting stays open, only for the proposal that starts a round. Defaults to 2
+    /// minutes, at most an hour.
+    voting_secs: Option<u64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct Ballot {
+    /// Anything that tells voters apart, like their user ID
+    #[schema(example = "U-someone")]
+    voter: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VotingRound {
+    /// When the proposal with the most votes gets applied
+    deadline: DateTime<Utc>,
+    proposals: Vec<ProposalView>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProposalView {
+    id: usize,
+    base_color: String,
+    theme_type: ThemeType,
+    votes: usize,
+}
+
+/// Voting, which goes in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/channels/:channel/proposals", get(get_round).post(propose))
+        .route("/channels/:channel/proposals/:id/vote", post(vote))
+}
+
+fn no_round() -> Response {
+    (StatusCode::NOT_FOUND, "There's no vote on this channel").into_response()
+}
+
+fn too_many_rounds() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "There are too many votes going on right now, try again later",
+    )
+        .into_response()
+}
+
+/// Proposes a theme for the channel. The first proposal starts a round (which takes the
+/// channel's token if it's claimed), and everyone can propose and vote until its deadline.
+/// Then the proposal with the most votes becomes the channel's theme.
+#[utoipa::path(
+    post,
+    path = "/v1/channels/{channel}/proposals",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel")),
+    request_body = NewProposal,
+    responses(
+        (status = 201, description = "Proposed, this is the round so far", body = VotingRound),
+        (status = 400, description = "Invalid channel name or base color, or too many proposals", body = String),
+        (status = 403, description = "Starting a round on a claimed channel needs its token", body = String),
+        (status = 429, description = "Too many proposals or new channels from this client, see `Retry-After`", body = String),
+        (status = 503, description = "There are too many votes going on to start another one", body = String)
+    )
+)]
+pub async fn propose(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    ip: ClientIp,
+    WriteToken(token): WriteToken,
+    Json(proposal): Json<NewProposal>,
+) -> Response {
+    if !channels::valid_channel(&channel) {
+        return channels::invalid_channel();
+    }
+    if let Err(retry_after) = state.votes.proposals.check(&ip) {
+        return rate_limit::too_many_requests(retry_after, "Too many proposals, try again later");
+    }
+    let Ok(source) = input::parse_color(&proposal.seed.base_color) else {
+        return (StatusCode::BAD_REQUEST, "Invalid base_color").into_response();
+    };
+    let proposal_seed = Proposal {
+        source,
+        theme_type: proposal.seed.theme_type,
+    };
+
+    let mut rounds = state.votes.rounds.lock().unwrap();
+    let round = match rounds.get_mut(&channel) {
+        Some(round) => round,
+        None => {
+            if !state.channels.may_write(&channel, token.as_deref()) {
+                return channels::not_allowed();
+            }
+            // The winner makes the channel if it isn't there yet
+            if let Err(retry_after) = state.channels.admit(&channel, &ip) {
+                return channels::too_many_new_channels(retry_after);
+            }
+            if rounds.len() >= MAX_CHANNELS {
+                return too_many_rounds();
+            }
+
+            let secs = proposal
+                .voting_secs
+                .unwrap_or(DEFAULT_VOTING_SECS)
+                .min(MAX_VOTING_SECS);
+            let voting_time = Duration::from_secs(secs);
+            close_after(&state, &channel, voting_time);
+            rounds.entry(channel.clone()).or_insert(Round {
+                deadline: Utc::now() + voting_time,
+                proposals: Vec::new(),
+                votes: HashMap::new(),
+            })
+        }
+    };
+
+    // The same seed twice is still one proposal
+    let duplicate = round.proposals.iter().any(|existing| {
+        existing.source == proposal_seed.source && existing.theme_type == proposal_seed.theme_type
+    });
+    if !duplicate {
+        if round.proposals.len() >= MAX_PROPOSALS {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("A round can have at most {} proposals", MAX_PROPOSALS),
+            )
+                .into_response();
+        }
+        round.proposals.push(proposal_seed);
+    }
+
+    (StatusCode::CREATED, Json(round.view())).into_response()
+}
+
+/// The channel's proposals and their votes so far.
+#[utoipa::path(
+    get,
+    path = "/v1/channels/{channel}/proposals",
+    params(("channel" = String, Path, description = "Any name, shared by everyone on the channel")),
+    responses(
+        (status = 200, description = "The open round", body = VotingRound),
+        (status = 400, description = "Invalid channel name", body = String),
+        (status = 404, description = "No vote going on", body = String)
+    )
+)]
+pub async fn get_round(State(state): State<AppState>, Path(channel): Path<String>) -> Response {
+    if !channels::valid_channel(&channel) {
+        return channels::invalid_channel();
+    }
+
+    let rounds = state.votes.rounds.lock().unwrap();
+    match rounds.get(&channel) {
+        Some(round) => Json(round.view()).into_response(),
+        None => no_round(),
+    }
+}
+
+/// Votes for a proposal. Each voter gets one vote per round, voting again moves it.
+#[utoipa::path(
+    post,
+    path = "/v1/channels/{channel}/proposals/{id}/vote",
+    params(
+        ("channel" = String, Path, description = "Any name, shared by everyone on the channel"),
+        ("id" = usize, Path, description = "From the round's proposals")
+    ),
+    request_body = Ballot,
+    responses(
+        (status = 200, description = "Counted, this is the round so far", body = VotingRound),
+        (status = 400, description = "Invalid channel name or voter, or the round is full", body = String),
+        (status = 404, description = "No vote going on, or no such proposal", body = String)
+    )
+)]
+pub async fn vote(
+    State(state): State<AppState>,
+    Path((channel, id)): Path<(String, usize)>,
+    Json(ballot): Json<Ballot>,
+) -> Response {
+    if !channels::valid_channel(&channel) {
+        return channels::invalid_channel();
+    }
+    if !(1..=MAX_VOTER_LENGTH).contains(&ballot.voter.len()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("voter has to be 1 to {} bytes", MAX_VOTER_LENGTH),
+        )
+            .into_response();
+    }
+
+    let mut rounds = state.votes.rounds.lock().unwrap();
+    let Some(round) = rounds.get_mut(&channel) else {
+        return no_round();
+    };
+    if id >= round.proposals.len() {
+        return (StatusCode::NOT_FOUND, "No such proposal").into_response();
+    }
+
+    if round.votes.len() >= MAX_VOTERS && !round.votes.contains_key(&ballot.voter) {
+        return (
+            StatusCode::BAD_REQUEST,
+            "This round has all the voters it can take",
+        )
+            .into_response();
+    }
+
+    round.votes.insert(ballot.voter, id);
+    metrics::counter!("theme_votes_total").increment(1);
+
+    Json(round.view()).into_response()
+}
+
+/// Applies the round's winner once voting is over.
+fn close_after(state: &AppState, channel: &str, voting_time: Duration) {
+    let state = state.clone();
+    let channel = channel.to_owned();
+    tokio::spawn(async move {
+        tokio::time::sleep(voting_time).await;
+
+        let Some(round) = state.votes.rounds.lock().unwrap().remove(&channel) else {
+            return;
+        };
+        let winner = round.winner();
+        let votes = round.votes.len();
+
+        match cache::palette(&state, winner.source, winner.theme_type).await {
+            Ok(palette) => {
+                let published = state.channels.publish(
+                    &channel,
+                    winner.source.to_hex(),
+                    winner.theme_type,
+                    palette,
+                );
+                if published.is_err() {
+                    warn!("Couldn't apply the vote on {}: too many channels", channel);
+                    return;
+                }
+                info!(
+                    "{} voted for {} ({} votes in total)",
+                    channel,
+                    winner.source.to_hex(),
+                    votes
+                );
+            }
+            Err(err) => warn!("Couldn't apply the vote on {}: {}", channel, err),
+        }
+    });
+}
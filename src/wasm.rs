@@ -0,0 +1,41 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    formats::{Format, FormatOptions},
+    input,
+    palette::{self, Palette, ThemeType},
+};
+
+/// A palette exactly like `GET /v1/getPalette` returns it with the built-in custom colors,
+/// for previewing themes without a round trip to the server. Takes the same values as its
+/// parameters: `theme_type` is `Dark` or `Light`, `format` one of `raw`, `json`, `dynvar`,
+/// `lines`, `annotated` and `android-xml`.
+#[wasm_bindgen(js_name = generatePalette)]
+pub fn generate_palette(
+    base_color: &str,
+    theme_type: &str,
+    format: &str,
+    dynvar_space: Option<String>,
+    names: Option<bool>,
+) -> Result<String, JsError> {
+    let render = || -> anyhow::Result<String> {
+        let source = input::parse_color(base_color)?;
+        let theme_type: ThemeType = theme_type.parse()?;
+        let format: Format = format.parse()?;
+        let custom_colors = palette::default_custom_colors();
+        let options = FormatOptions {
+            dynvar_space,
+            names: names.unwrap_or_default(),
+            ..Default::default()
+        };
+        if format.both_themes() {
+            let (dark, light) = Palette::generate_both(source, &custom_colors)?;
+            return Ok(format.render_both(&dark, &light, &options));
+        }
+
+        let palette = Palette::generate(source, theme_type, &custom_colors)?;
+        Ok(format.render(&palette, &options))
+    };
+
+    render().map_err(|err| JsError::new(&err.to_string()))
+}
@@ -0,0 +1,303 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::Context;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::put,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{redirect, Url};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    auth::ApiKey,
+    palette::ThemeType,
+    storage::{Storage, Webhook},
+    AppError, AppState,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewWebhook {
+    /// Gets a POST for every palette generated or stored with this API key
+    #[schema(example = "https://example.com/hooks/palettes")]
+    url: String,
+}
+
+/// What gets POSTed to webhooks. The body is signed with the webhook's secret, as
+/// `X-Webhook-Signature: sha256=<hex HMAC-SHA256>`.
+#[derive(Serialize, ToSchema)]
+pub struct WebhookPayload {
+    /// `palette.generated` or `palette.stored`
+    pub event: &'static str,
+    /// Name of the API key
+    pub api_key: String,
+    pub base_color: String,
+    pub theme_type: ThemeType,
+    pub palette: String,
+    /// ID of the stored palette, for `palette.stored`
+    pub stored_palette_id: Option<Uuid>,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// The caller's webhook, which goes in the v1 router.
+pub fn router() -> Router<AppState> {
+    Router::new().route(
+        "/me/webhook",
+        put(set_webhook).get(get_webhook).delete(delete_webhook),
+    )
+}
+
+/// Sets the URL that gets notified about this API key's palettes. The secret for checking
+/// signatures stays the same when the URL changes.
+#[utoipa::path(
+    put,
+    path = "/v1/me/webhook",
+    request_body = NewWebhook,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The webhook", body = Webhook),
+        (status = 400, description = "Not an http(s) URL, or its host isn't on the public internet", body = String),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn set_webhook(
+    key: ApiKey,
+    storage: Storage,
+    Json(new): Json<NewWebhook>,
+) -> Result<Response, AppError> {
+    let url = Url::parse(&new.url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+    let Some(url) = url else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            "The webhook has to be an http(s) URL",
+        )
+            .into_response());
+    };
+    if let Err(err) = public_addrs(&url).await {
+        return Ok((StatusCode::BAD_REQUEST, format!("{:#}", err)).into_response());
+    }
+
+    let secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let webhook = storage.set_webhook(&key.name, &new.url, &secret).await?;
+
+    Ok(Json(webhook).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/me/webhook",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The webhook", body = Webhook),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 404, description = "There's no webhook", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn get_webhook(key: ApiKey, storage: Storage) -> Result<Response, AppError> {
+    Ok(match storage.webhook(&key.name).await? {
+        Some(webhook) => Json(webhook).into_response(),
+        None => (StatusCode::NOT_FOUND, "There's no webhook for this API key").into_response(),
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/me/webhook",
+    security(("api_key" = [])),
+    responses(
+        (status = 204, description = "Removed (or there wasn't one)"),
+        (status = 401, description = "Missing or unknown API key", body = String),
+        (status = 503, description = "This instance has no database", body = String)
+    )
+)]
+pub async fn delete_webhook(key: ApiKey, storage: Storage) -> Result<StatusCode, AppError> {
+    storage.delete_webhook(&key.name).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sends the payload to the key's webhook, if it has one, in the background. Deliveries are
+/// tried once; a receiver that's down just misses the event.
+pub fn notify(state: &AppState, payload: WebhookPayload) {
+    let Some(storage) = state.storage.clone() else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let webhook = match storage.webhook(&payload.api_key).await {
+            Ok(Some(webhook)) => webhook,
+            Ok(None) => return,
+            Err(err) => {
+                warn!(
+                    "Couldn't look up the webhook for {:?}: {}",
+                    payload.api_key, err
+                );
+                return;
+            }
+        };
+
+        let result = deliver(&webhook, &payload).await;
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        metrics::counter!("webhook_deliveries_total", "result" => outcome).increment(1);
+
+        match result {
+            Ok(()) => debug!("Delivered {} to {}", payload.event, webhook.url),
+            Err(err) => warn!(
+                "Couldn't deliver {} to {}: {}",
+                payload.event, webhook.url, err
+            ),
+        }
+    });
+}
+
+async fn deliver(webhook: &Webhook, payload: &WebhookPayload) -> anyhow::Result<()> {
+    let url = Url::parse(&webhook.url)?;
+    // Checked again because DNS can change after the webhook was set. The client only
+    // connects to the addresses that were checked, and doesn't follow redirects anywhere else
+    let addrs = public_addrs(&url).await?;
+    let mut client = reqwest::Client::builder()
+        .redirect(redirect::Policy::none())
+        .connect_timeout(TIMEOUT)
+        .timeout(TIMEOUT);
+    if let Some(domain) = url.domain() {
+        client = client.resolve_to_addrs(domain, &addrs);
+    }
+    let client = client.build()?;
+
+    let body = serde_json::to_vec(payload)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(webhook.secret.as_bytes())?;
+    mac.update(&body);
+    let signature: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Webhook-Signature", format!("sha256={}", signature))
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// The addresses `url`'s host resolves to, if every one of them is on the public internet. Keeps
+/// API keys from pointing webhooks at the server itself, its network or a cloud metadata service.
+async fn public_addrs(url: &Url) -> anyhow::Result<Vec<SocketAddr>> {
+    let port = url
+        .port_or_known_default()
+        .context("The webhook has no port")?;
+    let addrs: Vec<SocketAddr> = match url.domain() {
+        Some(domain) => tokio::net::lookup_host((domain, port))
+            .await
+            .with_context(|| format!("Couldn't resolve {}", domain))?
+            .collect(),
+        None => {
+            // IPv6 hosts come in brackets
+            let host = url.host_str().context("The webhook has no host")?;
+            let ip: IpAddr = host.trim_matches(['[', ']']).parse()?;
+            vec![SocketAddr::new(ip, port)]
+        }
+    };
+
+    anyhow::ensure!(!addrs.is_empty(), "The webhook's host has no addresses");
+    if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        anyhow::bail!("The webhook's host is {}, which isn't public", addr.ip());
+    }
+
+    Ok(addrs)
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_v4(ip);
+            }
+            let segments = ip.segments();
+            // NAT64 addresses are IPv4 addresses underneath
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_v4(Ipv4Addr::new(a, b, c, d));
+            }
+
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, like fd00:ec2::254, AWS's metadata service
+                || segments[0] & 0xfe00 == 0xfc00
+                // Link-local
+                || segments[0] & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        // Includes 169.254.169.254, the metadata service of most clouds
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT, and Alibaba's metadata service at 100.100.100.200
+        || (a == 100 && b & 0xc0 == 64)
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && ip.octets()[2] == 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_are_public() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.100.100.200",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00:ec2::254",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Worlds split the raw palette string by position, so the order and spelling of every color
+//! is part of the API. These run requests through the whole router and compare the bodies to
+//! the snapshots in `src/snapshots`, which should only ever change on purpose (review them
+//! with `cargo insta review`).
+
+use std::sync::OnceLock;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    Router,
+};
+use tower::ServiceExt;
+
+use crate::{
+    app,
+    config::Config,
+    input,
+    palette::{self, Palette, ThemeType},
+    shutdown::Shutdown,
+};
+
+/// Seeds covering a typical color, the extremes and one with a lot of chroma.
+const SEEDS: [&str; 5] = ["59EB5C", "FF0000", "0000FF", "000000", "FFFFFF"];
+
+/// The router is built once, since logging and metrics can only be set up once per process.
+fn router() -> Router {
+    static ROUTER: OnceLock<Router> = OnceLock::new();
+    ROUTER
+        .get_or_init(|| app(Config::default(), None, None, Shutdown::default()))
+        .clone()
+}
+
+async fn get(uri: &str) -> (StatusCode, String, String) {
+    let response = router()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (
+        status,
+        content_type,
+        String::from_utf8(body.to_vec()).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn raw_palettes() {
+    for seed in SEEDS {
+        for theme_type in ["Dark", "Light"] {
+            let (status, content_type, body) = get(&format!(
+                "/v1/getPalette?base_color={}&theme_type={}",
+                seed, theme_type
+            ))
+            .await;
+
+            assert_eq!(status, StatusCode::OK);
+            assert!(content_type.starts_with("text/plain"), "{}", content_type);
+            insta::assert_snapshot!(format!("raw_{}_{}", seed, theme_type), body);
+        }
+    }
+}
+
+/// The CLI and the wasm module generate with the library, which is what the golden fixtures
+/// check, so the service has to come up with the same palettes.
+#[tokio::test]
+async fn the_service_generates_what_the_library_does() {
+    let custom_colors = palette::default_custom_colors();
+    for seed in SEEDS {
+        for theme_type in [ThemeType::Dark, ThemeType::Light] {
+            let (status, _, body) = get(&format!(
+                "/v1/getPalette?base_color={}&theme_type={}",
+                seed,
+                theme_type.as_str()
+            ))
+            .await;
+
+            let source = input::parse_color(seed).unwrap();
+            let expected = Palette::generate(source, theme_type, &custom_colors).unwrap();
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body, expected.to_wire_string(), "{} {:?}", seed, theme_type);
+        }
+    }
+}
+
+#[tokio::test]
+async fn spellings_of_a_color_get_the_same_palette() {
+    let (_, _, expected) = get("/v1/getPalette?base_color=FF0000&theme_type=Dark").await;
+    for seed in ["ff0000", "%23FF0000", "f00", "%23f00"] {
+        let (_, _, body) = get(&format!(
+            "/v1/getPalette?base_color={}&theme_type=Dark",
+            seed
+        ))
+        .await;
+        assert_eq!(body, expected, "{}", seed);
+    }
+}
+
+#[tokio::test]
+async fn other_formats() {
+    let uris = [
+        ("json", "format=json"),
+        ("dynvar", "format=dynvar"),
+        ("dynvar_space", "format=dynvar&dynvar_space=Ui"),
+        ("lines", "format=lines"),
+        ("lines_names", "format=lines&names=true"),
+        ("annotated", "format=annotated"),
+        ("color_names", "format=json&color_names=true"),
+        ("color_names_ja", "format=json&color_names=true&lang=ja"),
+        ("android_xml", "format=android-xml"),
+        ("fields", "fields=primary,onPrimary,surface,onSurface"),
+        ("json_fields", "format=json&fields=primary,onPrimary"),
+        ("no_customs", "include_customs=false"),
+        ("only_customs", "only=customs"),
+    ];
+    for (name, query) in uris {
+        let (status, _, body) = get(&format!(
+            "/v1/getPalette?base_color=59EB5C&theme_type=Dark&{}",
+            query
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        insta::assert_snapshot!(name, body);
+    }
+}
+
+/// Languages there are no names in get English, and the response says so.
+#[tokio::test]
+async fn unknown_langs_fall_back_to_english() {
+    let (_, _, english) =
+        get("/v1/getPalette?base_color=59EB5C&theme_type=Dark&format=json&color_names=true").await;
+    for (lang, expected) in [("fr", "en"), ("de-AT", "de"), ("JA", "ja")] {
+        let uri = format!(
+            "/v1/getPalette?base_color=59EB5C&theme_type=Dark&format=json&color_names=true&lang={}",
+            lang
+        );
+        let response = router()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK, "{}", lang);
+        assert_eq!(response.headers()[header::CONTENT_LANGUAGE], expected);
+        if expected == "en" {
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(body, english.as_bytes(), "{}", lang);
+        }
+    }
+
+    let (status, _, _) = get("/v1/nameColor?color=FF7F50&lang=fr").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+/// `colors.xml` has both themes, so a batch has to write the same file `getPalette` does.
+#[tokio::test]
+async fn batches_write_android_xml_with_both_themes() {
+    let (_, _, expected) =
+        get("/v1/getPalette?base_color=59EB5C&theme_type=Dark&format=android-xml").await;
+
+    let batch =
+        r#"{"format":"android-xml","seeds":[{"base_color":"59EB5C","theme_type":"Light"}]}"#;
+    let response = router()
+        .oneshot(
+            Request::post("/v1/getPalettes")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(batch))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let items: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(items[0]["palette"], expected.as_str());
+}
+
+#[tokio::test]
+async fn unversioned_path_is_the_same_as_v1() {
+    let (_, _, v1) = get("/v1/getPalette?base_color=59EB5C&theme_type=Light").await;
+    let (status, _, unversioned) = get("/getPalette?base_color=59EB5C&theme_type=Light").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(unversioned, v1);
+}
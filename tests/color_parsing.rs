@@ -0,0 +1,69 @@
+use material_colors::color::Argb;
+use proptest::prelude::*;
+use resomaterialyou::{
+    input,
+    seeds::{SeedSource, TextHash},
+};
+
+fn any_color() -> impl Strategy<Value = Argb> {
+    any::<[u8; 3]>().prop_map(|[red, green, blue]| Argb::new(255, red, green, blue))
+}
+
+/// What `base_color` is documented to take.
+fn is_hex_color(s: &str) -> bool {
+    let digits = s.strip_prefix('#').unwrap_or(s);
+    matches!(digits.len(), 3 | 6) && digits.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+proptest! {
+    #[test]
+    fn hex_round_trips(color in any_color()) {
+        let hex = color.to_hex();
+        prop_assert_eq!(input::parse_color(&hex).unwrap(), color);
+        prop_assert_eq!(input::parse_color(&format!("#{}", hex)).unwrap(), color);
+        prop_assert_eq!(input::parse_color(&hex.to_uppercase()).unwrap(), color);
+    }
+
+    #[test]
+    fn parsed_colors_are_stable(s in "#?([0-9a-fA-F]{3}|[0-9a-fA-F]{6})") {
+        let color = input::parse_color(&s).unwrap();
+        prop_assert_eq!(input::parse_color(&color.to_hex()).unwrap(), color);
+    }
+
+    #[test]
+    fn short_hex_doubles_every_digit(digits in "[0-9a-fA-F]{3}") {
+        let long: String = digits.chars().flat_map(|c| [c, c]).collect();
+        prop_assert_eq!(
+            input::parse_color(&digits).unwrap(),
+            input::parse_color(&long).unwrap()
+        );
+    }
+
+    #[test]
+    fn any_string_is_parsed_or_rejected(s in any::<String>()) {
+        prop_assert_eq!(input::parse_color(&s).is_ok(), is_hex_color(&s), "{:?}", s);
+    }
+
+    #[test]
+    fn almost_hex_is_rejected(s in "#{0,2}[0-9a-fA-F#xg ]{0,8}") {
+        prop_assert_eq!(input::parse_color(&s).is_ok(), is_hex_color(&s), "{:?}", s);
+    }
+
+    #[test]
+    fn any_text_gets_the_same_seed(s in any::<String>()) {
+        prop_assert_eq!(TextHash(&s).seed().unwrap(), TextHash(&s).seed().unwrap());
+    }
+}
+
+#[test]
+fn other_color_syntaxes_are_rejected() {
+    for input in [
+        "rgb(255, 0, 0)",
+        "hsl(0, 100%, 50%)",
+        "red",
+        "transparent",
+        "0xff0000",
+    ] {
+        assert!(input::parse_color(input).is_err(), "{:?}", input);
+    }
+}
@@ -0,0 +1,192 @@
+//! The palettes of a set of seeds as they were when `SCHEMA_VERSION` was last bumped, in
+//! `tests/golden/v{SCHEMA_VERSION}`. Deployed worlds hardcode these, so a dependency update
+//! that changes any value fails here until the change is made on purpose: bump
+//! `SCHEMA_VERSION` and write the fixtures of the new version with
+//!
+//! ```sh
+//! UPDATE_GOLDEN=1 cargo test --test golden
+//! ```
+//!
+//! Fixtures of a version that already exists never get overwritten.
+
+use std::{fmt, fs, path::PathBuf};
+
+use serde::{
+    de::{IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use resomaterialyou::{
+    formats::{dynvar_name, Format, FormatOptions},
+    input,
+    palette::{self, Palette, ThemeType, SCHEMA_VERSION},
+};
+
+/// A typical seed, primaries, greys and colors close to the edges of the gamut.
+const SEEDS: [&str; 12] = [
+    "59EB5C", "FF0000", "00FF00", "0000FF", "000000", "FFFFFF", "808080", "FF8FD8", "E69E50",
+    "61D1FA", "123456", "FEDCBA",
+];
+const THEME_TYPES: [ThemeType; 2] = [ThemeType::Dark, ThemeType::Light];
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("v{}", SCHEMA_VERSION))
+}
+
+fn fixture_name(seed: &str, theme_type: ThemeType) -> String {
+    format!("{}_{}.txt", seed, theme_type.as_str().to_lowercase())
+}
+
+fn generate(seed: &str, theme_type: ThemeType) -> Palette {
+    let source = input::parse_color(seed).unwrap();
+    Palette::generate(source, theme_type, &palette::default_custom_colors()).unwrap()
+}
+
+/// One `name=hex` per line, so a change shows up as a readable diff.
+fn render(seed: &str, theme_type: ThemeType) -> String {
+    let palette = generate(seed, theme_type);
+    let options = FormatOptions {
+        names: true,
+        ..Default::default()
+    };
+
+    Format::Lines.render(&palette, &options)
+}
+
+#[test]
+fn palettes_match_the_golden_fixtures() {
+    let dir = fixtures();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        assert!(
+            !dir.exists(),
+            "{} already exists, bump SCHEMA_VERSION instead of changing its fixtures",
+            dir.display()
+        );
+        fs::create_dir_all(&dir).unwrap();
+        for seed in SEEDS {
+            for theme_type in THEME_TYPES {
+                fs::write(
+                    dir.join(fixture_name(seed, theme_type)),
+                    render(seed, theme_type),
+                )
+                .unwrap();
+            }
+        }
+        return;
+    }
+
+    assert!(
+        dir.exists(),
+        "There are no golden fixtures for SCHEMA_VERSION {}, write them with UPDATE_GOLDEN=1",
+        SCHEMA_VERSION
+    );
+    let mut changed = Vec::new();
+    for seed in SEEDS {
+        for theme_type in THEME_TYPES {
+            let name = fixture_name(seed, theme_type);
+            let expected = fs::read_to_string(dir.join(&name))
+                .unwrap_or_else(|err| panic!("Couldn't read {}: {}", name, err));
+            if render(seed, theme_type) != expected {
+                changed.push(name);
+            }
+        }
+    }
+
+    assert!(
+        changed.is_empty(),
+        "Generated palettes changed from SCHEMA_VERSION {} in {:?}. If that's on purpose, \
+         bump SCHEMA_VERSION and write the new fixtures with UPDATE_GOLDEN=1",
+        SCHEMA_VERSION,
+        changed
+    );
+}
+
+/// The names in a fixture, in order.
+fn fixture_names(seed: &str, theme_type: ThemeType) -> Vec<String> {
+    fs::read_to_string(fixtures().join(fixture_name(seed, theme_type)))
+        .unwrap()
+        .lines()
+        .map(|line| line.split_once('=').unwrap().0.to_string())
+        .collect()
+}
+
+/// The keys of a JSON object in the order they're written, which maps don't keep.
+struct Keys(Vec<String>);
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> Visitor<'de> for KeysVisitor {
+            type Value = Keys;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Keys, A::Error> {
+                let mut keys = Vec::new();
+                while let Some((key, IgnoredAny)) = map.next_entry::<String, IgnoredAny>()? {
+                    keys.push(key);
+                }
+                Ok(Keys(keys))
+            }
+        }
+
+        deserializer.deserialize_map(KeysVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonPalette {
+    schema_version: u32,
+    colors: Keys,
+}
+
+#[test]
+fn every_format_keeps_the_order_of_the_fixtures() {
+    let options = FormatOptions::default();
+    for seed in SEEDS {
+        for theme_type in THEME_TYPES {
+            let names = fixture_names(seed, theme_type);
+            let palette = generate(seed, theme_type);
+
+            let json: JsonPalette =
+                serde_json::from_str(&Format::Json.render(&palette, &options)).unwrap();
+            assert_eq!(json.schema_version, SCHEMA_VERSION);
+            assert_eq!(json.colors.0, names);
+
+            let annotated: Vec<String> = Format::Annotated
+                .render(&palette, &options)
+                .split(' ')
+                .map(|color| color.split_once(':').unwrap().0.to_lowercase())
+                .collect();
+            let expected: Vec<String> = names.iter().map(|name| name.replace('_', "")).collect();
+            assert_eq!(annotated, expected);
+
+            let dynvars: Vec<String> = Format::Dynvar
+                .render(&palette, &options)
+                .lines()
+                .map(|line| line.split(['/', '|']).nth(1).unwrap().to_string())
+                .collect();
+            let expected: Vec<String> = names.iter().map(|name| dynvar_name(name)).collect();
+            assert_eq!(dynvars, expected);
+
+            let raw = Format::Raw.render(&palette, &options);
+            assert_eq!(raw.len(), names.len() * 6);
+        }
+    }
+}
+
+#[test]
+fn color_names_are_the_names_palettes_have() {
+    let custom_colors = palette::default_custom_colors();
+    let names = palette::color_names(&custom_colors);
+    for seed in SEEDS {
+        for theme_type in THEME_TYPES {
+            assert_eq!(generate(seed, theme_type).names(), names);
+        }
+    }
+}
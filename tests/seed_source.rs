@@ -0,0 +1,100 @@
+use material_colors::{color::Argb, hct::Hct};
+use resomaterialyou::seeds::{Hex, Pixels, Random, SeedSource, TextHash};
+
+#[test]
+fn hex_takes_every_spelling_of_a_color() {
+    let red = Argb::new(255, 255, 0, 0);
+    for spelling in ["ff0000", "FF0000", "#ff0000", "f00", "#F00"] {
+        assert_eq!(Hex(spelling).seed().unwrap(), red, "{}", spelling);
+    }
+    assert!(Hex("59EB5C").deterministic());
+}
+
+#[test]
+fn hex_rejects_what_isnt_a_color() {
+    for input in ["", "ff00", "ff00000", "red", "#gggggg", "ff 000"] {
+        assert!(Hex(input).seed().is_err(), "{:?}", input);
+    }
+}
+
+#[test]
+fn text_always_gets_the_same_seed() {
+    let seed = TextHash("RubberDuckShobe").seed().unwrap();
+    assert_eq!(TextHash("RubberDuckShobe").seed().unwrap(), seed);
+    assert_ne!(TextHash("rubberduckshobe").seed().unwrap(), seed);
+    assert!(TextHash("RubberDuckShobe").deterministic());
+}
+
+#[test]
+fn text_seeds_are_colorful() {
+    for text in ["", "a", "Resonite", "U-someone", "🦆"] {
+        let seed = Hct::new(TextHash(text).seed().unwrap());
+        assert!(seed.get_chroma() > 30.0, "{:?} is too gray", text);
+        assert_eq!(Argb::from(seed).alpha, 255);
+    }
+}
+
+#[test]
+fn random_seeds_differ() {
+    assert!(!Random.deterministic());
+
+    let seeds: Vec<Argb> = (0..16).map(|_| Random.seed().unwrap()).collect();
+    assert!(seeds.iter().any(|seed| *seed != seeds[0]));
+    for seed in seeds {
+        assert!(Hct::new(seed).get_chroma() > 30.0);
+    }
+}
+
+/// The quantizer needs more stack than test threads get, like it does on the server.
+fn on_big_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn pixels_of_one_color_seed_that_color() {
+    let blue = Argb::new(255, 0x42, 0x85, 0xf4);
+    let (seed, seeds) = on_big_stack(move || {
+        let pixels = vec![blue; 1024];
+        let source = Pixels {
+            pixels: &pixels,
+            iterations: 5,
+        };
+        (source.seed().unwrap(), source.seeds(4))
+    });
+
+    assert_eq!(seed, blue);
+    assert_eq!(seeds, vec![blue]);
+}
+
+#[test]
+fn pixels_rank_the_dominant_color_first() {
+    let green = Argb::new(255, 0x34, 0xa8, 0x53);
+    let red = Argb::new(255, 0xea, 0x43, 0x35);
+    let seeds = on_big_stack(move || {
+        let mut pixels = vec![green; 900];
+        pixels.extend(vec![red; 100]);
+        Pixels {
+            pixels: &pixels,
+            iterations: 5,
+        }
+        .seeds(2)
+    });
+
+    assert_eq!(seeds, vec![green, red]);
+}
+
+#[test]
+fn no_pixels_no_seed() {
+    let source = Pixels {
+        pixels: &[],
+        iterations: 5,
+    };
+
+    assert!(source.seeds(4).is_empty());
+    assert!(source.seed().is_err());
+}